@@ -0,0 +1,211 @@
+use crate::model::vec3::Vec3;
+
+use super::perspective::PerspectiveCamera;
+
+use Vec3 as Point3;
+
+/// Camera placement at a moment of an animation, `time` being in seconds.
+#[derive(Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vfov: f64,
+    pub focus_dist: f64,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, lookfrom: Point3, lookat: Point3, vfov: f64, focus_dist: f64) -> Self {
+        Self {
+            time,
+            lookfrom,
+            lookat,
+            vfov,
+            focus_dist,
+        }
+    }
+}
+
+/// Smooth camera move through keyframes, interpolated with a Catmull-Rom spline that
+/// passes through every one of them. Before the first and after the last keyframe
+/// the camera holds still. `aperture` is the lens diameter throughout.
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+    vup: Vec3,
+    aperture: f64,
+}
+
+impl CameraPath {
+    pub fn new(mut keyframes: Vec<Keyframe>, aperture: f64) -> Self {
+        assert!(!keyframes.is_empty(), "a camera path needs a keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Self {
+            keyframes,
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            aperture,
+        }
+    }
+
+    /// The keyframe values at `time`.
+    pub fn at(&self, time: f64) -> Keyframe {
+        let keys = &self.keyframes;
+        let last = keys.len() - 1;
+        if time <= keys[0].time {
+            return Keyframe { time, ..keys[0] };
+        }
+        if time >= keys[last].time {
+            return Keyframe { time, ..keys[last] };
+        }
+
+        let i = keys.partition_point(|k| k.time <= time) - 1;
+        let (k0, k1) = (&keys[i], &keys[i + 1]);
+        let dt = k1.time - k0.time;
+        let t = (time - k0.time) / dt;
+
+        // Tangents from the neighbouring keys, scaled to this segment so that uneven
+        // spacing in time does not make the camera jerk at the keys.
+        let before = &keys[i.saturating_sub(1)];
+        let after = &keys[(i + 2).min(last)];
+        let tangent = |a: &Keyframe, b: &Keyframe| dt / (b.time - a.time);
+        let m0 = tangent(before, k1);
+        let m1 = tangent(k0, after);
+
+        let hermite = |p0: f64, p1: f64, prev: f64, next: f64| {
+            let (t2, t3) = (t * t, t * t * t);
+            (2.0 * t3 - 3.0 * t2 + 1.0) * p0
+                + (t3 - 2.0 * t2 + t) * m0 * (p1 - prev)
+                + (-2.0 * t3 + 3.0 * t2) * p1
+                + (t3 - t2) * m1 * (next - p0)
+        };
+        let hermite_vec = |p0: &Vec3, p1: &Vec3, prev: &Vec3, next: &Vec3| {
+            Vec3::new(
+                hermite(p0.x(), p1.x(), prev.x(), next.x()),
+                hermite(p0.y(), p1.y(), prev.y(), next.y()),
+                hermite(p0.z(), p1.z(), prev.z(), next.z()),
+            )
+        };
+
+        Keyframe {
+            time,
+            lookfrom: hermite_vec(
+                &k0.lookfrom,
+                &k1.lookfrom,
+                &before.lookfrom,
+                &after.lookfrom,
+            ),
+            lookat: hermite_vec(&k0.lookat, &k1.lookat, &before.lookat, &after.lookat),
+            vfov: hermite(k0.vfov, k1.vfov, before.vfov, after.vfov),
+            focus_dist: hermite(
+                k0.focus_dist,
+                k1.focus_dist,
+                before.focus_dist,
+                after.focus_dist,
+            ),
+        }
+    }
+
+    /// Camera for a shutter open from `time0` to `time1`, placed where the path is
+    /// halfway through the exposure.
+    pub fn camera(&self, time0: f64, time1: f64, aspect_ratio: f64) -> PerspectiveCamera {
+        let key = self.at((time0 + time1) / 2.0);
+        PerspectiveCamera::new(
+            &key.lookfrom,
+            &key.lookat,
+            &self.vup,
+            key.vfov,
+            aspect_ratio,
+        )
+        .with_lens(self.aperture, key.focus_dist)
+        .with_shutter(time0, time1)
+    }
+}
+
+/// Range of numbered frames to render, both ends included. Frame 1 opens its shutter
+/// at time 0 and each frame stays open for `shutter_angle` degrees of the 360 that
+/// make up one frame interval, as on a film camera.
+pub struct FrameRange {
+    pub first: usize,
+    pub last: usize,
+    pub fps: f64,
+    pub shutter_angle: f64,
+}
+
+impl FrameRange {
+    pub fn new(first: usize, last: usize, fps: f64) -> Self {
+        Self {
+            first: first.max(1),
+            last,
+            fps,
+            shutter_angle: 180.0,
+        }
+    }
+
+    pub fn with_shutter_angle(mut self, degrees: f64) -> Self {
+        self.shutter_angle = degrees.clamp(0.0, 360.0);
+        self
+    }
+
+    /// Times at which the shutter of `frame` opens and closes.
+    pub fn shutter(&self, frame: usize) -> (f64, f64) {
+        let open = (frame - 1) as f64 / self.fps;
+        (open, open + self.shutter_angle / 360.0 / self.fps)
+    }
+
+    pub fn filename(frame: usize) -> String {
+        format!("frame_{:04}.png", frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_passes_through_keyframes() {
+        let path = CameraPath::new(
+            vec![
+                Keyframe::new(
+                    0.0,
+                    Point3::new(0.0, 0.0, 0.0),
+                    Point3::new(0.0, 0.0, -1.0),
+                    40.0,
+                    10.0,
+                ),
+                Keyframe::new(
+                    1.0,
+                    Point3::new(1.0, 0.0, 0.0),
+                    Point3::new(0.0, 0.0, -1.0),
+                    30.0,
+                    10.0,
+                ),
+                Keyframe::new(
+                    3.0,
+                    Point3::new(2.0, 1.0, 0.0),
+                    Point3::new(0.0, 0.0, -1.0),
+                    20.0,
+                    5.0,
+                ),
+            ],
+            0.0,
+        );
+
+        let key = path.at(1.0);
+        assert!((key.lookfrom - Point3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        assert!((key.vfov - 30.0).abs() < 1e-12);
+
+        // Between keys the values stay continuous, and outside them the camera holds.
+        assert!((path.at(0.999).lookfrom - path.at(1.001).lookfrom).length() < 1e-2);
+        assert!((path.at(5.0).focus_dist - 5.0).abs() < 1e-12);
+        assert!((path.at(-1.0).vfov - 40.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_shutter_follows_frame_rate() {
+        let frames = FrameRange::new(1, 48, 24.0);
+        let (open, close) = frames.shutter(25);
+        assert!((open - 1.0).abs() < 1e-12);
+        assert!((close - open - 1.0 / 48.0).abs() < 1e-12);
+        assert_eq!(FrameRange::filename(7), "frame_0007.png");
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use crate::{
+    model::vec3::Vec3,
+    texture::texture::Texture,
+    util::{
+        distribution::Distribution2D,
+        rtweekend::{degrees_to_radians, PI},
+    },
+};
+
+/// Shape of the lens opening, which out of focus highlights take on as bokeh.
+pub trait Aperture {
+    /// Uniformly chosen point of the opening, scaled to fit the unit disk, for two
+    /// numbers in [0, 1).
+    fn sample(&self, u: (f64, f64)) -> (f64, f64);
+}
+
+/// Perfectly round opening of a lens without a diaphragm.
+pub struct CircularAperture;
+
+impl Aperture for CircularAperture {
+    fn sample(&self, u: (f64, f64)) -> (f64, f64) {
+        let p = Vec3::sample_unit_disk(u);
+        (p.x(), p.y())
+    }
+}
+
+/// Diaphragm made of `blades` straight blades, giving a regular polygon inscribed in
+/// the unit circle and turned by `rotation` degrees.
+pub struct PolygonAperture {
+    blades: i32,
+    rotation: f64,
+}
+
+impl PolygonAperture {
+    pub fn new(blades: i32, rotation: f64) -> Self {
+        Self {
+            blades: blades.max(3),
+            rotation: degrees_to_radians(rotation),
+        }
+    }
+
+    fn corner(&self, i: i32) -> (f64, f64) {
+        let angle = self.rotation + 2.0 * PI * i as f64 / self.blades as f64;
+        (angle.cos(), angle.sin())
+    }
+}
+
+impl Aperture for PolygonAperture {
+    fn sample(&self, (u, v): (f64, f64)) -> (f64, f64) {
+        // All the triangles fanning out from the centre have the same area, so the
+        // first number picks one and what is left of it places the point inside.
+        let scaled = u * self.blades as f64;
+        let i = (scaled as i32).min(self.blades - 1);
+        let (x0, y0) = self.corner(i);
+        let (x1, y1) = self.corner(i + 1);
+
+        let r = (scaled - i as f64).sqrt();
+        let (a, b) = (r * (1.0 - v), r * v);
+
+        (a * x0 + b * x1, a * y0 + b * y1)
+    }
+}
+
+/// Opening painted as a mask, sampled in proportion to its brightness so that grey
+/// areas let through part of the light. The mask spans the square around the unit
+/// disk and is read at `resolution` by `resolution` points.
+pub struct ImageAperture {
+    distribution: Distribution2D,
+}
+
+impl ImageAperture {
+    pub fn new(mask: Arc<dyn Texture + Sync + Send>, resolution: usize) -> Self {
+        let resolution = resolution.max(1);
+        let mut func = Vec::with_capacity(resolution * resolution);
+        for j in 0..resolution {
+            let v = (j as f64 + 0.5) / resolution as f64;
+            for i in 0..resolution {
+                let u = (i as f64 + 0.5) / resolution as f64;
+                let (x, y) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+
+                // Anything outside the unit disk would stick out of the lens.
+                let weight = if x * x + y * y <= 1.0 {
+                    mask.value(u, v, &Vec3::new(x, y, 0.0)).luminance().max(0.0)
+                } else {
+                    0.0
+                };
+                func.push(weight);
+            }
+        }
+
+        Self {
+            distribution: Distribution2D::new(&func, resolution, resolution),
+        }
+    }
+}
+
+impl Aperture for ImageAperture {
+    fn sample(&self, (u, v): (f64, f64)) -> (f64, f64) {
+        let (u, v, _) = self.distribution.sample_continuous(u, v);
+        (2.0 * u - 1.0, 2.0 * v - 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::rtweekend::random_double;
+
+    use super::*;
+
+    #[test]
+    fn test_polygon_samples_stay_inside_blades() {
+        // A square turned by 45 degrees has its edges at |x| = |y| = sqrt(0.5).
+        let aperture = PolygonAperture::new(4, 45.0);
+        let edge = 0.5_f64.sqrt() + 1e-9;
+        for _ in 0..1000 {
+            let (x, y) = aperture.sample((random_double(), random_double()));
+            assert!(x.abs() <= edge && y.abs() <= edge);
+        }
+    }
+
+    /// Open on the left half of the lens and closed on the right.
+    struct HalfMask;
+
+    impl Texture for HalfMask {
+        fn value(&self, u: f64, _v: f64, _p: &Vec3) -> Vec3 {
+            let open = if u < 0.5 { 1.0 } else { 0.0 };
+            Vec3::new(open, open, open)
+        }
+    }
+
+    #[test]
+    fn test_image_aperture_avoids_dark_areas() {
+        let aperture = ImageAperture::new(Arc::new(HalfMask), 32);
+        for _ in 0..1000 {
+            let (x, y) = aperture.sample((random_double(), random_double()));
+            assert!(x <= 0.0);
+            assert!(x * x + y * y <= 1.0 + 0.1);
+        }
+    }
+}
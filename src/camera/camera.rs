@@ -0,0 +1,90 @@
+use crate::{
+    model::{ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+};
+
+use Vec3 as Point3;
+
+/// Maps points on the film to primary rays.
+pub trait Camera {
+    /// Ray through the film at `(s, t)`, both in [0, 1] from the lower left corner, or
+    /// `None` where the projection covers no direction and the film stays black. The
+    /// ray leaves the lens and the shutter at the positions given by `sample`.
+    fn ray(&self, s: f64, t: f64, sample: &CameraSample) -> Option<Ray>;
+
+    /// Ray through the film at `(s, t)` carrying the rays `ds` and `dt` further along
+    /// the film, which leave the same point of the lens at the same instant, so that
+    /// they only tell how far apart neighbouring pixels look.
+    fn get_ray_differential(
+        &self,
+        s: f64,
+        t: f64,
+        ds: f64,
+        dt: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Option<Ray> {
+        let sample = CameraSample::new(sampler);
+        let ray = self.ray(s, t, &sample)?;
+        match (self.ray(s + ds, t, &sample), self.ray(s, t + dt, &sample)) {
+            (Some(rx), Some(ry)) => Some(ray.with_differential(&rx, &ry)),
+            _ => Some(ray),
+        }
+    }
+}
+
+/// Numbers a primary ray is drawn with besides its position on the film: the point of
+/// the lens it leaves from and the instant it does so, both in [0, 1).
+#[derive(Clone, Copy)]
+pub struct CameraSample {
+    pub lens: (f64, f64),
+    pub time: f64,
+}
+
+impl CameraSample {
+    pub fn new(sampler: &mut dyn SamplerState) -> Self {
+        let lens = sampler.next_2d();
+        Self {
+            lens,
+            time: sampler.next_1d(),
+        }
+    }
+}
+
+/// Position, orientation and shutter interval shared by all projections. The camera
+/// looks down `-w`, with `u` pointing right and `v` up on the film.
+#[derive(Clone)]
+pub struct CameraFrame {
+    pub origin: Point3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+    pub time0: f64,
+    pub time1: f64,
+}
+
+impl CameraFrame {
+    pub fn new(lookfrom: &Point3, lookat: &Point3, vup: &Vec3, time0: f64, time1: f64) -> Self {
+        let w = (lookfrom - lookat).unit_vector();
+        let u = (vup.cross(&w)).unit_vector();
+        let v = w.cross(&u);
+
+        Self {
+            origin: *lookfrom,
+            u,
+            v,
+            w,
+            time0,
+            time1,
+        }
+    }
+
+    /// Direction given in camera space, with `z` pointing away from the view.
+    pub fn to_world(&self, a: &Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+
+    /// Instant while the shutter is open, for the time of `sample`.
+    pub fn time(&self, sample: &CameraSample) -> f64 {
+        self.time0 + sample.time * (self.time1 - self.time0)
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{
+    model::{ray::Ray, vec3::Vec3},
+    util::rtweekend::PI,
+};
+
+use super::camera::{Camera, CameraFrame, CameraSample};
+
+use Vec3 as Point3;
+
+/// Full 360° by 180° panorama, with longitude across the film and latitude up it.
+/// `lookat` lands in the centre, so the film should be twice as wide as it is tall.
+pub struct EquirectangularCamera {
+    frame: CameraFrame,
+}
+
+impl EquirectangularCamera {
+    pub fn new(lookfrom: &Point3, lookat: &Point3, vup: &Vec3, time0: f64, time1: f64) -> Self {
+        Self {
+            frame: CameraFrame::new(lookfrom, lookat, vup, time0, time1),
+        }
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn ray(&self, s: f64, t: f64, sample: &CameraSample) -> Option<Ray> {
+        let phi = 2.0 * PI * (s - 0.5);
+        let latitude = PI * (t - 0.5);
+        let direction = Vec3::new(
+            latitude.cos() * phi.sin(),
+            latitude.sin(),
+            -latitude.cos() * phi.cos(),
+        );
+
+        Some(Ray::new(
+            &self.frame.origin,
+            &self.frame.to_world(&direction),
+            self.frame.time(sample),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampler::independent::IndependentSampler;
+
+    use super::*;
+
+    #[test]
+    fn test_longitude_runs_across_the_film() {
+        let camera = EquirectangularCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            0.0,
+        );
+        let direction = |s, t| {
+            camera
+                .ray(s, t, &CameraSample::new(&mut IndependentSampler))
+                .unwrap()
+                .dir()
+                .unit_vector()
+        };
+
+        assert!((direction(0.5, 0.5) - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+        assert!((direction(0.75, 0.5) - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        assert!((direction(0.0, 0.5) - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+        assert!((direction(0.3, 1.0) - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-12);
+    }
+}
@@ -0,0 +1,71 @@
+/// Height of a full frame 35mm sensor, in metres.
+pub const SENSOR_HEIGHT: f64 = 0.024;
+
+/// Photographic exposure settings, for scenes lit in physical units: scene units are
+/// metres, time is in seconds and radiance values are luminance in cd/m².
+#[derive(Clone, Copy)]
+pub struct Exposure {
+    pub f_number: f64,
+    /// Shutter time in seconds.
+    pub shutter: f64,
+    pub iso: f64,
+    /// Exposure compensation in stops, brightening the image when positive.
+    pub compensation: f64,
+}
+
+impl Exposure {
+    pub fn new(f_number: f64, shutter: f64, iso: f64) -> Self {
+        Self {
+            f_number,
+            shutter,
+            iso,
+            compensation: 0.0,
+        }
+    }
+
+    pub fn with_compensation(mut self, ev: f64) -> Self {
+        self.compensation = ev;
+        self
+    }
+
+    /// Exposure value of these settings, referred to ISO 100.
+    pub fn ev100(&self) -> f64 {
+        (self.f_number * self.f_number / self.shutter).log2() - (self.iso / 100.0).log2()
+    }
+
+    /// Factor taking luminance to film values, where 1.0 is the saturation point of
+    /// the sensor as defined by ISO 12232 with a lens transmittance of 0.65.
+    pub fn scale(&self) -> f64 {
+        let max_luminance = 1.2 * self.ev100().exp2();
+        self.compensation.exp2() / max_luminance
+    }
+
+    /// Diameter of the entrance pupil for a lens of the given focal length.
+    pub fn aperture(&self, focal_length: f64) -> f64 {
+        focal_length / self.f_number
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sunny_sixteen() {
+        // f/16 at 1/100 s on ISO 100 film is the textbook exposure for bright sun.
+        let exposure = Exposure::new(16.0, 1.0 / 100.0, 100.0);
+        assert!((exposure.ev100() - 14.64).abs() < 0.01);
+
+        // Doubling the ISO or opening up one stop both double the exposure.
+        let faster = Exposure::new(16.0, 1.0 / 100.0, 200.0);
+        let wider = Exposure::new(16.0 / 2.0_f64.sqrt(), 1.0 / 100.0, 100.0);
+        assert!((faster.scale() / exposure.scale() - 2.0).abs() < 1e-9);
+        assert!((wider.scale() / exposure.scale() - 2.0).abs() < 1e-9);
+        assert!((exposure.with_compensation(1.0).scale() - faster.scale()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_aperture_follows_f_number() {
+        assert!((Exposure::new(2.0, 1.0, 100.0).aperture(0.05) - 0.025).abs() < 1e-12);
+    }
+}
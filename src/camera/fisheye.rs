@@ -0,0 +1,154 @@
+use crate::{
+    model::{ray::Ray, vec3::Vec3},
+    util::rtweekend::degrees_to_radians,
+};
+
+use super::camera::{Camera, CameraFrame, CameraSample};
+
+use Vec3 as Point3;
+
+/// How a fisheye lens maps the angle `theta` off its axis to the distance `r` from
+/// the centre of the image circle.
+#[derive(Clone, Copy)]
+pub enum FisheyeProjection {
+    /// `r` proportional to `theta`, keeping angular distances.
+    Equidistant,
+    /// `r` proportional to `sin(theta / 2)`, keeping solid angles.
+    Equisolid,
+}
+
+/// Circular fisheye whose image circle, covering `fov` degrees, fits the height of
+/// the film. Beyond the circle the film stays black. The projection is equidistant
+/// unless chosen otherwise with `with_projection`.
+pub struct FisheyeCamera {
+    frame: CameraFrame,
+    aspect_ratio: f64,
+    max_theta: f64,
+    projection: FisheyeProjection,
+}
+
+impl FisheyeCamera {
+    pub fn new(
+        lookfrom: &Point3,
+        lookat: &Point3,
+        vup: &Vec3,
+        fov: f64,
+        aspect_ratio: f64,
+        time0: f64,
+        time1: f64,
+    ) -> Self {
+        Self {
+            frame: CameraFrame::new(lookfrom, lookat, vup, time0, time1),
+            aspect_ratio,
+            max_theta: degrees_to_radians(fov.clamp(1.0, 360.0)) / 2.0,
+            projection: FisheyeProjection::Equidistant,
+        }
+    }
+
+    pub fn with_projection(mut self, projection: FisheyeProjection) -> Self {
+        self.projection = projection;
+        self
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn ray(&self, s: f64, t: f64, sample: &CameraSample) -> Option<Ray> {
+        let x = (2.0 * s - 1.0) * self.aspect_ratio;
+        let y = 2.0 * t - 1.0;
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta = match self.projection {
+            FisheyeProjection::Equidistant => r * self.max_theta,
+            FisheyeProjection::Equisolid => 2.0 * (r * (self.max_theta / 2.0).sin()).asin(),
+        };
+        let phi = y.atan2(x);
+        let direction = Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            -theta.cos(),
+        );
+
+        Some(Ray::new(
+            &self.frame.origin,
+            &self.frame.to_world(&direction),
+            self.frame.time(sample),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampler::independent::IndependentSampler;
+
+    use super::*;
+
+    fn camera(projection: FisheyeProjection) -> FisheyeCamera {
+        FisheyeCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            180.0,
+            1.0,
+            0.0,
+            0.0,
+        )
+        .with_projection(projection)
+    }
+
+    #[test]
+    fn test_center_looks_down_the_axis() {
+        for projection in [FisheyeProjection::Equidistant, FisheyeProjection::Equisolid] {
+            let ray = camera(projection)
+                .ray(0.5, 0.5, &CameraSample::new(&mut IndependentSampler))
+                .unwrap();
+            assert!((ray.dir().unit_vector() - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_projections_agree_at_center_and_edge() {
+        let equidistant = camera(FisheyeProjection::Equidistant);
+        let equisolid = camera(FisheyeProjection::Equisolid);
+        for (s, t) in [(0.5, 0.5), (1.0, 0.5), (0.5, 0.0)] {
+            let a = equidistant
+                .ray(s, t, &CameraSample::new(&mut IndependentSampler))
+                .unwrap();
+            let b = equisolid
+                .ray(s, t, &CameraSample::new(&mut IndependentSampler))
+                .unwrap();
+            assert!((a.dir().unit_vector() - b.dir().unit_vector()).length() < 1e-9);
+        }
+
+        // The edge of a 180° circle looks sideways.
+        let edge = equisolid
+            .ray(1.0, 0.5, &CameraSample::new(&mut IndependentSampler))
+            .unwrap();
+        assert!((edge.dir().unit_vector() - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+
+        // Halfway out the projections differ.
+        let a = equidistant
+            .ray(0.75, 0.5, &CameraSample::new(&mut IndependentSampler))
+            .unwrap();
+        let b = equisolid
+            .ray(0.75, 0.5, &CameraSample::new(&mut IndependentSampler))
+            .unwrap();
+        assert!((a.dir().unit_vector() - b.dir().unit_vector()).length() > 1e-3);
+    }
+
+    #[test]
+    fn test_no_rays_outside_the_image_circle() {
+        let camera = camera(FisheyeProjection::Equidistant);
+        assert!(camera
+            .ray(0.0, 0.0, &CameraSample::new(&mut IndependentSampler))
+            .is_none());
+        assert!(camera
+            .ray(0.95, 0.95, &CameraSample::new(&mut IndependentSampler))
+            .is_none());
+        assert!(camera
+            .ray(0.5, 1.0, &CameraSample::new(&mut IndependentSampler))
+            .is_some());
+    }
+}
@@ -0,0 +1,9 @@
+pub mod animation;
+pub mod aperture;
+pub mod camera;
+pub mod equirectangular;
+pub mod exposure;
+pub mod fisheye;
+pub mod orthographic;
+pub mod perspective;
+pub mod stereo;
@@ -0,0 +1,82 @@
+use crate::model::{ray::Ray, vec3::Vec3};
+
+use super::camera::{Camera, CameraFrame, CameraSample};
+
+use Vec3 as Point3;
+
+/// Parallel projection without perspective foreshortening, for technical and
+/// architectural views. The film is `view_height` scene units tall and centred on
+/// `lookfrom`, with every ray travelling straight towards `lookat`.
+pub struct OrthographicCamera {
+    frame: CameraFrame,
+    lower_left_corner: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+}
+
+impl OrthographicCamera {
+    pub fn new(
+        lookfrom: &Point3,
+        lookat: &Point3,
+        vup: &Vec3,
+        view_height: f64,
+        aspect_ratio: f64,
+        time0: f64,
+        time1: f64,
+    ) -> Self {
+        let frame = CameraFrame::new(lookfrom, lookat, vup, time0, time1);
+        let horizontal = aspect_ratio * view_height * frame.u;
+        let vertical = view_height * frame.v;
+        let lower_left_corner = frame.origin - horizontal / 2.0 - vertical / 2.0;
+
+        Self {
+            frame,
+            lower_left_corner,
+            horizontal,
+            vertical,
+        }
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn ray(&self, s: f64, t: f64, sample: &CameraSample) -> Option<Ray> {
+        Some(Ray::new(
+            &(self.lower_left_corner + s * self.horizontal + t * self.vertical),
+            &-self.frame.w,
+            self.frame.time(sample),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampler::independent::IndependentSampler;
+
+    use super::*;
+
+    #[test]
+    fn test_rays_are_parallel_to_the_view() {
+        let camera = OrthographicCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            2.0,
+            2.0,
+            0.0,
+            0.0,
+        );
+
+        let center = camera
+            .ray(0.5, 0.5, &CameraSample::new(&mut IndependentSampler))
+            .unwrap();
+        assert!(center.origin().length() < 1e-12);
+        assert!((center.dir() - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+
+        // Corners move the origin across the film, not the direction.
+        let corner = camera
+            .ray(1.0, 0.0, &CameraSample::new(&mut IndependentSampler))
+            .unwrap();
+        assert!((corner.origin() - Point3::new(2.0, -1.0, 0.0)).length() < 1e-12);
+        assert!((corner.dir() - center.dir()).length() < 1e-12);
+    }
+}
@@ -0,0 +1,287 @@
+use std::sync::Arc;
+
+use crate::{
+    model::{ray::Ray, vec3::Vec3},
+    util::rtweekend::degrees_to_radians,
+};
+
+use super::{
+    aperture::{Aperture, CircularAperture},
+    camera::{Camera, CameraFrame, CameraSample},
+    exposure::{Exposure, SENSOR_HEIGHT},
+};
+
+use Vec3 as Point3;
+
+/// Pinhole or thin lens camera with a rectilinear projection. The lens opening is
+/// round unless given another shape with `with_aperture`.
+pub struct PerspectiveCamera {
+    frame: CameraFrame,
+    lower_left_corner: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    lens_radius: f64,
+    aspect_ratio: f64,
+    focus_dist: f64,
+    aperture: Arc<dyn Aperture + Sync + Send>,
+    vignetting: f64,
+    focus_normal: Option<Vec3>,
+}
+
+impl PerspectiveCamera {
+    /// Pinhole camera, sharp at every distance, whose shutter is open from time 0 to 1
+    /// unless given a lens with `with_lens` and a shutter with `with_shutter`.
+    pub fn new(
+        lookfrom: &Point3,
+        lookat: &Point3,
+        vup: &Vec3,
+        vfov: f64, // vertical field-of-view in degrees
+        aspect_ratio: f64,
+    ) -> Self {
+        let theta = degrees_to_radians(vfov);
+        let h = (theta / 2.0).tan();
+        let viewport_height = 2.0 * h;
+        let viewport_width = aspect_ratio * viewport_height;
+
+        let frame = CameraFrame::new(lookfrom, lookat, vup, 0.0, 1.0);
+        let horizontal = viewport_width * frame.u;
+        let vertical = viewport_height * frame.v;
+        let lower_left_corner = frame.origin - horizontal / 2.0 - vertical / 2.0 - frame.w;
+        Self {
+            frame,
+            horizontal,
+            vertical,
+            lower_left_corner,
+            lens_radius: 0.0,
+            aspect_ratio,
+            focus_dist: 1.0,
+            aperture: Arc::new(CircularAperture),
+            vignetting: 0.0,
+            focus_normal: None,
+        }
+    }
+
+    /// Thin lens `aperture` wide, focused at `focus_dist` in front of the camera.
+    pub fn with_lens(mut self, aperture: f64, focus_dist: f64) -> Self {
+        // The film is moved out to the plane in focus, keeping any shift.
+        let scale = focus_dist / self.focus_dist;
+        self.horizontal = scale * self.horizontal;
+        self.vertical = scale * self.vertical;
+        self.lower_left_corner =
+            self.frame.origin + scale * (self.lower_left_corner - self.frame.origin);
+        self.lens_radius = aperture / 2.0;
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    /// Keeps the shutter open from `time0` to `time1`.
+    pub fn with_shutter(mut self, time0: f64, time1: f64) -> Self {
+        self.frame.time0 = time0;
+        self.frame.time1 = time1;
+        self
+    }
+
+    pub fn with_aperture(mut self, aperture: Arc<dyn Aperture + Sync + Send>) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    /// Sizes the lens from the f-number, taking the field of view to be that of a
+    /// full frame camera, and keeps the shutter open for the exposure time.
+    pub fn with_exposure(mut self, exposure: &Exposure) -> Self {
+        let h = self.vertical.length() / (2.0 * self.focus_dist);
+        let focal_length = SENSOR_HEIGHT / 2.0 / h;
+        self.lens_radius = exposure.aperture(focal_length) / 2.0;
+        self.frame.time1 = self.frame.time0 + exposure.shutter;
+        self
+    }
+
+    /// Optical vignetting from the lens barrel. Away from the centre of the film the
+    /// opening is clipped by a second one, offset by up to `strength` lens radii in
+    /// the corners, so bokeh turns into cat's eyes and the corners darken.
+    pub fn with_vignetting(mut self, strength: f64) -> Self {
+        self.vignetting = strength.max(0.0);
+        self
+    }
+
+    /// Slides the lens parallel to the film by the given fractions of the frame, which
+    /// reframes the view while keeping vertical lines parallel.
+    pub fn with_shift(mut self, shift_x: f64, shift_y: f64) -> Self {
+        self.lower_left_corner += shift_x * self.horizontal + shift_y * self.vertical;
+        self
+    }
+
+    /// Tilts the plane of focus itself by `tilt` degrees about the horizontal axis of
+    /// the film and `swing` degrees about the vertical one. It still passes through the
+    /// point `focus_dist` in front of the camera; positive `tilt` moves the focus
+    /// further away towards the top of the frame, as for ground seen from above.
+    pub fn with_tilt(mut self, tilt: f64, swing: f64) -> Self {
+        let (tilt, swing) = (degrees_to_radians(tilt), degrees_to_radians(swing));
+        let normal = tilt.cos() * swing.cos() * self.frame.w
+            + tilt.sin() * self.frame.v
+            + tilt.cos() * swing.sin() * self.frame.u;
+        self.focus_normal = Some(normal.unit_vector());
+        self
+    }
+
+    /// Point in focus behind the film position `target`.
+    fn focus_point(&self, target: Point3) -> Point3 {
+        let normal = match self.focus_normal {
+            Some(normal) => normal,
+            None => return target,
+        };
+
+        let direction = target - self.frame.origin;
+        let plane_point = self.frame.origin - self.focus_dist * self.frame.w;
+        let denom = direction.dot(&normal);
+        if denom.abs() < 1e-8 {
+            return target;
+        }
+
+        // Rays looking away from a steeply tilted plane focus at infinity.
+        let k = (plane_point - self.frame.origin).dot(&normal) / denom;
+        if k <= 0.0 {
+            self.frame.origin + 1e8 * direction
+        } else {
+            self.frame.origin + k * direction
+        }
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn ray(&self, s: f64, t: f64, sample: &CameraSample) -> Option<Ray> {
+        let (lens_x, lens_y) = self.aperture.sample(sample.lens);
+
+        if self.vignetting > 0.0 {
+            // The barrel opening, seen from the film, drifts out towards the edges.
+            let scale = self.vignetting / (self.aspect_ratio * self.aspect_ratio + 1.0).sqrt();
+            let barrel_x = lens_x + scale * (2.0 * s - 1.0) * self.aspect_ratio;
+            let barrel_y = lens_y + scale * (2.0 * t - 1.0);
+            if barrel_x * barrel_x + barrel_y * barrel_y > 1.0 {
+                return None;
+            }
+        }
+
+        let offset = self.lens_radius * (self.frame.u * lens_x + self.frame.v * lens_y);
+        let origin = self.frame.origin + offset;
+        let target =
+            self.focus_point(self.lower_left_corner + s * self.horizontal + t * self.vertical);
+
+        Some(Ray::new(
+            &origin,
+            &(target - origin),
+            self.frame.time(sample),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampler::independent::IndependentSampler;
+
+    use super::*;
+
+    #[test]
+    fn test_tilted_focus_plane_keeps_rays_converging() {
+        let camera = PerspectiveCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            60.0,
+            1.0,
+        )
+        .with_lens(2.0, 10.0)
+        .with_tilt(30.0, 0.0);
+
+        // Whatever point of the lens a ray leaves from, it passes the tilted plane of
+        // focus at the same spot, and that spot is further away above the centre.
+        let focus_at = |t: f64| {
+            let r = camera
+                .ray(0.5, t, &CameraSample::new(&mut IndependentSampler))
+                .unwrap();
+            let normal = camera.focus_normal.unwrap();
+            let plane_point = Point3::new(0.0, 0.0, -10.0);
+            let k = (plane_point - *r.origin()).dot(&normal) / r.dir().dot(&normal);
+            r.at(k)
+        };
+        for t in [0.2, 0.5, 0.8] {
+            let a = focus_at(t);
+            let b = focus_at(t);
+            assert!((a - b).length() < 1e-6);
+        }
+        assert!(focus_at(0.8).z() < focus_at(0.2).z());
+    }
+
+    #[test]
+    fn test_lens_keeps_the_framing() {
+        let pinhole = PerspectiveCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            60.0,
+            1.5,
+        )
+        .with_shift(0.1, -0.2);
+        let focused = PerspectiveCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            60.0,
+            1.5,
+        )
+        .with_shift(0.1, -0.2)
+        .with_lens(0.0, 7.0);
+
+        let sample = CameraSample::new(&mut IndependentSampler);
+        for (s, t) in [(0.0, 0.0), (0.3, 0.9), (1.0, 0.5)] {
+            let a = pinhole.ray(s, t, &sample).unwrap();
+            let b = focused.ray(s, t, &sample).unwrap();
+            assert!((a.dir().unit_vector() - b.dir().unit_vector()).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_vignetting_darkens_corners_only() {
+        let camera = PerspectiveCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            60.0,
+            1.0,
+        )
+        .with_lens(2.0, 10.0)
+        .with_vignetting(2.0);
+
+        assert!((0..100).all(|_| camera
+            .ray(0.5, 0.5, &CameraSample::new(&mut IndependentSampler))
+            .is_some()));
+        assert!((0..100).all(|_| camera
+            .ray(1.0, 1.0, &CameraSample::new(&mut IndependentSampler))
+            .is_none()));
+    }
+
+    #[test]
+    fn test_differentials_leave_the_same_point_of_the_lens() {
+        let camera = PerspectiveCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            60.0,
+            1.0,
+        )
+        .with_lens(2.0, 10.0);
+
+        for _ in 0..100 {
+            let r = camera
+                .get_ray_differential(0.5, 0.5, 0.01, 0.01, &mut IndependentSampler)
+                .unwrap();
+            let differential = r.differential().unwrap();
+            assert_eq!(differential.rx_origin, *r.origin());
+            assert_eq!(differential.ry_origin, *r.origin());
+
+            // Only the neighbouring pixels set them apart, so they stay close together.
+            let spread = (differential.rx_dir.unit_vector() - r.dir().unit_vector()).length();
+            assert!(spread < 0.02);
+        }
+    }
+}
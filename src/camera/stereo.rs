@@ -0,0 +1,322 @@
+use crate::{
+    model::{ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+    util::rtweekend::{degrees_to_radians, PI},
+};
+
+use super::{
+    camera::{Camera, CameraFrame, CameraSample},
+    perspective::PerspectiveCamera,
+};
+
+use Vec3 as Point3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+impl Eye {
+    /// -1 for the left eye and 1 for the right one, along the camera's `u` axis.
+    fn side(&self) -> f64 {
+        match self {
+            Eye::Left => -1.0,
+            Eye::Right => 1.0,
+        }
+    }
+}
+
+/// How the views of both eyes share one image.
+#[derive(Clone, Copy)]
+pub enum StereoLayout {
+    /// Left eye in the left half, right eye in the right half.
+    SideBySide,
+    /// Left eye in the top half, right eye in the bottom half.
+    TopBottom,
+}
+
+impl StereoLayout {
+    /// The eye seeing the film position `(s, t)` of the whole image, and the same
+    /// position on the film of that eye.
+    pub fn split(&self, s: f64, t: f64) -> (Eye, f64, f64) {
+        match self {
+            StereoLayout::SideBySide if s < 0.5 => (Eye::Left, 2.0 * s, t),
+            StereoLayout::SideBySide => (Eye::Right, 2.0 * s - 1.0, t),
+            StereoLayout::TopBottom if t >= 0.5 => (Eye::Left, s, 2.0 * t - 1.0),
+            StereoLayout::TopBottom => (Eye::Right, s, 2.0 * t),
+        }
+    }
+
+    /// Offsets `(ds, dt)` across the whole image as offsets across the film of an eye.
+    pub fn eye_offsets(&self, ds: f64, dt: f64) -> (f64, f64) {
+        match self {
+            StereoLayout::SideBySide => (2.0 * ds, dt),
+            StereoLayout::TopBottom => (ds, 2.0 * dt),
+        }
+    }
+
+    /// Aspect ratio of each eye's view within a whole image of `aspect_ratio`.
+    pub fn eye_aspect_ratio(&self, aspect_ratio: f64) -> f64 {
+        match self {
+            StereoLayout::SideBySide => aspect_ratio / 2.0,
+            StereoLayout::TopBottom => aspect_ratio * 2.0,
+        }
+    }
+}
+
+/// Pair of pinhole cameras `interocular` apart, centred on `lookfrom`, that converge
+/// at the convergence distance: objects there appear at screen depth. The eyes look
+/// parallel, with their frames shifted towards each other, unless `with_toe_in` turns
+/// them to face the convergence point, which is simpler but bends the image planes
+/// apart and causes vertical disparity in the corners.
+pub struct StereoCamera {
+    lookfrom: Point3,
+    lookat: Point3,
+    vup: Vec3,
+    vfov: f64,
+    aspect_ratio: f64,
+    interocular: f64,
+    convergence: f64,
+    toe_in: bool,
+    layout: StereoLayout,
+    eyes: Vec<PerspectiveCamera>,
+}
+
+impl StereoCamera {
+    /// `aspect_ratio` is that of the whole image holding both views. The eyes
+    /// converge at `lookat` and the shutter is open from time 0 to 1.
+    pub fn new(
+        lookfrom: &Point3,
+        lookat: &Point3,
+        vup: &Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        interocular: f64,
+    ) -> Self {
+        let mut camera = Self {
+            lookfrom: *lookfrom,
+            lookat: *lookat,
+            vup: *vup,
+            vfov,
+            aspect_ratio,
+            interocular,
+            convergence: (lookat - lookfrom).length(),
+            toe_in: false,
+            layout: StereoLayout::SideBySide,
+            eyes: Vec::new(),
+        };
+        camera.build_eyes();
+        camera
+    }
+
+    pub fn with_convergence(mut self, distance: f64) -> Self {
+        self.convergence = distance;
+        self.build_eyes();
+        self
+    }
+
+    pub fn with_toe_in(mut self) -> Self {
+        self.toe_in = true;
+        self.build_eyes();
+        self
+    }
+
+    pub fn with_layout(mut self, layout: StereoLayout) -> Self {
+        self.layout = layout;
+        self.build_eyes();
+        self
+    }
+
+    fn build_eyes(&mut self) {
+        let aspect_ratio = self.layout.eye_aspect_ratio(self.aspect_ratio);
+        let frame = CameraFrame::new(&self.lookfrom, &self.lookat, &self.vup, 0.0, 0.0);
+        let forward = -frame.w;
+        let convergence_point = self.lookfrom + self.convergence * forward;
+        let frame_width =
+            2.0 * self.convergence * (degrees_to_radians(self.vfov) / 2.0).tan() * aspect_ratio;
+
+        self.eyes = [Eye::Left, Eye::Right]
+            .map(|eye| {
+                let offset = eye.side() * self.interocular / 2.0;
+                let position = self.lookfrom + offset * frame.u;
+                let target = if self.toe_in {
+                    convergence_point
+                } else {
+                    position + forward
+                };
+
+                let camera =
+                    PerspectiveCamera::new(&position, &target, &self.vup, self.vfov, aspect_ratio);
+                if self.toe_in {
+                    camera
+                } else {
+                    // Slide each frame back under the centre of the rig at the
+                    // convergence distance.
+                    camera.with_shift(-offset / frame_width, 0.0)
+                }
+            })
+            .into();
+    }
+
+    fn eye(&self, eye: Eye) -> &PerspectiveCamera {
+        match eye {
+            Eye::Left => &self.eyes[0],
+            Eye::Right => &self.eyes[1],
+        }
+    }
+}
+
+impl Camera for StereoCamera {
+    fn ray(&self, s: f64, t: f64, sample: &CameraSample) -> Option<Ray> {
+        let (eye, s, t) = self.layout.split(s, t);
+        self.eye(eye).ray(s, t, sample)
+    }
+
+    /// The differentials stay in the view of the eye seeing `(s, t)`, even next to
+    /// where the other view begins.
+    fn get_ray_differential(
+        &self,
+        s: f64,
+        t: f64,
+        ds: f64,
+        dt: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Option<Ray> {
+        let (eye, s, t) = self.layout.split(s, t);
+        let (ds, dt) = self.layout.eye_offsets(ds, dt);
+        self.eye(eye).get_ray_differential(s, t, ds, dt, sampler)
+    }
+}
+
+/// Omnidirectional stereo: an equirectangular panorama for each eye, where every
+/// column is seen from eyes `interocular` apart turned to face it. Towards the poles
+/// the eyes close in, which avoids swirling there at the cost of depth. The views
+/// are stacked top and bottom, as headsets expect them.
+pub struct OdsCamera {
+    frame: CameraFrame,
+    interocular: f64,
+}
+
+impl OdsCamera {
+    pub fn new(
+        lookfrom: &Point3,
+        lookat: &Point3,
+        vup: &Vec3,
+        interocular: f64,
+        time0: f64,
+        time1: f64,
+    ) -> Self {
+        Self {
+            frame: CameraFrame::new(lookfrom, lookat, vup, time0, time1),
+            interocular,
+        }
+    }
+}
+
+impl OdsCamera {
+    /// Ray through `(s, t)` of the panorama of `eye`.
+    fn eye_ray(&self, eye: Eye, s: f64, t: f64, sample: &CameraSample) -> Ray {
+        let phi = 2.0 * PI * (s - 0.5);
+        let latitude = PI * (t - 0.5);
+        let direction = Vec3::new(
+            latitude.cos() * phi.sin(),
+            latitude.sin(),
+            -latitude.cos() * phi.cos(),
+        );
+
+        // To the right of someone looking towards phi.
+        let right = Vec3::new(phi.cos(), 0.0, phi.sin());
+        let offset = eye.side() * self.interocular / 2.0 * latitude.cos() * right;
+
+        Ray::new(
+            &(self.frame.origin + self.frame.to_world(&offset)),
+            &self.frame.to_world(&direction),
+            self.frame.time(sample),
+        )
+    }
+}
+
+impl Camera for OdsCamera {
+    fn ray(&self, s: f64, t: f64, sample: &CameraSample) -> Option<Ray> {
+        let (eye, s, t) = StereoLayout::TopBottom.split(s, t);
+        Some(self.eye_ray(eye, s, t, sample))
+    }
+
+    /// The differentials stay in the panorama of the eye seeing `(s, t)`.
+    fn get_ray_differential(
+        &self,
+        s: f64,
+        t: f64,
+        ds: f64,
+        dt: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Option<Ray> {
+        let (eye, s, t) = StereoLayout::TopBottom.split(s, t);
+        let (ds, dt) = StereoLayout::TopBottom.eye_offsets(ds, dt);
+        let sample = CameraSample::new(sampler);
+        let rx = self.eye_ray(eye, s + ds, t, &sample);
+        let ry = self.eye_ray(eye, s, t + dt, &sample);
+        Some(self.eye_ray(eye, s, t, &sample).with_differential(&rx, &ry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampler::independent::IndependentSampler;
+
+    use super::*;
+
+    /// Distance between the line along `r` and the point `p`.
+    fn miss_distance(r: &Ray, p: &Point3) -> f64 {
+        let d = r.dir().unit_vector();
+        let to_p = *p - *r.origin();
+        (to_p - to_p.dot(&d) * d).length()
+    }
+
+    #[test]
+    fn test_eyes_converge_at_screen_depth() {
+        let lookfrom = Point3::new(0.0, 1.0, 5.0);
+        let lookat = Point3::new(0.0, 1.0, 0.0);
+        let vup = Vec3::new(0.0, 1.0, 0.0);
+
+        for rig in [
+            StereoCamera::new(&lookfrom, &lookat, &vup, 60.0, 2.0, 0.065),
+            StereoCamera::new(&lookfrom, &lookat, &vup, 60.0, 2.0, 0.065).with_toe_in(),
+        ] {
+            let left = rig
+                .ray(0.25, 0.5, &CameraSample::new(&mut IndependentSampler))
+                .unwrap();
+            let right = rig
+                .ray(0.75, 0.5, &CameraSample::new(&mut IndependentSampler))
+                .unwrap();
+            assert!((left.origin().x() + 0.0325).abs() < 1e-9);
+            assert!((right.origin().x() - 0.0325).abs() < 1e-9);
+            assert!(miss_distance(&left, &lookat) < 1e-9);
+            assert!(miss_distance(&right, &lookat) < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_ods_eyes_see_parallel_rays() {
+        let camera = OdsCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            0.064,
+            0.0,
+            1.0,
+        );
+
+        // The same direction seen from the top and the bottom half of the image.
+        let left = camera
+            .ray(0.3, 0.75, &CameraSample::new(&mut IndependentSampler))
+            .unwrap();
+        let right = camera
+            .ray(0.3, 0.25, &CameraSample::new(&mut IndependentSampler))
+            .unwrap();
+        assert!((left.dir().unit_vector() - right.dir().unit_vector()).length() < 1e-9);
+        assert!(((*left.origin() - *right.origin()).length() - 0.064).abs() < 1e-9);
+        assert!(left.dir().dot(&(*left.origin() - *right.origin())).abs() < 1e-9);
+    }
+}
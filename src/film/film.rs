@@ -0,0 +1,171 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::model::vec3::Vec3;
+
+use super::filter::Filter;
+
+/// Image being rendered. Every sample is splatted into the pixels whose filter covers
+/// it, and each pixel ends up as the filter weighted average of those samples.
+///
+/// A film may hold only a tile of the image, for one thread to splat into before the
+/// tile is merged back into the whole.
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    filter: Arc<dyn Filter + Sync + Send>,
+    /// Pixels of the image held, `x0..x1` by `y0..y1`.
+    bounds: (Range<usize>, Range<usize>),
+    color_sum: Vec<Vec3>,
+    weight_sum: Vec<f64>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Arc<dyn Filter + Sync + Send>) -> Self {
+        Self::new_with_bounds(width, height, filter, (0..width, 0..height))
+    }
+
+    fn new_with_bounds(
+        width: usize,
+        height: usize,
+        filter: Arc<dyn Filter + Sync + Send>,
+        bounds: (Range<usize>, Range<usize>),
+    ) -> Self {
+        let pixels = bounds.0.len() * bounds.1.len();
+        Self {
+            width,
+            height,
+            filter,
+            bounds,
+            color_sum: vec![Vec3::new(0.0, 0.0, 0.0); pixels],
+            weight_sum: vec![0.0; pixels],
+        }
+    }
+
+    /// Empty tile for the samples of pixels `xs` by `ys`, which also holds the pixels
+    /// around them that the filter reaches.
+    pub fn tile(&self, xs: Range<usize>, ys: Range<usize>) -> Self {
+        let reach = (self.filter.radius() + 0.5).ceil() as usize;
+        let grow = |range: Range<usize>, size: usize| {
+            range.start.saturating_sub(reach)..(range.end + reach).min(size)
+        };
+        let bounds = (grow(xs, self.width), grow(ys, self.height));
+        Self::new_with_bounds(self.width, self.height, self.filter.clone(), bounds)
+    }
+
+    /// Adds the samples splatted into `tile`, which must lie within this film.
+    pub fn merge(&mut self, tile: &Film) {
+        for j in tile.bounds.1.clone() {
+            for i in tile.bounds.0.clone() {
+                let (to, from) = (self.index(i, j), tile.index(i, j));
+                self.color_sum[to] += tile.color_sum[from];
+                self.weight_sum[to] += tile.weight_sum[from];
+            }
+        }
+    }
+
+    fn index(&self, i: usize, j: usize) -> usize {
+        (j - self.bounds.1.start) * self.bounds.0.len() + (i - self.bounds.0.start)
+    }
+
+    /// Adds a sample taken at `(x, y)`, in pixels from the lower left corner of the
+    /// image, so that pixel `(i, j)` spans `[i, i + 1) x [j, j + 1)`.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: &Vec3) {
+        let radius = self.filter.radius();
+        let range = |centre: f64, bounds: &Range<usize>| {
+            let first = (centre - 0.5 - radius).ceil().max(bounds.start as f64) as usize;
+            let last = (centre - 0.5 + radius).floor().min(bounds.end as f64 - 1.0);
+            (first, last)
+        };
+        let (x0, x1) = range(x, &self.bounds.0);
+        let (y0, y1) = range(y, &self.bounds.1);
+        if x1 < x0 as f64 || y1 < y0 as f64 {
+            return;
+        }
+
+        for j in y0..=y1 as usize {
+            for i in x0..=x1 as usize {
+                let weight = self
+                    .filter
+                    .evaluate(x - (i as f64 + 0.5), y - (j as f64 + 0.5));
+                if weight != 0.0 {
+                    let index = self.index(i, j);
+                    self.color_sum[index] += weight * *color;
+                    self.weight_sum[index] += weight;
+                }
+            }
+        }
+    }
+
+    /// The reconstructed value of pixel `(i, j)`, black where no sample reached.
+    pub fn pixel(&self, i: usize, j: usize) -> Vec3 {
+        let index = self.index(i, j);
+        let weight = self.weight_sum[index];
+        if weight > 0.0 {
+            self.color_sum[index] / weight
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::film::filter::{BoxFilter, TentFilter};
+
+    use super::*;
+
+    #[test]
+    fn test_box_filter_averages_within_pixel() {
+        let mut film = Film::new(2, 1, Arc::new(BoxFilter::new(0.5)));
+        film.add_sample(0.2, 0.5, &Vec3::new(1.0, 0.0, 0.0));
+        film.add_sample(0.9, 0.5, &Vec3::new(0.0, 1.0, 0.0));
+        film.add_sample(1.0, 0.5, &Vec3::new(0.0, 0.0, 1.0));
+
+        assert!((film.pixel(0, 0) - Vec3::new(0.5, 0.5, 0.0)).length() < 1e-12);
+        assert!((film.pixel(1, 0) - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_wide_filter_splats_into_neighbours() {
+        let mut film = Film::new(3, 3, Arc::new(TentFilter::new(1.5)));
+        film.add_sample(1.5, 1.5, &Vec3::new(1.0, 1.0, 1.0));
+
+        for j in 0..3 {
+            for i in 0..3 {
+                assert!((film.pixel(i, j) - Vec3::new(1.0, 1.0, 1.0)).length() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_merged_tiles_match_one_film() {
+        let filter = Arc::new(TentFilter::new(1.5));
+        let samples: Vec<(f64, f64, Vec3)> = (0..64)
+            .map(|k| {
+                let (x, y) = ((k % 8) as f64 + 0.3, (k / 8) as f64 + 0.7);
+                (x, y, Vec3::new(x, y, 1.0))
+            })
+            .collect();
+
+        let mut whole = Film::new(8, 8, filter.clone());
+        let mut tiled = Film::new(8, 8, filter);
+        for rows in [0..3, 3..8] {
+            let mut tile = tiled.tile(0..8, rows.clone());
+            for (x, y, color) in &samples {
+                if rows.contains(&(*y as usize)) {
+                    tile.add_sample(*x, *y, color);
+                }
+            }
+            tiled.merge(&tile);
+        }
+        for (x, y, color) in &samples {
+            whole.add_sample(*x, *y, color);
+        }
+
+        for j in 0..8 {
+            for i in 0..8 {
+                assert!((whole.pixel(i, j) - tiled.pixel(i, j)).length() < 1e-12);
+            }
+        }
+    }
+}
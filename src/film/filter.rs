@@ -0,0 +1,217 @@
+use crate::util::rtweekend::PI;
+
+/// Weighting of the samples around a pixel centre, by their offset from it in pixels.
+/// Filters are separable, the product of one profile along x and another along y.
+pub trait Filter {
+    /// Half the width of the square outside of which the filter is zero.
+    fn radius(&self) -> f64;
+
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
+
+/// Equal weight over the support. With a radius of half a pixel every sample lands in
+/// exactly one pixel, which is a plain average.
+pub struct BoxFilter {
+    radius: f64,
+}
+
+impl BoxFilter {
+    pub fn new(radius: f64) -> Self {
+        Self { radius }
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        // Half open, so that a sample on the border of two pixels counts once.
+        let inside = |d: f64| -self.radius <= d && d < self.radius;
+        if inside(x) && inside(y) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Weight falling off linearly to zero at the radius.
+pub struct TentFilter {
+    radius: f64,
+}
+
+impl TentFilter {
+    pub fn new(radius: f64) -> Self {
+        Self { radius }
+    }
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let tent = |d: f64| (self.radius - d.abs()).max(0.0);
+        tent(x) * tent(y)
+    }
+}
+
+/// Gaussian of standard deviation `sigma`, lowered so that it reaches zero at the
+/// radius instead of being cut off there.
+pub struct GaussianFilter {
+    radius: f64,
+    sigma: f64,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f64, sigma: f64) -> Self {
+        Self { radius, sigma }
+    }
+
+    fn gaussian(&self, d: f64) -> f64 {
+        (-d * d / (2.0 * self.sigma * self.sigma)).exp()
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let edge = self.gaussian(self.radius);
+        let profile = |d: f64| (self.gaussian(d) - edge).max(0.0);
+        profile(x) * profile(y)
+    }
+}
+
+/// Mitchell and Netravali's cubic, whose small negative lobes keep edges sharper than
+/// a Gaussian does. `b` and `c` default to a third each, their recommendation for
+/// balancing blur against ringing.
+pub struct MitchellFilter {
+    radius: f64,
+    b: f64,
+    c: f64,
+}
+
+impl MitchellFilter {
+    pub fn new(radius: f64) -> Self {
+        Self {
+            radius,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        }
+    }
+
+    pub fn with_parameters(mut self, b: f64, c: f64) -> Self {
+        self.b = b;
+        self.c = c;
+        self
+    }
+
+    /// The cubic over [-2, 2].
+    fn mitchell(&self, x: f64) -> f64 {
+        let (b, c) = (self.b, self.c);
+        let x = x.abs();
+        if x < 1.0 {
+            ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                + (6.0 - 2.0 * b))
+                / 6.0
+        } else if x < 2.0 {
+            ((-b - 6.0 * c) * x * x * x
+                + (6.0 * b + 30.0 * c) * x * x
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c))
+                / 6.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        // Stretched from its natural width of 2 to the radius.
+        self.mitchell(2.0 * x / self.radius) * self.mitchell(2.0 * y / self.radius)
+    }
+}
+
+/// Sinc windowed by a wider sinc, with as many lobes on each side as the radius has
+/// pixels. The sharpest of these filters, and the most prone to ringing.
+pub struct LanczosFilter {
+    radius: f64,
+}
+
+impl LanczosFilter {
+    pub fn new(radius: f64) -> Self {
+        Self { radius }
+    }
+
+    fn windowed_sinc(&self, x: f64) -> f64 {
+        if x.abs() >= self.radius {
+            0.0
+        } else {
+            sinc(x) * sinc(x / self.radius)
+        }
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.windowed_sinc(x) * self.windowed_sinc(y)
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_vanish_at_radius() {
+        let filters: Vec<Box<dyn Filter>> = vec![
+            Box::new(TentFilter::new(1.5)),
+            Box::new(GaussianFilter::new(1.5, 0.5)),
+            Box::new(MitchellFilter::new(2.0)),
+            Box::new(LanczosFilter::new(3.0)),
+        ];
+        for filter in filters {
+            let r = filter.radius();
+            assert!(filter.evaluate(0.0, 0.0) > 0.0);
+            assert!(filter.evaluate(r, 0.0).abs() < 1e-9);
+            assert!(filter.evaluate(0.0, -r).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_mitchell_interpolates_smoothly() {
+        // With b = 0 the cubic is 1 at the centre and 0 at every other integer.
+        let filter = MitchellFilter::new(2.0).with_parameters(0.0, 0.5);
+        assert!((filter.evaluate(0.0, 0.0) - 1.0).abs() < 1e-12);
+        assert!(filter.evaluate(1.0, 0.0).abs() < 1e-12);
+
+        // The default parameters sum to one over unit spaced samples.
+        let filter = MitchellFilter::new(2.0);
+        let sum: f64 = (-2..=2).map(|i| filter.mitchell(i as f64 + 0.3)).sum();
+        assert!((sum - 1.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    model::{onb::Onb, vec3::Vec3},
+    sampler::sampler::SamplerState,
+    util::rtweekend::{degrees_to_radians, INFINITY, PI},
+};
+
+use super::light::{Light, LightSample};
+
+use Vec3 as Point3;
+
+/// Distant light such as the sun, arriving from `direction`. A non-zero angular
+/// diameter spreads it over a small disk in the sky, which softens the shadows.
+pub struct DirectionalLight {
+    /// Unit direction pointing towards the light.
+    pub direction: Vec3,
+    /// Irradiance on a surface facing the light, in watts per square metre.
+    pub irradiance: Vec3,
+    pub cos_half_angle: f64,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: &Vec3, irradiance: &Vec3) -> Self {
+        Self {
+            direction: direction.unit_vector(),
+            irradiance: *irradiance,
+            cos_half_angle: 1.0,
+        }
+    }
+
+    /// Gives the light the apparent size of a disk `degrees` across, about 0.53 for the sun.
+    pub fn with_angular_diameter(mut self, degrees: f64) -> Self {
+        self.cos_half_angle = degrees_to_radians(0.5 * degrees).cos();
+        self
+    }
+
+    /// Solid angle of the disk, zero for a light from a single direction.
+    fn solid_angle(&self) -> f64 {
+        2.0 * PI * (1.0 - self.cos_half_angle)
+    }
+
+    fn covers(&self, direction: &Vec3) -> bool {
+        self.cos_half_angle < 1.0
+            && direction.unit_vector().dot(&self.direction) >= self.cos_half_angle
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(
+        &self,
+        _p: &Point3,
+        sample: &mut LightSample,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        sample.direction = if self.cos_half_angle < 1.0 {
+            // Uniform direction inside the cone subtended by the disk.
+            let (u, v) = sampler.next_2d();
+            let cos_theta = 1.0 - u * (1.0 - self.cos_half_angle);
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * v;
+            Onb::build_from_w(&self.direction).local(&Vec3::new(
+                sin_theta * phi.cos(),
+                sin_theta * phi.sin(),
+                cos_theta,
+            ))
+        } else {
+            self.direction
+        };
+        sample.distance = INFINITY;
+        // The radiance of the disk times its solid angle, which the uniform pdf cancels.
+        sample.li = self.irradiance;
+        sample.pdf = if self.cos_half_angle < 1.0 {
+            1.0 / self.solid_angle()
+        } else {
+            0.0
+        };
+        true
+    }
+
+    /// The irradiance spread evenly over the disk.
+    fn radiance(&self, direction: &Vec3) -> Vec3 {
+        if self.covers(direction) {
+            self.irradiance / self.solid_angle()
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f64 {
+        if self.covers(direction) {
+            1.0 / self.solid_angle()
+        } else {
+            0.0
+        }
+    }
+}
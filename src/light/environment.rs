@@ -0,0 +1,148 @@
+use crate::{
+    model::vec3::Vec3,
+    sampler::sampler::SamplerState,
+    texture::hdr_image::HdrImage,
+    util::{
+        distribution::Distribution2D,
+        rtweekend::{degrees_to_radians, INFINITY, PI},
+    },
+};
+
+use super::light::LightSample;
+
+/// Light arriving from infinitely far away in every direction, read from an
+/// equirectangular image whose top row looks straight up (+y). Directions are
+/// importance sampled in proportion to the image brightness, so small bright
+/// features like the sun are found by shadow rays rather than by chance.
+pub struct EnvironmentLight {
+    image: HdrImage,
+    distribution: Distribution2D,
+    pub intensity: f64,
+    sin_rotation: f64,
+    cos_rotation: f64,
+}
+
+impl EnvironmentLight {
+    pub fn new(image: HdrImage) -> Self {
+        // Rows near the poles cover less solid angle, hence the sine weighting.
+        let mut func = Vec::with_capacity(image.width * image.height);
+        for y in 0..image.height {
+            let sin_theta = (PI * (y as f64 + 0.5) / image.height as f64).sin();
+            for x in 0..image.width {
+                func.push(image.pixel(x, y).luminance() * sin_theta);
+            }
+        }
+        let distribution = Distribution2D::new(&func, image.width, image.height);
+
+        Self {
+            image,
+            distribution,
+            intensity: 1.0,
+            sin_rotation: 0.0,
+            cos_rotation: 1.0,
+        }
+    }
+
+    /// Tabulates the radiance seen looking towards each direction into a `width` by
+    /// `height` map, e.g. to light a scene with an analytic sky.
+    pub fn new_with_function(
+        width: usize,
+        height: usize,
+        radiance: impl Fn(&Vec3) -> Vec3,
+    ) -> EnvironmentLight {
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let u = (x as f64 + 0.5) / width as f64;
+                let v = (y as f64 + 0.5) / height as f64;
+                data.push(radiance(&unrotated_direction(u, v)));
+            }
+        }
+
+        EnvironmentLight::new(HdrImage::new(width, height, data))
+    }
+
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Turns the environment around the vertical axis by `degrees`.
+    pub fn with_rotation(mut self, degrees: f64) -> Self {
+        let radians = degrees_to_radians(degrees);
+        self.sin_rotation = radians.sin();
+        self.cos_rotation = radians.cos();
+        self
+    }
+
+    /// Radiance arriving along `-direction`, i.e. seen when looking towards `direction`.
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = self.direction_to_uv(direction);
+        let x = ((u * self.image.width as f64) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f64) as usize).min(self.image.height - 1);
+
+        self.intensity * self.image.pixel(x, y)
+    }
+
+    /// Solid angle density with which `sample` picks `direction`.
+    pub fn pdf(&self, direction: &Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    pub fn sample(&self, sample: &mut LightSample, sampler: &mut dyn SamplerState) -> bool {
+        let (u, v) = sampler.next_2d();
+        let (u, v, map_pdf) = self.distribution.sample_continuous(u, v);
+        let sin_theta = (PI * v).sin();
+        if map_pdf <= 0.0 || sin_theta <= 0.0 {
+            return false;
+        }
+
+        sample.direction = self.uv_to_direction(u, v);
+        sample.distance = INFINITY;
+        sample.pdf = map_pdf / (2.0 * PI * PI * sin_theta);
+        sample.li = self.radiance(&sample.direction) / sample.pdf;
+        true
+    }
+
+    /// Image coordinates in [0, 1)², with v growing downwards from the zenith.
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let d = direction.unit_vector();
+        let x = self.cos_rotation * d.x() - self.sin_rotation * d.z();
+        let z = self.sin_rotation * d.x() + self.cos_rotation * d.z();
+
+        // Same longitude convention as `Sphere::get_sphere_uv`.
+        let phi = (-z).atan2(x) + PI;
+        let theta = d.y().clamp(-1.0, 1.0).acos();
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let d = unrotated_direction(u, v);
+
+        // Undo the rotation applied in `direction_to_uv`.
+        Vec3::new(
+            self.cos_rotation * d.x() + self.sin_rotation * d.z(),
+            d.y(),
+            -self.sin_rotation * d.x() + self.cos_rotation * d.z(),
+        )
+    }
+}
+
+/// Inverse of the mapping in `EnvironmentLight::direction_to_uv`, without rotation.
+fn unrotated_direction(u: f64, v: f64) -> Vec3 {
+    let phi = 2.0 * PI * u - PI;
+    let theta = PI * v;
+
+    Vec3::new(
+        theta.sin() * phi.cos(),
+        theta.cos(),
+        -theta.sin() * phi.sin(),
+    )
+}
@@ -0,0 +1,36 @@
+use crate::{model::vec3::Vec3, sampler::sampler::SamplerState};
+
+use Vec3 as Point3;
+
+/// Illumination arriving at a point from one sampled direction of a light.
+#[derive(Default)]
+pub struct LightSample {
+    /// Unit direction from the shaded point towards the light.
+    pub direction: Vec3,
+    /// Distance to the light along `direction`, infinite for directional lights.
+    pub distance: f64,
+    /// Incident radiance divided by the probability density of `direction`.
+    pub li: Vec3,
+    /// Solid angle density of `direction`, left at zero by delta lights.
+    pub pdf: f64,
+}
+
+/// Light source that is not part of the scene geometry, which the integrator samples
+/// explicitly with shadow rays. Delta lights can never be hit by chance, while distant
+/// lights of some angular size are also seen by rays escaping towards them, and report
+/// their radiance and sampling density so that both strategies can be weighted.
+pub trait Light {
+    /// Picks a direction towards the light as seen from `p`, drawing the numbers it
+    /// needs from `sampler`.
+    fn sample(&self, p: &Point3, sample: &mut LightSample, sampler: &mut dyn SamplerState) -> bool;
+
+    /// Radiance seen by a ray escaping the scene towards `direction`.
+    fn radiance(&self, _direction: &Vec3) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    /// Solid angle density with which `sample` picks `direction`, zero for delta lights.
+    fn pdf(&self, _direction: &Vec3) -> f64 {
+        0.0
+    }
+}
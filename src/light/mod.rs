@@ -0,0 +1,6 @@
+pub mod directional;
+pub mod environment;
+pub mod light;
+pub mod point;
+pub mod sky;
+pub mod spot;
@@ -0,0 +1,46 @@
+use crate::{model::vec3::Vec3, sampler::sampler::SamplerState, util::rtweekend::PI};
+
+use super::light::{Light, LightSample};
+
+use Vec3 as Point3;
+
+/// Light radiating equally in all directions from a single point.
+pub struct PointLight {
+    pub position: Point3,
+    /// Radiant intensity in watts per steradian.
+    pub intensity: Vec3,
+}
+
+impl PointLight {
+    pub fn new(position: &Point3, intensity: &Vec3) -> Self {
+        Self {
+            position: *position,
+            intensity: *intensity,
+        }
+    }
+
+    /// Light emitting `watts` in total, tinted by `color`.
+    pub fn new_with_power(position: &Point3, color: &Vec3, watts: f64) -> PointLight {
+        PointLight::new(position, &(watts / (4.0 * PI) * color))
+    }
+}
+
+impl Light for PointLight {
+    fn sample(
+        &self,
+        p: &Point3,
+        sample: &mut LightSample,
+        _sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return false;
+        }
+
+        sample.distance = distance_squared.sqrt();
+        sample.direction = to_light / sample.distance;
+        sample.li = self.intensity / distance_squared;
+        true
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{
+    model::vec3::Vec3,
+    util::rtweekend::{degrees_to_radians, PI},
+};
+
+use super::{directional::DirectionalLight, environment::EnvironmentLight};
+
+/// Illuminance of the sun outside the atmosphere, in kilolux.
+const SOLAR_ILLUMINANCE: f64 = 127.5;
+/// Angular diameter of the sun seen from the ground, in degrees.
+const SUN_ANGULAR_DIAMETER: f64 = 0.53;
+
+/// Analytic daylight model of Preetham, Shirley and Smits (1999). Sky luminance is
+/// in kilocandela per square metre and the matching sun in kilolux, so scenes usually
+/// scale both down with `with_intensity`.
+pub struct PreethamSky {
+    /// Unit direction towards the sun.
+    pub sun_direction: Vec3,
+    pub turbidity: f64,
+    pub ground_albedo: Vec3,
+    pub intensity: f64,
+    /// Zenith luminance and chromaticity.
+    zenith: [f64; 3],
+    /// Perez distribution coefficients A to E for luminance and both chromaticities.
+    perez: [[f64; 5]; 3],
+}
+
+impl PreethamSky {
+    /// Sun `elevation` above the horizon and `azimuth` from +x towards +z, in degrees.
+    /// Turbidity ranges from 2 for a very clear sky to about 10 for hazy ones.
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        let elevation_rad = degrees_to_radians(elevation.clamp(0.0, 90.0));
+        let azimuth_rad = degrees_to_radians(azimuth);
+        let sun_direction = Vec3::new(
+            elevation_rad.cos() * azimuth_rad.cos(),
+            elevation_rad.sin(),
+            elevation_rad.cos() * azimuth_rad.sin(),
+        );
+
+        let t = turbidity;
+        let theta_s = 0.5 * PI - elevation_rad;
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let cubic =
+            |c: [f64; 4]| c[0] * theta_s.powi(3) + c[1] * theta_s.powi(2) + c[2] * theta_s + c[3];
+        let zenith_x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+            + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let zenith_y = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+            + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        Self {
+            sun_direction,
+            turbidity,
+            ground_albedo: Vec3::new(0.3, 0.3, 0.3),
+            intensity: 1.0,
+            zenith: [zenith_luminance, zenith_x, zenith_y],
+            perez,
+        }
+    }
+
+    pub fn with_ground_albedo(mut self, albedo: &Vec3) -> Self {
+        self.ground_albedo = *albedo;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Sky radiance seen looking towards `direction`, excluding the sun itself, whose
+    /// disk `sun` adds for rays escaping towards it. Below the horizon the ground
+    /// reflects the sky radiance at the horizon.
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let d = direction.unit_vector();
+        let below_horizon = d.y() < 0.0;
+        let d = if below_horizon {
+            Vec3::new(d.x(), 0.0, d.z()).unit_vector()
+        } else {
+            d
+        };
+
+        let cos_theta = d.y().max(0.01);
+        let cos_gamma = d.dot(&self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+        let theta_s = self.sun_direction.y().clamp(-1.0, 1.0).acos();
+
+        let mut yxy = [0.0; 3];
+        for (i, c) in self.perez.iter().enumerate() {
+            let perez = |cos_theta: f64, gamma: f64| {
+                (1.0 + c[0] * (c[1] / cos_theta).exp())
+                    * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+            };
+            yxy[i] = self.zenith[i] * perez(cos_theta, gamma) / perez(1.0, theta_s);
+        }
+
+        let color = yxy_to_rgb(yxy[0], yxy[1], yxy[2]);
+        let color = if below_horizon {
+            self.ground_albedo * color
+        } else {
+            color
+        };
+
+        self.intensity * color
+    }
+
+    /// The sky tabulated into an importance-sampled environment map.
+    pub fn environment(&self, width: usize, height: usize) -> EnvironmentLight {
+        EnvironmentLight::new_with_function(width, height, |direction| self.radiance(direction))
+    }
+
+    /// The sun as a disk light, reddened by its path through the atmosphere.
+    pub fn sun(&self) -> DirectionalLight {
+        let cos_theta_s = self.sun_direction.y().max(0.0);
+        let theta_s_deg = cos_theta_s.acos().to_degrees();
+
+        // Relative optical mass and the Rayleigh and aerosol (Angstrom) transmittances
+        // from the appendix of the paper, at red, green and blue wavelengths in microns.
+        let mass = 1.0 / (cos_theta_s + 0.15 * (93.885 - theta_s_deg).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |lambda: f64| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * mass).exp();
+            rayleigh * aerosol
+        };
+        let transmittance = Vec3::new(
+            transmittance(0.68),
+            transmittance(0.55),
+            transmittance(0.44),
+        );
+
+        DirectionalLight::new(
+            &self.sun_direction,
+            &(self.intensity * SOLAR_ILLUMINANCE * transmittance),
+        )
+        .with_angular_diameter(SUN_ANGULAR_DIAMETER)
+    }
+}
+
+/// Converts luminance and CIE xy chromaticity to linear sRGB.
+fn yxy_to_rgb(luminance: f64, x: f64, y: f64) -> Vec3 {
+    if y <= 0.0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    let cx = x * luminance / y;
+    let cz = (1.0 - x - y) * luminance / y;
+    Vec3::new(
+        (3.2406 * cx - 1.5372 * luminance - 0.4986 * cz).max(0.0),
+        (-0.9689 * cx + 1.8758 * luminance + 0.0415 * cz).max(0.0),
+        (0.0557 * cx - 0.2040 * luminance + 1.0570 * cz).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        light::light::{Light, LightSample},
+        sampler::independent::IndependentSampler,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_sun_disk_is_visible_and_sampled_consistently() {
+        let sky = PreethamSky::new(30.0, 45.0, 3.0);
+        let sun = sky.sun();
+        let solid_angle = 2.0 * PI * (1.0 - degrees_to_radians(SUN_ANGULAR_DIAMETER / 2.0).cos());
+
+        // Looking at the sun shows a disk far brighter than the sky around it.
+        let disk = sun.radiance(&sky.sun_direction);
+        assert!((disk * solid_angle - sun.irradiance).length() < 1e-9 * disk.length());
+        assert!(disk.luminance() > 1000.0 * sky.radiance(&sky.sun_direction).luminance());
+
+        // A degree away there is only sky.
+        let beside = Vec3::new(0.0, 1.0, 0.0) * degrees_to_radians(1.0) + sky.sun_direction;
+        assert!(sun.radiance(&beside).near_zero());
+        assert_eq!(sun.pdf(&beside), 0.0);
+
+        let mut sample = LightSample::default();
+        assert!(sun.sample(
+            &Vec3::new(0.0, 0.0, 0.0),
+            &mut sample,
+            &mut IndependentSampler
+        ));
+        assert!((sample.pdf - sun.pdf(&sample.direction)).abs() < 1e-9 * sample.pdf);
+        assert!((sample.li - sun.radiance(&sample.direction) / sample.pdf).length() < 1e-6);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{
+    model::vec3::Vec3, sampler::sampler::SamplerState, util::rtweekend::degrees_to_radians,
+};
+
+use super::light::{Light, LightSample};
+
+use Vec3 as Point3;
+
+/// Point light restricted to a cone, with full intensity inside `falloff_start`
+/// degrees of its axis fading smoothly to nothing at `total_width` degrees.
+pub struct SpotLight {
+    pub position: Point3,
+    pub axis: Vec3,
+    /// Radiant intensity along the axis, in watts per steradian.
+    pub intensity: Vec3,
+    pub cos_total_width: f64,
+    pub cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: &Point3,
+        target: &Point3,
+        intensity: &Vec3,
+        total_width: f64,
+        falloff_start: f64,
+    ) -> Self {
+        Self {
+            position: *position,
+            axis: (target - position).unit_vector(),
+            intensity: *intensity,
+            cos_total_width: degrees_to_radians(total_width).cos(),
+            cos_falloff_start: degrees_to_radians(falloff_start.min(total_width)).cos(),
+        }
+    }
+
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta < self.cos_total_width {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+
+        let t =
+            (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(
+        &self,
+        p: &Point3,
+        sample: &mut LightSample,
+        _sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return false;
+        }
+
+        sample.distance = distance_squared.sqrt();
+        sample.direction = to_light / sample.distance;
+
+        let falloff = self.falloff(-sample.direction.dot(&self.axis));
+        if falloff <= 0.0 {
+            return false;
+        }
+
+        sample.li = falloff * self.intensity / distance_squared;
+        true
+    }
+}
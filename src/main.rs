@@ -1,12 +1,43 @@
 use std::{
     io::{self, Write},
-    sync::{Arc, Mutex},
+    sync::Arc,
 };
 
-use material::{diffuse_light::DiffuseLight, material::Material};
+use camera::{
+    animation::{CameraPath, FrameRange, Keyframe},
+    aperture::{ImageAperture, PolygonAperture},
+    camera::Camera,
+    equirectangular::EquirectangularCamera,
+    exposure::Exposure,
+    fisheye::{FisheyeCamera, FisheyeProjection},
+    orthographic::OrthographicCamera,
+    perspective::PerspectiveCamera,
+    stereo::{OdsCamera, StereoCamera, StereoLayout},
+};
+use film::{
+    film::Film,
+    filter::{BoxFilter, Filter, GaussianFilter, LanczosFilter, MitchellFilter, TentFilter},
+};
+use light::{
+    directional::DirectionalLight,
+    environment::EnvironmentLight,
+    light::{Light, LightSample},
+    point::PointLight,
+    sky::PreethamSky,
+    spot::SpotLight,
+};
+use material::{
+    diffuse_light::{DiffuseLight, Falloff},
+    interior::MediumStack,
+    material::Material,
+};
 use model::{
+    alpha_mask::AlphaMask,
     bvh::BvhNode,
     constant_medium::ConstantMedium,
+    density::{DensityGrid, NoiseDensity},
+    flip_face::FlipFace,
+    heterogeneous_medium::HeterogeneousMedium,
     hit::{HitRecord, Hittable},
     moving_sphere::MovingSphere,
     r#box::Box,
@@ -20,29 +51,70 @@ use model::{
 };
 use Vec3 as Point3;
 
-use rayon::prelude::{IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
-use texture::{checker::CheckerTexture, image::ImageTexture, noise::NoiseTexture};
+use photon::photon_map::{PhotonMap, ProgressivePhotonMapping};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use sampler::{
+    blue_noise::BlueNoiseSampler,
+    halton::HaltonSampler,
+    independent::IndependentSampler,
+    sampler::{PixelSample, Sampler, SamplerState},
+    sobol::SobolSampler,
+    stratified::StratifiedSampler,
+};
+use texture::{
+    alpha_channel::AlphaChannel,
+    checker::CheckerTexture,
+    hdr_image::HdrImage,
+    image_file::ColorSpace,
+    mipmap::{TextureFilter, WrapMode},
+    noise::NoiseTexture,
+    solid_color::SolidColor,
+    texture::Texture,
+    texture_manager::TextureManager,
+};
 use util::{
+    ies::IesProfile,
+    png::write_png,
     rtweekend::INFINITY,
     rtweekend::{random_double, random_double_by_range},
 };
 
 use crate::{
-    material::{dielectric::Dielectric, lambertian::Lambertian, metal::Metal},
-    model::{camera::Camera, color::Color, hit::HittableList, sphere::Sphere},
+    material::{
+        bump_map::BumpMap,
+        coated::CoatedMaterial,
+        conductor::{Conductor, MetalPreset},
+        dielectric::Dielectric,
+        lambertian::Lambertian,
+        metal::Metal,
+        mix::MixMaterial,
+        normal_map::NormalMap,
+        phase_function::{
+            DoubleHenyeyGreenstein, HenyeyGreenstein, IsotropicPhase, PhaseFunction, Rayleigh,
+        },
+        principled::Principled,
+        rough_dielectric::RoughDielectric,
+    },
+    model::{color::Color, hit::HittableList, sphere::Sphere},
     util::rtweekend::PI,
 };
+mod camera;
+mod film;
+mod light;
 mod material;
 mod model;
+mod photon;
+mod sampler;
 mod texture;
 mod util;
 
+const MAX_DEPTH: i32 = 50;
+
 fn main() {
     // Image
     let mut ASPECT_RATIO: f64 = 16.0 / 9.0;
     let mut IMAGE_WIDTH: usize = 400;
     let mut SAMPLES_PER_PIXEL: usize = 100;
-    const MAX_DEPTH: i32 = 50;
 
     // World
     let mut world = HittableList::new();
@@ -51,6 +123,30 @@ fn main() {
     let mut vfov = 40.0;
     let mut aperture = 0.0;
     let mut background = Vec3::new(0.0, 0.0, 0.0);
+    let mut lights = HittableList::new();
+    let mut analytic_lights: Vec<Arc<dyn Light + Sync + Send>> = Vec::new();
+    let mut environment: Option<EnvironmentLight> = None;
+    let mut photon_mapping: Option<ProgressivePhotonMapping> = None;
+    let mut camera: Option<Arc<dyn Camera + Sync + Send>> = None;
+    let mut exposure: Option<Exposure> = None;
+    let mut animation: Option<(CameraPath, FrameRange)> = None;
+    // 0 draws every number independently; scenes may pick a low-discrepancy sampler below.
+    let mut sampling = 0;
+    // 0 averages the samples within each pixel; scenes may pick a wider filter below.
+    let mut filtering = 0;
+
+    // Textures are decoded on first use, at most a gigabyte of them at a time.
+    let textures = TextureManager::new().with_memory_budget(1 << 30);
+
+    // Texture filtering: 0 reads the texel under the lookup point.
+    let texture_filtering = 0;
+    let texture_filter = match texture_filtering {
+        1 => TextureFilter::Bilinear,
+        2 => TextureFilter::Bicubic,
+        3 => TextureFilter::Trilinear,
+        4 => TextureFilter::Ewa,
+        _ => TextureFilter::Nearest,
+    };
 
     let scene = 8;
     match scene {
@@ -69,7 +165,7 @@ fn main() {
             vfov = 20.0;
         }
         4 => {
-            world = earth();
+            world = earth(&textures, texture_filter);
             background = Vec3::new(0.7, 0.8, 1.0);
             lookfrom = Point3::new(13.0, 2.0, 3.0);
             lookat = Point3::new(0.0, 0.0, 0.0);
@@ -103,7 +199,7 @@ fn main() {
             vfov = 40.0;
         }
         8 => {
-            world = final_scene();
+            world = final_scene(&textures);
             ASPECT_RATIO = 1.0;
             IMAGE_WIDTH = 800;
             SAMPLES_PER_PIXEL = 10000;
@@ -112,6 +208,352 @@ fn main() {
             lookat = Point3::new(278.0, 278.0, 0.0);
             vfov = 40.0;
         }
+        9 => {
+            (world, lights) = caustics();
+            ASPECT_RATIO = 1.0;
+            IMAGE_WIDTH = 600;
+            SAMPLES_PER_PIXEL = 200;
+            background = Vec3::new(0.0, 0.0, 0.0);
+            lookfrom = Point3::new(278.0, 278.0, -800.0);
+            lookat = Point3::new(278.0, 278.0, 0.0);
+            vfov = 40.0;
+            photon_mapping = Some(ProgressivePhotonMapping::new(20, 200_000, 10.0));
+        }
+        10 => {
+            world = metals();
+            background = Vec3::new(0.7, 0.8, 1.0);
+            lookfrom = Point3::new(0.0, 3.0, 16.0);
+            lookat = Point3::new(0.0, 0.8, 0.0);
+            vfov = 30.0;
+        }
+        11 => {
+            world = frosted_glass();
+            background = Vec3::new(0.7, 0.8, 1.0);
+            lookfrom = Point3::new(0.0, 2.0, 12.0);
+            lookat = Point3::new(0.0, 0.8, 0.0);
+            vfov = 30.0;
+        }
+        12 => {
+            world = principled_spheres();
+            background = Vec3::new(0.7, 0.8, 1.0);
+            lookfrom = Point3::new(0.0, 3.0, 14.0);
+            lookat = Point3::new(0.0, 0.7, 0.0);
+            vfov = 30.0;
+        }
+        13 => {
+            world = layered_materials();
+            background = Vec3::new(0.7, 0.8, 1.0);
+            lookfrom = Point3::new(0.0, 3.0, 12.0);
+            lookat = Point3::new(0.0, 0.8, 0.0);
+            vfov = 30.0;
+        }
+        14 => {
+            world = surface_detail();
+            background = Vec3::new(0.7, 0.8, 1.0);
+            lookfrom = Point3::new(0.0, 3.0, 12.0);
+            lookat = Point3::new(0.0, 0.8, 0.0);
+            vfov = 30.0;
+        }
+        15 => {
+            world = cutouts(&textures);
+            background = Vec3::new(0.05, 0.06, 0.08);
+            lookfrom = Point3::new(0.0, 3.0, 12.0);
+            lookat = Point3::new(0.0, 1.0, 0.0);
+            vfov = 30.0;
+        }
+        16 => {
+            world = shaped_lights(&textures);
+            ASPECT_RATIO = 1.0;
+            IMAGE_WIDTH = 600;
+            SAMPLES_PER_PIXEL = 200;
+            background = Vec3::new(0.0, 0.0, 0.0);
+            lookfrom = Point3::new(278.0, 278.0, -800.0);
+            lookat = Point3::new(278.0, 278.0, 0.0);
+            vfov = 40.0;
+        }
+        17 => {
+            (world, analytic_lights) = light_rig();
+            background = Vec3::new(0.02, 0.03, 0.05);
+            lookfrom = Point3::new(0.0, 3.0, 12.0);
+            lookat = Point3::new(0.0, 0.8, 0.0);
+            vfov = 30.0;
+        }
+        18 => {
+            world = environment_lighting();
+            environment = Some(
+                EnvironmentLight::new(HdrImage::load("sky.hdr").expect("invalid HDR image"))
+                    .with_rotation(30.0)
+                    .with_intensity(0.8),
+            );
+            lookfrom = Point3::new(0.0, 3.0, 12.0);
+            lookat = Point3::new(0.0, 0.8, 0.0);
+            vfov = 30.0;
+        }
+        19 => {
+            world = random_scene();
+            let sky = PreethamSky::new(25.0, 120.0, 3.0)
+                .with_ground_albedo(&Vec3::new(0.4, 0.35, 0.3))
+                .with_intensity(0.02);
+            environment = Some(sky.environment(512, 256));
+            analytic_lights.push(Arc::new(sky.sun()));
+            lookfrom = Point3::new(13.0, 2.0, 3.0);
+            lookat = Point3::new(0.0, 0.0, 0.0);
+            vfov = 20.0;
+        }
+        20 => {
+            world = smoke_and_clouds();
+            background = Vec3::new(0.45, 0.55, 0.75);
+            analytic_lights.push(Arc::new(
+                DirectionalLight::new(&Vec3::new(1.0, 1.2, 0.6), &Vec3::new(3.0, 2.8, 2.5))
+                    .with_angular_diameter(1.0),
+            ));
+            lookfrom = Point3::new(0.0, 3.0, 14.0);
+            lookat = Point3::new(0.0, 1.8, 0.0);
+            vfov = 30.0;
+        }
+        21 => {
+            world = phase_functions();
+            background = Vec3::new(0.02, 0.02, 0.03);
+            analytic_lights.push(Arc::new(
+                DirectionalLight::new(&Vec3::new(0.0, 0.5, -1.0), &Vec3::new(4.0, 4.0, 4.0))
+                    .with_angular_diameter(1.0),
+            ));
+            lookfrom = Point3::new(0.0, 2.0, 14.0);
+            lookat = Point3::new(0.0, 1.2, 0.0);
+            vfov = 30.0;
+        }
+        22 => {
+            world = coloured_media();
+            background = Vec3::new(0.5, 0.55, 0.6);
+            lookfrom = Point3::new(0.0, 3.0, 12.0);
+            lookat = Point3::new(0.0, 1.2, 0.0);
+            vfov = 30.0;
+        }
+        23 => {
+            world = liquid_in_glass();
+            background = Vec3::new(0.5, 0.55, 0.6);
+            lookfrom = Point3::new(0.0, 4.0, 12.0);
+            lookat = Point3::new(0.0, 1.2, 0.0);
+            vfov = 25.0;
+        }
+        24 => {
+            world = environment_lighting();
+            environment = Some(
+                EnvironmentLight::new(HdrImage::load("sky.hdr").expect("invalid HDR image"))
+                    .with_rotation(30.0)
+                    .with_intensity(0.8),
+            );
+            ASPECT_RATIO = 2.0;
+            lookfrom = Point3::new(0.0, 1.0, 4.0);
+            lookat = Point3::new(0.0, 1.0, 0.0);
+            camera = Some(Arc::new(EquirectangularCamera::new(
+                &lookfrom,
+                &lookat,
+                &Vec3::new(0.0, 1.0, 0.0),
+                0.0,
+                1.0,
+            )));
+        }
+        25 => {
+            world = cornell_box();
+            ASPECT_RATIO = 1.0;
+            background = Vec3::new(0.0, 0.0, 0.0);
+            lookfrom = Point3::new(278.0, 278.0, 20.0);
+            lookat = Point3::new(278.0, 278.0, 555.0);
+            camera = Some(Arc::new(
+                FisheyeCamera::new(
+                    &lookfrom,
+                    &lookat,
+                    &Vec3::new(0.0, 1.0, 0.0),
+                    180.0,
+                    ASPECT_RATIO,
+                    0.0,
+                    1.0,
+                )
+                .with_projection(FisheyeProjection::Equisolid),
+            ));
+        }
+        26 => {
+            world = random_scene();
+            background = Vec3::new(0.7, 0.8, 1.0);
+            lookfrom = Point3::new(13.0, 13.0, 13.0);
+            lookat = Point3::new(0.0, 0.0, 0.0);
+            camera = Some(Arc::new(OrthographicCamera::new(
+                &lookfrom,
+                &lookat,
+                &Vec3::new(0.0, 1.0, 0.0),
+                12.0,
+                ASPECT_RATIO,
+                0.0,
+                1.0,
+            )));
+        }
+        27 => {
+            world = bokeh();
+            background = Vec3::new(0.01, 0.01, 0.02);
+            lookfrom = Point3::new(0.0, 1.0, 6.0);
+            lookat = Point3::new(0.0, 1.0, 0.0);
+            vfov = 30.0;
+            camera = Some(Arc::new(
+                PerspectiveCamera::new(
+                    &lookfrom,
+                    &lookat,
+                    &Vec3::new(0.0, 1.0, 0.0),
+                    vfov,
+                    ASPECT_RATIO,
+                )
+                .with_lens(0.4, 6.0)
+                .with_aperture(Arc::new(PolygonAperture::new(6, 15.0)))
+                .with_vignetting(1.2),
+            ));
+        }
+        28 => {
+            world = random_scene();
+            background = Vec3::new(0.7, 0.8, 1.0);
+            lookfrom = Point3::new(13.0, 9.0, 3.0);
+            lookat = Point3::new(0.0, 0.0, 0.0);
+            vfov = 30.0;
+            // Tilting the focus against the ground leaves a thin sharp band, which
+            // makes the scene look like a miniature.
+            camera = Some(Arc::new(
+                PerspectiveCamera::new(
+                    &lookfrom,
+                    &lookat,
+                    &Vec3::new(0.0, 1.0, 0.0),
+                    vfov,
+                    ASPECT_RATIO,
+                )
+                .with_lens(1.0, (lookfrom - lookat).length())
+                .with_tilt(-25.0, 0.0)
+                .with_shift(0.0, -0.1),
+            ));
+        }
+        29 => {
+            world = bokeh();
+            background = Vec3::new(0.01, 0.01, 0.02);
+            lookfrom = Point3::new(0.0, 1.0, 6.0);
+            lookat = Point3::new(0.0, 1.0, 0.0);
+            vfov = 30.0;
+            let leaf = Arc::new(textures.texture("leaf.png", ColorSpace::Linear));
+            camera = Some(Arc::new(
+                PerspectiveCamera::new(
+                    &lookfrom,
+                    &lookat,
+                    &Vec3::new(0.0, 1.0, 0.0),
+                    vfov,
+                    ASPECT_RATIO,
+                )
+                .with_lens(0.4, 6.0)
+                .with_aperture(Arc::new(ImageAperture::new(
+                    Arc::new(AlphaChannel::new(leaf)),
+                    64,
+                ))),
+            ));
+        }
+        30 => {
+            // Daylight in physical units, exposed by the sunny 16 rule.
+            world = random_scene();
+            let sky = PreethamSky::new(40.0, 120.0, 3.0)
+                .with_ground_albedo(&Vec3::new(0.4, 0.35, 0.3))
+                .with_intensity(1000.0);
+            environment = Some(sky.environment(512, 256));
+            analytic_lights.push(Arc::new(sky.sun()));
+            // Pulled back a third of a stop to hold the highlights on the metal.
+            exposure = Some(Exposure::new(16.0, 1.0 / 100.0, 100.0).with_compensation(-1.0 / 3.0));
+            lookfrom = Point3::new(13.0, 2.0, 3.0);
+            lookat = Point3::new(0.0, 0.0, 0.0);
+            vfov = 20.0;
+        }
+        31 => {
+            // Two second orbit around the spheres, written out as numbered frames.
+            world = random_scene();
+            background = Vec3::new(0.7, 0.8, 1.0);
+            lookfrom = Point3::new(13.0, 2.0, 3.0);
+            lookat = Point3::new(0.0, 0.0, 0.0);
+            let keyframes = vec![
+                Keyframe::new(0.0, lookfrom, lookat, 20.0, 10.0),
+                Keyframe::new(0.7, Point3::new(6.0, 3.0, 11.0), lookat, 25.0, 10.0),
+                Keyframe::new(1.4, Point3::new(-6.0, 4.0, 10.0), lookat, 30.0, 10.0),
+                Keyframe::new(
+                    2.0,
+                    Point3::new(-12.0, 2.0, 3.0),
+                    Point3::new(0.0, 1.0, 0.0),
+                    30.0,
+                    12.0,
+                ),
+            ];
+            // A wide open shutter so the rising spheres smear visibly.
+            animation = Some((
+                CameraPath::new(keyframes, 0.1),
+                FrameRange::new(1, 48, 24.0).with_shutter_angle(360.0),
+            ));
+        }
+        32 => {
+            // Side by side stereo, with the big glass sphere at screen depth.
+            world = random_scene();
+            background = Vec3::new(0.7, 0.8, 1.0);
+            ASPECT_RATIO = 32.0 / 9.0;
+            lookfrom = Point3::new(13.0, 2.0, 3.0);
+            lookat = Point3::new(0.0, 0.0, 0.0);
+            camera = Some(Arc::new(
+                StereoCamera::new(
+                    &lookfrom,
+                    &lookat,
+                    &Vec3::new(0.0, 1.0, 0.0),
+                    20.0,
+                    ASPECT_RATIO,
+                    0.3,
+                )
+                .with_convergence(10.0),
+            ));
+        }
+        33 => {
+            world = cornell_box();
+            ASPECT_RATIO = 1.0;
+            background = Vec3::new(0.0, 0.0, 0.0);
+            lookfrom = Point3::new(278.0, 278.0, -800.0);
+            lookat = Point3::new(278.0, 278.0, 0.0);
+            camera = Some(Arc::new(
+                StereoCamera::new(
+                    &lookfrom,
+                    &lookat,
+                    &Vec3::new(0.0, 1.0, 0.0),
+                    40.0,
+                    ASPECT_RATIO,
+                    40.0,
+                )
+                .with_layout(StereoLayout::TopBottom)
+                .with_toe_in(),
+            ));
+        }
+        34 => {
+            world = environment_lighting();
+            environment = Some(
+                EnvironmentLight::new(HdrImage::load("sky.hdr").expect("invalid HDR image"))
+                    .with_rotation(30.0)
+                    .with_intensity(0.8),
+            );
+            ASPECT_RATIO = 1.0;
+            lookfrom = Point3::new(0.0, 1.0, 4.0);
+            lookat = Point3::new(0.0, 1.0, 0.0);
+            camera = Some(Arc::new(OdsCamera::new(
+                &lookfrom,
+                &lookat,
+                &Vec3::new(0.0, 1.0, 0.0),
+                0.064,
+                0.0,
+                1.0,
+            )));
+        }
+        35 => {
+            world = tiled_earth(&textures, TextureFilter::Ewa);
+            background = Vec3::new(0.7, 0.8, 1.0);
+            lookfrom = Point3::new(0.0, 1.0, 12.0);
+            lookat = Point3::new(0.0, 0.0, 0.0);
+            vfov = 30.0;
+            sampling = 3;
+            filtering = 2;
+        }
         _ => {
             world = random_scene();
             background = Vec3::new(0.7, 0.8, 1.0);
@@ -122,65 +564,229 @@ fn main() {
         }
     }
 
+    // Sampling
+    let sampler: Arc<dyn Sampler + Sync + Send> = match sampling {
+        1 => Arc::new(StratifiedSampler::new(SAMPLES_PER_PIXEL)),
+        2 => Arc::new(HaltonSampler),
+        3 => Arc::new(SobolSampler::new(0)),
+        4 => Arc::new(BlueNoiseSampler::new()),
+        _ => Arc::new(IndependentSampler),
+    };
+
+    // Reconstruction
+    let filter: Arc<dyn Filter + Sync + Send> = match filtering {
+        1 => Arc::new(TentFilter::new(1.0)),
+        2 => Arc::new(GaussianFilter::new(1.5, 0.5)),
+        3 => Arc::new(MitchellFilter::new(2.0)),
+        4 => Arc::new(MitchellFilter::new(2.0).with_parameters(0.0, 0.5)),
+        5 => Arc::new(LanczosFilter::new(3.0)),
+        _ => Arc::new(BoxFilter::new(0.5)),
+    };
+
     let IMAGE_HEIGHT: usize = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize;
 
     // Camera
     let vup = Vec3::new(0.0, 1.0, 0.0);
     let dist_to_focus = 10.0;
     let aperture = 0.1;
-    let camera = Camera::new(
-        &lookfrom,
-        &lookat,
-        &vup,
-        vfov,
-        ASPECT_RATIO,
-        aperture,
-        dist_to_focus,
-        0.0,
-        1.0,
-    );
-
-    // Render
-    print!("P3\n{} {}\n255\n", IMAGE_WIDTH, IMAGE_HEIGHT);
-
-    let mut result = vec!["".to_owned(); IMAGE_WIDTH * IMAGE_HEIGHT];
-    let result = Mutex::new(result);
-    let mut v = vec![];
-    for i in 0..IMAGE_WIDTH {
-        v.push(i);
-    }
-
-    for j in (0..IMAGE_HEIGHT).rev() {
-        eprint!("\rScanlines remaining: {} ", j);
-        io::stderr().flush().unwrap();
-
-        v.clone().into_par_iter().for_each(|x| {
-            let mut pixel_color = Vec3::new(0.0, 0.0, 0.0);
-            for s in 0..SAMPLES_PER_PIXEL {
-                let u = (x as f64 + random_double()) / (IMAGE_WIDTH as f64 - 1.0);
-                let v = (j as f64 + random_double()) / (IMAGE_HEIGHT as f64 - 1.0);
-                let r = camera.get_ray(u, v);
-                pixel_color += ray_color(&r, &background, &world, MAX_DEPTH);
+    let camera = camera.unwrap_or_else(|| {
+        let camera = PerspectiveCamera::new(&lookfrom, &lookat, &vup, vfov, ASPECT_RATIO)
+            .with_lens(aperture, dist_to_focus);
+        match exposure.as_ref() {
+            Some(exposure) => Arc::new(camera.with_exposure(exposure)),
+            None => Arc::new(camera),
+        }
+    });
+    let exposure_scale = exposure.as_ref().map_or(1.0, Exposure::scale);
+
+    // Render either the still on stdout or every frame of the animation to its own file.
+    let shots: Vec<(Arc<dyn Camera + Sync + Send>, Option<String>)> = match animation {
+        Some((path, frames)) => (frames.first..=frames.last)
+            .map(|frame| {
+                let (time0, time1) = frames.shutter(frame);
+                let camera: Arc<dyn Camera + Sync + Send> =
+                    Arc::new(path.camera(time0, time1, ASPECT_RATIO));
+                (camera, Some(FrameRange::filename(frame)))
+            })
+            .collect(),
+        None => vec![(camera, None)],
+    };
+
+    for (camera, filename) in shots {
+        let mut film = Film::new(IMAGE_WIDTH, IMAGE_HEIGHT, filter.clone());
+        let passes = photon_mapping.as_ref().map_or(1, |ppm| ppm.passes.max(1));
+        let mut first_sample = 0;
+
+        for pass in 0..passes {
+            let caustics = photon_mapping
+                .as_ref()
+                .map(|ppm| ppm.photon_map(pass, &world, &lights));
+            if let Some(caustics) = caustics.as_ref() {
+                eprintln!(
+                    "\rPass {}/{}: {} caustic photons, radius {:.3}",
+                    pass + 1,
+                    passes,
+                    caustics.len(),
+                    caustics.radius
+                );
             }
 
-            let s = pixel_color.as_color_repr(SAMPLES_PER_PIXEL);
-            let mut result = result.lock().unwrap();
-            result[x * IMAGE_HEIGHT + j] = s.clone();
-        });
-    }
+            let render_scene = Scene {
+                world: &world,
+                background,
+                environment: environment.as_ref(),
+                lights: &analytic_lights,
+                caustics: caustics.as_ref(),
+            };
+
+            // Spread the samples over the passes, giving the remainder to the first ones.
+            let samples =
+                SAMPLES_PER_PIXEL / passes + usize::from(pass < SAMPLES_PER_PIXEL % passes);
+
+            for j in (0..IMAGE_HEIGHT).rev() {
+                eprint!("\rScanlines remaining: {} ", j);
+                io::stderr().flush().unwrap();
+
+                // Trace the scanline in parallel, each thread splatting its samples
+                // into a tile of the film around the scanline, then merge the tiles.
+                let tile = (0..IMAGE_WIDTH)
+                    .into_par_iter()
+                    .fold(
+                        || film.tile(0..IMAGE_WIDTH, j..j + 1),
+                        |mut tile, x| {
+                            for index in first_sample..first_sample + samples {
+                                let (film_x, film_y, color) = render_sample(
+                                    camera.as_ref(),
+                                    &render_scene,
+                                    sampler.as_ref(),
+                                    (x, j),
+                                    index,
+                                    (IMAGE_WIDTH, IMAGE_HEIGHT),
+                                );
+                                tile.add_sample(film_x, film_y, &color);
+                            }
+                            tile
+                        },
+                    )
+                    .reduce(
+                        || film.tile(0..IMAGE_WIDTH, j..j + 1),
+                        |mut a, b| {
+                            a.merge(&b);
+                            a
+                        },
+                    );
+                film.merge(&tile);
+            }
+            first_sample += samples;
+        }
 
-    let res = result.lock().unwrap();
-    for j in (0..IMAGE_HEIGHT).rev() {
-        for i in 0..IMAGE_WIDTH {
-            let index = i * IMAGE_HEIGHT + j;
-            print!("{}", res[index]);
+        match filename {
+            Some(filename) => {
+                let rgb: Vec<u8> = (0..IMAGE_HEIGHT)
+                    .rev()
+                    .flat_map(|j| (0..IMAGE_WIDTH).map(move |i| (i, j)))
+                    .flat_map(|(i, j)| (exposure_scale * film.pixel(i, j)).as_rgb8(1))
+                    .collect();
+                write_png(&filename, IMAGE_WIDTH, IMAGE_HEIGHT, &rgb).expect("cannot write frame");
+                eprintln!("\nWrote {}", filename);
+            }
+            None => {
+                print!("P3\n{} {}\n255\n", IMAGE_WIDTH, IMAGE_HEIGHT);
+                for j in (0..IMAGE_HEIGHT).rev() {
+                    for i in 0..IMAGE_WIDTH {
+                        print!("{}", (exposure_scale * film.pixel(i, j)).as_color_repr(1));
+                    }
+                }
+            }
         }
     }
 
+    let memory = textures.memory();
+    if memory.images > 0 {
+        eprintln!(
+            "\nTextures: {} of {} images decoded, {:.1} MiB in memory, {} tiles read back",
+            memory.decoded,
+            memory.images,
+            memory.bytes as f64 / (1 << 20) as f64,
+            memory.reloads
+        );
+    }
     eprintln!("\nDone.");
 }
 
-fn ray_color(r: &Ray, background: &Vec3, world: &dyn Hittable, depth: i32) -> Vec3 {
+/// Where a path stands with respect to caustics: once a diffuse bounce is followed by
+/// specular ones, any light reached is already accounted for by the caustic photon map.
+#[derive(Clone, Copy, PartialEq)]
+enum PathState {
+    Camera,
+    Diffuse,
+    Caustic,
+}
+
+/// Everything a path needs to know about the scene besides the ray itself.
+struct Scene<'a> {
+    world: &'a (dyn Hittable + Sync + Send),
+    background: Vec3,
+    environment: Option<&'a EnvironmentLight>,
+    lights: &'a [Arc<dyn Light + Sync + Send>],
+    caustics: Option<&'a PhotonMap>,
+}
+
+/// Traces sample `index` of the pixel at `pixel`, drawing its random numbers from
+/// `sampler`, in an image of `size` pixels. Returns where on the film the sample was
+/// taken, in pixels, along with its colour.
+fn render_sample(
+    camera: &(dyn Camera + Sync + Send),
+    scene: &Scene,
+    sampler: &(dyn Sampler + Sync + Send),
+    pixel: (usize, usize),
+    index: usize,
+    size: (usize, usize),
+) -> (f64, f64, Vec3) {
+    let (x, y) = pixel;
+    let (width, height) = size;
+    let mut state = PixelSample::new(sampler, x, y, index);
+
+    let (jitter_x, jitter_y) = state.next_2d();
+    let film_x = x as f64 + jitter_x;
+    let film_y = y as f64 + jitter_y;
+    let u = film_x / (width as f64 - 1.0);
+    let v = film_y / (height as f64 - 1.0);
+    // Rays through the next pixels over, to estimate the footprint of the first.
+    let ray = camera.get_ray_differential(
+        u,
+        v,
+        1.0 / (width as f64 - 1.0),
+        1.0 / (height as f64 - 1.0),
+        &mut state,
+    );
+    let color = match ray {
+        Some(r) => ray_color(
+            &r,
+            scene,
+            MAX_DEPTH,
+            PathState::Camera,
+            None,
+            &MediumStack::new(),
+            &mut state,
+        ),
+        None => Vec3::new(0.0, 0.0, 0.0),
+    };
+    (film_x, film_y, color)
+}
+
+/// `scatter_pdf` is the density with which the previous bounce picked `r`, if the
+/// environment could also have been sampled in that direction, `media` holds the
+/// refractive objects the ray travels inside and `sampler` the path's random numbers.
+fn ray_color(
+    r: &Ray,
+    scene: &Scene,
+    depth: i32,
+    state: PathState,
+    scatter_pdf: Option<f64>,
+    media: &MediumStack,
+    sampler: &mut dyn SamplerState,
+) -> Vec3 {
     let mut rec = HitRecord::default();
 
     // If we've exceeded the ray bounce limit, no more light is gathered.
@@ -188,23 +794,197 @@ fn ray_color(r: &Ray, background: &Vec3, world: &dyn Hittable, depth: i32) -> Ve
         return Vec3::new(0.0, 0.0, 0.0);
     }
 
-    // If the ray hits nothing, return the background color
-    if !world.hit(r, 0.001, INFINITY, &mut rec) {
-        return background.clone();
+    // If the ray hits nothing, return the background color and any distant light
+    // it looks into, such as the disk of the sun.
+    if !scene.world.hit(r, 0.001, INFINITY, &mut rec, sampler) {
+        let mut background = match scene.environment {
+            Some(environment) => {
+                let weight =
+                    scatter_pdf.map_or(1.0, |pdf| power_heuristic(pdf, environment.pdf(r.dir())));
+                weight * environment.radiance(r.dir())
+            }
+            None => scene.background,
+        };
+        for light in scene.lights {
+            let radiance = light.radiance(r.dir());
+            if !radiance.near_zero() {
+                let weight =
+                    scatter_pdf.map_or(1.0, |pdf| power_heuristic(pdf, light.pdf(r.dir())));
+                background += weight * radiance;
+            }
+        }
+        return scene.world.medium_weight(r, 0.001, INFINITY, None, sampler) * background;
+    }
+    rec.set_differentials(r);
+
+    // Chromatic media cannot sample free paths that suit every channel at once, and
+    // the interior the ray travels in may absorb some of the light.
+    let weight = scene
+        .world
+        .medium_weight(r, 0.001, rec.t, Some(&rec), sampler)
+        * media.transmittance(rec.t * r.dir().length());
+
+    // Surfaces of objects overlapped by an interior of higher priority are skipped,
+    // without counting as a bounce.
+    let interior = rec.material.interior();
+    if let Some(interior) = interior.as_ref() {
+        let id = MediumStack::id(&rec);
+        match media.exterior_ior(id, interior) {
+            Some(ior) => rec.exterior_ior = ior,
+            None => {
+                let mut next_media = *media;
+                next_media.cross(id, interior, rec.front_face);
+                let continued = Ray::new(&rec.p, r.dir(), r.time());
+                return weight
+                    * ray_color(
+                        &continued,
+                        scene,
+                        depth,
+                        state,
+                        scatter_pdf,
+                        &next_media,
+                        sampler,
+                    );
+            }
+        }
     }
 
     let mut scattered = Ray::new(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 0.0), 0.0);
     let mut attenuation = Vec3::new(0.0, 0.0, 0.0);
-    let emitted = rec.material.emitted(rec.u, rec.v, &rec.p);
+    let emitted = if scene.caustics.is_some() && state == PathState::Caustic {
+        Vec3::new(0.0, 0.0, 0.0)
+    } else {
+        rec.material.emitted(r, &rec, rec.u, rec.v, &rec.p)
+    };
+
+    sampler.start_bounce((MAX_DEPTH - depth) as usize);
+    let direct = direct_lighting(r, &rec, scene, sampler);
 
     if !rec
         .material
-        .scatter(r, &rec, &mut attenuation, &mut scattered)
+        .scatter(r, &rec, &mut attenuation, &mut scattered, sampler)
     {
-        return emitted;
+        return weight * (emitted + direct);
+    }
+
+    let mut caustic = Vec3::new(0.0, 0.0, 0.0);
+    let next_state = if rec.material.is_specular() {
+        if state == PathState::Camera {
+            PathState::Camera
+        } else {
+            PathState::Caustic
+        }
+    } else if rec.material.is_volume() {
+        // Photons are not stored in media, so light reached through specular bounces
+        // from here on is left to the path.
+        PathState::Camera
+    } else {
+        if let Some(caustics) = scene.caustics {
+            caustic = caustics.estimate(r, &rec);
+        }
+        PathState::Diffuse
+    };
+
+    // Refracted rays enter or leave the object's interior.
+    let mut next_media = *media;
+    if let Some(interior) = interior.as_ref() {
+        if scattered.dir().dot(&rec.normal) < 0.0 {
+            next_media.cross(MediumStack::id(&rec), interior, rec.front_face);
+        }
     }
 
-    return emitted + attenuation * ray_color(&scattered, background, world, depth - 1);
+    // Directions that light sampling covers too are weighted against it once they escape.
+    let mut next_pdf = None;
+    if scene.environment.is_some() || !scene.lights.is_empty() {
+        let direction = scattered.dir().unit_vector();
+        if !rec.material.eval(r, &rec, &direction).near_zero() {
+            next_pdf = Some(rec.material.pdf(r, &rec, &direction));
+        }
+    }
+
+    return weight
+        * (emitted
+            + direct
+            + caustic
+            + attenuation
+                * ray_color(
+                    &scattered,
+                    scene,
+                    depth - 1,
+                    next_state,
+                    next_pdf,
+                    &next_media,
+                    sampler,
+                ));
+}
+
+/// Light reaching `rec` straight from the analytic lights, which paths cannot hit by
+/// chance, and from the environment, combined with scattered rays by multiple
+/// importance sampling.
+fn direct_lighting(
+    r: &Ray,
+    rec: &HitRecord,
+    scene: &Scene,
+    sampler: &mut dyn SamplerState,
+) -> Vec3 {
+    let mut result = Vec3::new(0.0, 0.0, 0.0);
+    for light in scene.lights {
+        let mut sample = LightSample::default();
+        if !light.sample(&rec.p, &mut sample, sampler) {
+            continue;
+        }
+
+        let f = rec.material.eval(r, rec, &sample.direction);
+        if !f.near_zero() {
+            // Delta lights, of zero density, can only be reached this way.
+            let weight = if sample.pdf > 0.0 {
+                power_heuristic(sample.pdf, rec.material.pdf(r, rec, &sample.direction))
+            } else {
+                1.0
+            };
+            result +=
+                weight * f * transmittance(scene, rec, &sample, r.time(), sampler) * sample.li;
+        }
+    }
+
+    if let Some(environment) = scene.environment {
+        let mut sample = LightSample::default();
+        if environment.sample(&mut sample, sampler) {
+            let f = rec.material.eval(r, rec, &sample.direction);
+            if !f.near_zero() {
+                let weight =
+                    power_heuristic(sample.pdf, rec.material.pdf(r, rec, &sample.direction));
+                result +=
+                    weight * f * transmittance(scene, rec, &sample, r.time(), sampler) * sample.li;
+            }
+        }
+    }
+
+    result
+}
+
+/// Fraction of the light from `sample` reaching `rec.p`: zero behind opaque surfaces and
+/// attenuated through participating media.
+fn transmittance(
+    scene: &Scene,
+    rec: &HitRecord,
+    sample: &LightSample,
+    time: f64,
+    sampler: &mut dyn SamplerState,
+) -> Vec3 {
+    let shadow_ray = Ray::new(&rec.p, &sample.direction, time);
+    scene
+        .world
+        .transmittance(&shadow_ray, 0.001, sample.distance - 0.001, sampler)
+}
+
+/// Weight of a sample drawn with density `f` against another strategy with density `g`.
+fn power_heuristic(f: f64, g: f64) -> f64 {
+    if f <= 0.0 {
+        return 0.0;
+    }
+
+    f * f / (f * f + g * g)
 }
 
 fn random_scene() -> HittableList {
@@ -334,10 +1114,16 @@ fn two_perlin_spheres() -> HittableList {
     world
 }
 
-fn earth() -> HittableList {
+fn earth(textures: &TextureManager, filter: TextureFilter) -> HittableList {
     let mut world = HittableList::new();
 
-    let earth_texture = Arc::new(ImageTexture::new("earthmap.jpg".to_owned()));
+    // The map wraps around the globe, so filters reach across the seam.
+    let earth_texture = Arc::new(
+        textures
+            .texture("earthmap.jpg", ColorSpace::Srgb)
+            .with_filter(filter)
+            .with_wrap(WrapMode::Repeat),
+    );
     let earth_surface = Arc::new(Lambertian::new_with_texture(earth_texture));
     let globe = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 2.0, earth_surface));
 
@@ -346,6 +1132,31 @@ fn earth() -> HittableList {
     world
 }
 
+/// A globe on a floor tiled with mirrored copies of its map, which recede to the
+/// horizon and alias badly without a filter that follows the pixel footprint.
+fn tiled_earth(textures: &TextureManager, filter: TextureFilter) -> HittableList {
+    let mut world = earth(textures, filter);
+
+    let tiles = Arc::new(
+        textures
+            .texture("earthmap.jpg", ColorSpace::Srgb)
+            .with_filter(filter)
+            .with_wrap(WrapMode::Mirror)
+            .with_scale(50.0, 100.0)
+            .with_offset(0.5, 0.0),
+    );
+    world.add(Arc::new(XzRect::new(
+        -100.0,
+        100.0,
+        -100.0,
+        100.0,
+        -2.0,
+        Arc::new(Lambertian::new_with_texture(tiles)),
+    )));
+
+    world
+}
+
 fn simple_light() -> HittableList {
     let mut world = HittableList::new();
 
@@ -507,7 +1318,7 @@ fn cornell_smoke() -> HittableList {
     world
 }
 
-fn final_scene() -> HittableList {
+fn final_scene(textures: &TextureManager) -> HittableList {
     let mut boxes1 = HittableList::new();
     let ground = Arc::new(Lambertian::new(&Vec3::new(0.48, 0.83, 0.53)));
 
@@ -589,9 +1400,9 @@ fn final_scene() -> HittableList {
         Vec3::new(1.0, 1.0, 1.0),
     )));
 
-    let emat = Arc::new(Lambertian::new_with_texture(Arc::new(ImageTexture::new(
-        "earthmap.jpg".to_owned(),
-    ))));
+    let emat = Arc::new(Lambertian::new_with_texture(Arc::new(
+        textures.texture("earthmap.jpg", ColorSpace::Srgb),
+    )));
     world.add(Arc::new(Sphere::new(
         Point3::new(400.0, 200.0, 400.0),
         100.0,
@@ -625,3 +1436,951 @@ fn final_scene() -> HittableList {
 
     world
 }
+
+fn caustics() -> (HittableList, HittableList) {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+
+    let red = Arc::new(Lambertian::new(&Vec3::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(&Vec3::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(&Vec3::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new_with_color(Vec3::new(15.0, 15.0, 15.0)));
+
+    world.add(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
+    world.add(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
+    let ceiling_light = Arc::new(XzRect::new(213.0, 343.0, 227.0, 332.0, 554.0, light));
+    world.add(ceiling_light.clone());
+    lights.add(ceiling_light);
+    world.add(Arc::new(XzRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+        white.clone(),
+    )));
+    world.add(Arc::new(XzRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+    world.add(Arc::new(XyRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(190.0, 90.0, 190.0),
+        90.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(390.0, 90.0, 330.0),
+        90.0,
+        Arc::new(Metal::new(&Vec3::new(0.8, 0.85, 0.88), 0.0)),
+    )));
+
+    (world, lights)
+}
+
+fn metals() -> HittableList {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.2, 0.3, 0.1),
+        &Vec3::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new_with_texture(checker)),
+    )));
+
+    // Front row: measured presets at increasing roughness.
+    let presets = [
+        MetalPreset::Gold,
+        MetalPreset::Silver,
+        MetalPreset::Copper,
+        MetalPreset::Aluminium,
+        MetalPreset::Chromium,
+        MetalPreset::Iron,
+        MetalPreset::Titanium,
+    ];
+    for (i, preset) in presets.iter().enumerate() {
+        let roughness = 0.05 + 0.1 * i as f64;
+        world.add(Arc::new(Sphere::new(
+            Point3::new(-4.8 + 1.6 * i as f64, 0.7, 2.0),
+            0.7,
+            Arc::new(Conductor::new_with_preset(*preset, roughness)),
+        )));
+    }
+
+    // Back row: the old fuzzy metal next to isotropic and brushed (anisotropic) gold.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-3.0, 1.0, -1.0),
+        1.0,
+        Arc::new(Metal::new(&Vec3::new(0.8, 0.6, 0.2), 0.3)),
+    )));
+    let (eta, k) = MetalPreset::Gold.ior();
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, -1.0),
+        1.0,
+        Arc::new(Conductor::new(eta, k, 0.3)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(3.0, 1.0, -1.0),
+        1.0,
+        Arc::new(Conductor::new_anisotropic(eta, k, 0.05, 0.5)),
+    )));
+
+    world
+}
+
+fn frosted_glass() -> HittableList {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.2, 0.3, 0.1),
+        &Vec3::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new_with_texture(checker)),
+    )));
+
+    // Smooth glass for reference, then increasingly frosted glass.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.5, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-2.25, 1.0, 0.0),
+        1.0,
+        Arc::new(RoughDielectric::new(1.5, 0.1)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(RoughDielectric::new(1.5, 0.4)),
+    )));
+
+    // Tinted glass: green after travelling one radius inside.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(2.25, 1.0, 0.0),
+        1.0,
+        Arc::new(RoughDielectric::new_tinted(
+            1.5,
+            Arc::new(SolidColor::new_with_values(0.05, 0.05, 0.05)),
+            &Vec3::new(0.3, 0.8, 0.4),
+            1.0,
+        )),
+    )));
+
+    // Roughness driven by a texture: frosted patterns on clear glass.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.5, 1.0, 0.0),
+        1.0,
+        Arc::new(RoughDielectric::new_with_texture(
+            1.5,
+            Arc::new(NoiseTexture::new(3.0)),
+        )),
+    )));
+
+    world
+}
+
+fn principled_spheres() -> HittableList {
+    let mut world = HittableList::new();
+    let value = |x: f64| -> Arc<dyn Texture + Sync + Send> {
+        Arc::new(SolidColor::new_with_values(x, x, x))
+    };
+
+    let checker = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.2, 0.3, 0.1),
+        &Vec3::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new_with_texture(checker)),
+    )));
+
+    // Back row: each simple material next to the principled set up to match it.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.2, 0.65, 0.0),
+        0.65,
+        Arc::new(Lambertian::new(&Vec3::new(0.8, 0.3, 0.2))),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-0.7, 0.65, 0.0),
+        0.65,
+        Arc::new(Metal::new(&Vec3::new(0.9, 0.8, 0.6), 0.1)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(2.8, 0.65, 0.0),
+        0.65,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-2.8, 0.65, 0.0),
+        0.65,
+        Arc::new(
+            Principled::new(&Vec3::new(0.8, 0.3, 0.2))
+                .with_roughness(value(1.0))
+                .with_specular(value(0.0)),
+        ),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.7, 0.65, 0.0),
+        0.65,
+        Arc::new(
+            Principled::new(&Vec3::new(0.9, 0.8, 0.6))
+                .with_metallic(value(1.0))
+                .with_roughness(value(0.2)),
+        ),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.2, 0.65, 0.0),
+        0.65,
+        Arc::new(
+            Principled::new(&Vec3::new(1.0, 1.0, 1.0))
+                .with_transmission(value(1.0))
+                .with_roughness(value(0.0))
+                .with_ior(1.5),
+        ),
+    )));
+
+    // Front row: what only the principled material can do.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-3.0, 0.5, 2.5),
+        0.5,
+        Arc::new(
+            Principled::new(&Vec3::new(0.6, 0.05, 0.05))
+                .with_roughness(value(0.6))
+                .with_clearcoat(value(1.0)),
+        ),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-1.0, 0.5, 2.5),
+        0.5,
+        Arc::new(
+            Principled::new(&Vec3::new(0.1, 0.1, 0.5))
+                .with_roughness(value(0.9))
+                .with_sheen(value(1.0)),
+        ),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(1.0, 0.5, 2.5),
+        0.5,
+        Arc::new(
+            Principled::new_with_texture(Arc::new(NoiseTexture::new(4.0)))
+                .with_metallic(Arc::new(NoiseTexture::new(4.0)))
+                .with_roughness(value(0.3)),
+        ),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(3.0, 0.5, 2.5),
+        0.5,
+        Arc::new(
+            Principled::new(&Vec3::new(0.2, 0.2, 0.2))
+                .with_emission(Arc::new(SolidColor::new(&Vec3::new(2.0, 1.2, 0.4)))),
+        ),
+    )));
+
+    world
+}
+
+fn layered_materials() -> HittableList {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.2, 0.3, 0.1),
+        &Vec3::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new_with_texture(checker.clone())),
+    )));
+
+    // Rust over iron, driven by marble-like noise.
+    let iron = Arc::new(Conductor::new_with_preset(MetalPreset::Iron, 0.2));
+    let rust = Arc::new(Lambertian::new(&Vec3::new(0.45, 0.18, 0.06)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-3.0, 1.0, 0.0),
+        1.0,
+        Arc::new(MixMaterial::new(
+            iron,
+            rust,
+            Arc::new(NoiseTexture::new(3.0)),
+        )),
+    )));
+
+    // Clear coat over diffuse red, smooth and rough.
+    let red = Arc::new(Lambertian::new(&Vec3::new(0.7, 0.05, 0.05)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-1.0, 1.0, 0.0),
+        1.0,
+        Arc::new(CoatedMaterial::new(red.clone(), 1.5)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(1.0, 1.0, 0.0),
+        1.0,
+        Arc::new(CoatedMaterial::new_with_roughness(red, 1.5, 0.3)),
+    )));
+
+    // Amber varnish over a checkered base.
+    world.add(Arc::new(Sphere::new(
+        Point3::new(3.0, 1.0, 0.0),
+        1.0,
+        Arc::new(
+            CoatedMaterial::new(Arc::new(Lambertian::new_with_texture(checker)), 1.5)
+                .with_tint(&Vec3::new(0.9, 0.6, 0.2), 0.5),
+        ),
+    )));
+
+    world
+}
+
+fn surface_detail() -> HittableList {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.2, 0.3, 0.1),
+        &Vec3::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new_with_texture(checker)),
+    )));
+
+    // Bump maps from procedural noise, over a diffuse and a metallic base.
+    let bumps = Arc::new(NoiseTexture::new(4.0));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-3.0, 1.0, 0.0),
+        1.0,
+        Arc::new(BumpMap::new(
+            Arc::new(Lambertian::new(&Vec3::new(0.8, 0.8, 0.8))),
+            bumps.clone(),
+            0.05,
+        )),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-1.0, 1.0, 0.0),
+        1.0,
+        Arc::new(BumpMap::new(
+            Arc::new(Conductor::new_with_preset(MetalPreset::Gold, 0.2)),
+            bumps.clone(),
+            0.02,
+        )),
+    )));
+
+    // Any texture can serve as a tangent-space normal map: here alternating facets.
+    let facets = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.5, 0.5, 1.0),
+        &Vec3::new(0.85, 0.5, 0.8),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(1.0, 1.0, 0.0),
+        1.0,
+        Arc::new(
+            NormalMap::new(Arc::new(Principled::new(&Vec3::new(0.2, 0.3, 0.7))), facets)
+                .with_strength(1.5),
+        ),
+    )));
+
+    // Boxes and rects carry a tangent frame too.
+    let bumpy_box: Arc<dyn Hittable + Sync + Send> = Arc::new(Box::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        &Point3::new(1.4, 1.4, 1.4),
+        Arc::new(BumpMap::new(
+            Arc::new(Lambertian::new(&Vec3::new(0.7, 0.4, 0.2))),
+            bumps,
+            0.01,
+        )),
+    ));
+    world.add(Arc::new(Translate::new(
+        Arc::new(RotateY::new(bumpy_box, 30.0)),
+        &Vec3::new(2.6, 0.0, -0.5),
+    )));
+
+    world
+}
+
+fn cutouts(textures: &TextureManager) -> HittableList {
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(&Vec3::new(0.6, 0.6, 0.6))),
+    )));
+    world.add(Arc::new(XzRect::new(
+        -3.0,
+        3.0,
+        -3.0,
+        3.0,
+        7.0,
+        Arc::new(DiffuseLight::new_with_color(Vec3::new(6.0, 6.0, 6.0))),
+    )));
+
+    // A fence cut from a single rect with a checker mask.
+    let fence: Arc<dyn Hittable + Sync + Send> = Arc::new(XyRect::new(
+        -4.0,
+        4.0,
+        0.0,
+        1.6,
+        -1.5,
+        Arc::new(Lambertian::new(&Vec3::new(0.55, 0.35, 0.2))),
+    ));
+    let slats = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.0, 0.0, 0.0),
+        &Vec3::new(1.0, 1.0, 1.0),
+    ));
+    world.add(Arc::new(AlphaMask::new(fence, slats)));
+
+    // Leaves shaped by the alpha channel of their image, stored in a BVH.
+    let leaf_image = Arc::new(
+        textures
+            .texture("leaf.png", ColorSpace::Srgb)
+            .with_wrap(WrapMode::Clamp),
+    );
+    let leaf_material = Arc::new(Lambertian::new_with_texture(leaf_image.clone()));
+    let leaf_mask = Arc::new(AlphaChannel::new(leaf_image));
+    let mut leaves = HittableList::new();
+    for i in 0..12 {
+        let leaf: Arc<dyn Hittable + Sync + Send> = Arc::new(AlphaMask::new(
+            Arc::new(XyRect::new(0.0, 1.0, 0.0, 1.0, 0.0, leaf_material.clone())),
+            leaf_mask.clone(),
+        ));
+        leaves.add(Arc::new(Translate::new(
+            Arc::new(RotateY::new(leaf, 30.0 * i as f64)),
+            &Vec3::new(
+                random_double_by_range(-2.5, 2.5),
+                random_double_by_range(0.2, 2.5),
+                random_double_by_range(-0.5, 2.0),
+            ),
+        )));
+    }
+    world.add(Arc::new(BvhNode::new_with_list(&leaves, 0.0, 1.0)));
+
+    // A lace canopy whose noise pattern is treated as a hit probability.
+    let canopy: Arc<dyn Hittable + Sync + Send> = Arc::new(XzRect::new(
+        -3.0,
+        3.0,
+        -3.0,
+        3.0,
+        4.5,
+        Arc::new(Lambertian::new(&Vec3::new(0.8, 0.8, 0.8))),
+    ));
+    world.add(Arc::new(AlphaMask::new_stochastic(
+        canopy,
+        Arc::new(NoiseTexture::new(2.0)),
+    )));
+
+    world
+}
+
+fn shaped_lights(textures: &TextureManager) -> HittableList {
+    let mut world = HittableList::new();
+
+    let red = Arc::new(Lambertian::new(&Vec3::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(&Vec3::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(&Vec3::new(0.12, 0.45, 0.15)));
+
+    world.add(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
+    world.add(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
+    world.add(Arc::new(XzRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+        white.clone(),
+    )));
+    world.add(Arc::new(XzRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+    world.add(Arc::new(XyRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white)));
+
+    // One-sided ceiling lights facing down: a photometric profile on the left and a
+    // spotlight on the right, both emitting the same power.
+    let profile = Arc::new(IesProfile::load("downlight.ies").expect("invalid IES profile"));
+    let downlight = Arc::new(
+        DiffuseLight::new_with_color(Vec3::new(1.0, 0.9, 0.8))
+            .with_two_sided(false)
+            .with_falloff(Falloff::Ies(profile))
+            .with_power(200_000.0, 50.0 * 50.0),
+    );
+    world.add(Arc::new(FlipFace::new(Arc::new(XzRect::new(
+        113.0, 163.0, 253.0, 303.0, 554.0, downlight,
+    )))));
+
+    let spot = Arc::new(
+        DiffuseLight::new_with_color(Vec3::new(0.8, 0.9, 1.0))
+            .with_two_sided(false)
+            .with_falloff(Falloff::spot(30.0, 20.0))
+            .with_power(200_000.0, 50.0 * 50.0),
+    );
+    world.add(Arc::new(FlipFace::new(Arc::new(XzRect::new(
+        392.0, 442.0, 253.0, 303.0, 554.0, spot,
+    )))));
+
+    // A hanging two-sided screen with a textured, forward-peaked emission.
+    let picture = Arc::new(textures.texture("earthmap.jpg", ColorSpace::Srgb));
+    let screen = Arc::new(
+        DiffuseLight::new(picture)
+            .with_falloff(Falloff::CosinePower(4.0))
+            .with_scale(2.0),
+    );
+    world.add(Arc::new(XyRect::new(
+        218.0, 338.0, 360.0, 420.0, 300.0, screen,
+    )));
+
+    world
+}
+
+fn light_rig() -> (HittableList, Vec<Arc<dyn Light + Sync + Send>>) {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.2, 0.3, 0.1),
+        &Vec3::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new_with_texture(checker)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-2.2, 1.0, 0.0),
+        1.0,
+        Arc::new(Lambertian::new(&Vec3::new(0.7, 0.2, 0.2))),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Conductor::new_with_preset(MetalPreset::Gold, 0.3)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(2.2, 1.0, 0.0),
+        1.0,
+        Arc::new(
+            Principled::new(&Vec3::new(0.2, 0.3, 0.8))
+                .with_roughness(Arc::new(SolidColor::new_with_values(0.4, 0.4, 0.4))),
+        ),
+    )));
+
+    // Warm sun, cool fill from the other side and a spot picking out the right sphere.
+    let lights: Vec<Arc<dyn Light + Sync + Send>> = vec![
+        Arc::new(
+            DirectionalLight::new(&Vec3::new(-1.0, 1.5, 0.8), &Vec3::new(3.0, 2.7, 2.3))
+                .with_angular_diameter(2.0),
+        ),
+        Arc::new(PointLight::new_with_power(
+            &Point3::new(5.0, 3.0, 4.0),
+            &Vec3::new(0.4, 0.5, 1.0),
+            400.0,
+        )),
+        Arc::new(SpotLight::new(
+            &Point3::new(2.2, 6.0, 3.0),
+            &Point3::new(2.2, 0.0, 0.0),
+            &Vec3::new(20.0, 20.0, 20.0),
+            20.0,
+            12.0,
+        )),
+    ];
+
+    (world, lights)
+}
+
+fn environment_lighting() -> HittableList {
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(&Vec3::new(0.5, 0.5, 0.5))),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-3.3, 1.0, 0.0),
+        1.0,
+        Arc::new(Lambertian::new(&Vec3::new(0.8, 0.8, 0.8))),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-1.1, 1.0, 0.0),
+        1.0,
+        Arc::new(Conductor::new_with_preset(MetalPreset::Copper, 0.25)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(1.1, 1.0, 0.0),
+        1.0,
+        Arc::new(CoatedMaterial::new(
+            Arc::new(Lambertian::new(&Vec3::new(0.1, 0.3, 0.6))),
+            1.5,
+        )),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(3.3, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+
+    world
+}
+
+fn smoke_and_clouds() -> HittableList {
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(&Vec3::new(0.5, 0.45, 0.4))),
+    )));
+
+    // A rising plume from a voxel grid next to a patchy cloud of procedural noise.
+    let minimum = Point3::new(-3.5, 0.0, -1.0);
+    let maximum = Point3::new(-0.5, 4.0, 2.0);
+    let plume =
+        DensityGrid::load("smoke.density", &minimum, &maximum).expect("invalid density grid");
+    world.add(Arc::new(HeterogeneousMedium::new(
+        Arc::new(Box::new(
+            &minimum,
+            &maximum,
+            Arc::new(Lambertian::new(&Vec3::new(0.0, 0.0, 0.0))),
+        )),
+        Arc::new(plume),
+        10.0,
+        Vec3::new(0.8, 0.8, 0.8),
+    )));
+
+    world.add(Arc::new(
+        HeterogeneousMedium::new(
+            Arc::new(Sphere::new(
+                Point3::new(2.2, 2.2, 0.0),
+                1.8,
+                Arc::new(Lambertian::new(&Vec3::new(0.0, 0.0, 0.0))),
+            )),
+            Arc::new(NoiseDensity::new(1.5)),
+            3.0,
+            Vec3::new(0.95, 0.95, 0.95),
+        )
+        .with_phase_function(Arc::new(HenyeyGreenstein::new(0.6))),
+    ));
+
+    world
+}
+
+fn phase_functions() -> HittableList {
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(&Vec3::new(0.4, 0.4, 0.4))),
+    )));
+
+    // Backlit fog balls: isotropic, forward-scattering Henyey-Greenstein, a double
+    // lobe with some backscatter and Rayleigh.
+    let phase_functions: Vec<Arc<dyn PhaseFunction + Sync + Send>> = vec![
+        Arc::new(IsotropicPhase),
+        Arc::new(HenyeyGreenstein::new(0.8)),
+        Arc::new(DoubleHenyeyGreenstein::new(0.85, -0.4, 0.8)),
+        Arc::new(Rayleigh),
+    ];
+    for (i, phase_function) in phase_functions.into_iter().enumerate() {
+        let boundary = Arc::new(Sphere::new(
+            Point3::new(-3.3 + 2.2 * i as f64, 1.2, 0.0),
+            1.0,
+            Arc::new(Dielectric::new(1.0)),
+        ));
+        world.add(Arc::new(
+            ConstantMedium::new(boundary, 1.5, Vec3::new(0.9, 0.9, 0.9))
+                .with_phase_function(phase_function),
+        ));
+    }
+
+    world
+}
+
+fn coloured_media() -> HittableList {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.2, 0.2, 0.2),
+        &Vec3::new(0.8, 0.8, 0.8),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new_with_texture(checker)),
+    )));
+    world.add(Arc::new(XzRect::new(
+        -3.0,
+        3.0,
+        -3.0,
+        3.0,
+        8.0,
+        Arc::new(DiffuseLight::new_with_color(Vec3::new(6.0, 6.0, 6.0))),
+    )));
+
+    // Cloudy orange juice: strong blue absorption and plenty of scattering, behind a
+    // water-like interface.
+    let juice: Arc<dyn Hittable + Sync + Send> = Arc::new(Box::new(
+        &Point3::new(-3.2, 0.0, -0.6),
+        &Point3::new(-2.0, 2.2, 0.6),
+        Arc::new(Dielectric::new(1.34)),
+    ));
+    world.add(juice.clone());
+    world.add(Arc::new(ConstantMedium::new_with_coefficients(
+        juice,
+        &Vec3::new(0.05, 0.6, 4.0),
+        &Vec3::new(3.0, 3.0, 3.0),
+    )));
+
+    // Clear cranberry juice, which only absorbs.
+    let cranberry: Arc<dyn Hittable + Sync + Send> = Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.34)),
+    ));
+    world.add(cranberry.clone());
+    world.add(Arc::new(ConstantMedium::new_with_coefficients(
+        cranberry,
+        &Vec3::new(0.4, 3.5, 2.5),
+        &Vec3::new(0.0, 0.0, 0.0),
+    )));
+
+    // Glowing gas with a hot core colour and a little scattering.
+    world.add(Arc::new(
+        ConstantMedium::new_with_coefficients(
+            Arc::new(Sphere::new(
+                Point3::new(2.6, 1.0, 0.0),
+                1.0,
+                Arc::new(Dielectric::new(1.0)),
+            )),
+            &Vec3::new(0.8, 0.8, 0.8),
+            &Vec3::new(0.4, 0.4, 0.4),
+        )
+        .with_emission(&Vec3::new(4.0, 1.6, 0.4)),
+    ));
+
+    world
+}
+
+fn liquid_in_glass() -> HittableList {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::new_with_color(
+        &Vec3::new(0.2, 0.2, 0.2),
+        &Vec3::new(0.8, 0.8, 0.8),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new_with_texture(checker)),
+    )));
+    world.add(Arc::new(XzRect::new(
+        -3.0,
+        3.0,
+        -3.0,
+        3.0,
+        8.0,
+        Arc::new(DiffuseLight::new_with_color(Vec3::new(6.0, 6.0, 6.0))),
+    )));
+
+    // Each glass is a solid block carved out by the liquid and by an air pocket above
+    // it, both of higher priority. The liquid overlaps the air pocket so its surface
+    // meets air rather than glass, and the pocket sticks out of the open top.
+    let glass = |offset: f64,
+                 body: Arc<dyn Material + Sync + Send>,
+                 liquid: Arc<dyn Material + Sync + Send>|
+     -> Vec<Arc<dyn Hittable + Sync + Send>> {
+        vec![
+            Arc::new(Box::new(
+                &Point3::new(offset - 1.0, 0.0, -1.0),
+                &Point3::new(offset + 1.0, 2.5, 1.0),
+                body,
+            )),
+            Arc::new(Box::new(
+                &Point3::new(offset - 0.85, 1.5, -0.85),
+                &Point3::new(offset + 0.85, 2.6, 0.85),
+                Arc::new(Dielectric::new(1.0).with_priority(1)),
+            )),
+            Arc::new(Box::new(
+                &Point3::new(offset - 0.85, 0.15, -0.85),
+                &Point3::new(offset + 0.85, 1.6, 0.85),
+                liquid,
+            )),
+        ]
+    };
+
+    // Clear red liquid, absorbing as it goes.
+    let wine = Dielectric::new(1.33)
+        .with_priority(2)
+        .with_tint(&Vec3::new(0.8, 0.2, 0.25), 1.0);
+    for object in glass(-1.4, Arc::new(Dielectric::new(1.5)), Arc::new(wine)) {
+        world.add(object);
+    }
+
+    // Cloudy orange juice, scattering inside a frosted glass.
+    let juice = glass(
+        1.4,
+        Arc::new(RoughDielectric::new(1.5, 0.2)),
+        Arc::new(Dielectric::new(1.34).with_priority(2)),
+    );
+    world.add(Arc::new(ConstantMedium::new_with_coefficients(
+        juice[2].clone(),
+        &Vec3::new(0.05, 0.6, 4.0),
+        &Vec3::new(3.0, 3.0, 3.0),
+    )));
+    for object in juice {
+        world.add(object);
+    }
+
+    world
+}
+
+fn bokeh() -> HittableList {
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(&Vec3::new(0.3, 0.3, 0.3)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground,
+    )));
+
+    let subject = Arc::new(Metal::new(&Vec3::new(0.8, 0.6, 0.4), 0.05));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        subject,
+    )));
+
+    // Small lamps far behind the subject, well out of focus.
+    for i in -6..=6 {
+        for j in 0..4 {
+            let colour = Vec3::new(
+                random_double_by_range(0.5, 1.0),
+                random_double_by_range(0.3, 0.8),
+                random_double_by_range(0.1, 0.6),
+            );
+            let lamp = Arc::new(DiffuseLight::new_with_color(4.0 * colour));
+            let center = Point3::new(
+                2.5 * i as f64 + random_double_by_range(-0.5, 0.5),
+                1.0 + 2.0 * j as f64 + random_double_by_range(-0.5, 0.5),
+                -25.0 + random_double_by_range(-3.0, 3.0),
+            );
+            world.add(Arc::new(Sphere::new(center, 0.3, lamp)));
+        }
+    }
+
+    world
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::{IndexedParallelIterator, IntoParallelRefMutIterator};
+
+    use super::*;
+
+    /// Looks pixels up a fixed distance away in a sampler's pattern, so that samplers
+    /// without a seed give a different image in each trial.
+    struct Shifted(Arc<dyn Sampler + Sync + Send>, usize);
+
+    impl Sampler for Shifted {
+        fn sample(&self, x: usize, y: usize, index: usize, dimension: usize) -> f64 {
+            self.0.sample(x + self.1, y, index, dimension)
+        }
+    }
+
+    /// The Cornell box as seen in scene 6, rendered 8 pixels square with `samples`
+    /// per pixel.
+    fn render_cornell_box(sampler: Arc<dyn Sampler + Sync + Send>, samples: usize) -> Vec<Vec3> {
+        let size = 8;
+        let world = cornell_box();
+        let scene = Scene {
+            world: &world,
+            background: Vec3::new(0.0, 0.0, 0.0),
+            environment: None,
+            lights: &[],
+            caustics: None,
+        };
+        let camera = PerspectiveCamera::new(
+            &Point3::new(278.0, 278.0, -800.0),
+            &Point3::new(278.0, 278.0, 0.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+        );
+
+        let mut image = vec![Vec3::new(0.0, 0.0, 0.0); size * size];
+        image.par_iter_mut().enumerate().for_each(|(p, pixel)| {
+            for index in 0..samples {
+                let (_, _, color) = render_sample(
+                    &camera,
+                    &scene,
+                    sampler.as_ref(),
+                    (p % size, p / size),
+                    index,
+                    (size, size),
+                );
+                *pixel += color;
+            }
+            *pixel /= samples as f64;
+        });
+        image
+    }
+
+    fn mean_squared_error(image: &[Vec3], reference: &[Vec3]) -> f64 {
+        let sum: f64 = image
+            .iter()
+            .zip(reference)
+            .map(|(a, b)| (*a - *b).length_squared())
+            .sum();
+        sum / image.len() as f64
+    }
+
+    #[test]
+    fn test_low_discrepancy_sampling_lowers_error() {
+        let reference = render_cornell_box(Arc::new(IndependentSampler), 1024);
+
+        // Averaged over a few renders, so that one lucky image cannot decide it.
+        let trials = 8;
+        let error = |sampler: &dyn Fn(u64) -> Arc<dyn Sampler + Sync + Send>| {
+            (0..trials)
+                .map(|seed| {
+                    let shifted = Arc::new(Shifted(sampler(seed), 8 * seed as usize));
+                    mean_squared_error(&render_cornell_box(shifted, 16), &reference)
+                })
+                .sum::<f64>()
+                / trials as f64
+        };
+
+        // With a single stratum every number is a hash of its pixel, sample and
+        // dimension: independent samples, but the same ones on every run, unlike the
+        // fireflies `IndependentSampler` would bring in here.
+        let independent = error(&|_| Arc::new(StratifiedSampler::new(1)));
+        assert!(error(&|_| Arc::new(StratifiedSampler::new(16))) < 0.75 * independent);
+        assert!(error(&|_| Arc::new(HaltonSampler)) < 0.75 * independent);
+        assert!(error(&|seed| Arc::new(SobolSampler::new(seed))) < 0.75 * independent);
+        assert!(error(&|_| Arc::new(BlueNoiseSampler::new())) < 0.75 * independent);
+    }
+}
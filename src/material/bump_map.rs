@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use crate::{
+    model::{hit::HitRecord, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+    texture::texture::Texture,
+};
+
+use super::{
+    interior::Interior,
+    material::Material,
+    normal_map::{eval_with_shading_normal, pdf_with_shading_normal, scatter_with_shading_normal},
+};
+
+/// Step in texture space used to take finite differences of the height field.
+const DELTA: f64 = 5e-4;
+
+/// Wraps a material and perturbs its shading normal as if the surface were displaced
+/// along its normal by `scale` times the first channel of `height`.
+pub struct BumpMap {
+    pub base: Arc<dyn Material + Sync + Send>,
+    pub height: Arc<dyn Texture + Sync + Send>,
+    pub scale: f64,
+}
+
+impl BumpMap {
+    pub fn new(
+        base: Arc<dyn Material + Sync + Send>,
+        height: Arc<dyn Texture + Sync + Send>,
+        scale: f64,
+    ) -> Self {
+        Self {
+            base,
+            height,
+            scale,
+        }
+    }
+}
+
+impl BumpMap {
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        // Without a parameterization there is nothing to differentiate.
+        if rec.dpdu.near_zero() || rec.dpdv.near_zero() {
+            return rec.normal;
+        }
+
+        let displacement = |u: f64, v: f64, p: &Vec3| self.scale * self.height.value(u, v, p).x();
+        let d = displacement(rec.u, rec.v, &rec.p);
+        let d_du = (displacement(rec.u + DELTA, rec.v, &(rec.p + DELTA * rec.dpdu)) - d) / DELTA;
+        let d_dv = (displacement(rec.u, rec.v + DELTA, &(rec.p + DELTA * rec.dpdv)) - d) / DELTA;
+
+        let outward_normal = if rec.front_face {
+            rec.normal
+        } else {
+            -rec.normal
+        };
+        let dpdu = rec.dpdu + d_du * outward_normal;
+        let dpdv = rec.dpdv + d_dv * outward_normal;
+
+        let shading_normal = dpdu.cross(&dpdv).unit_vector();
+        if shading_normal.dot(&rec.normal) < 0.0 {
+            -shading_normal
+        } else {
+            shading_normal
+        }
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Vec3,
+        scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        scatter_with_shading_normal(
+            self.base.as_ref(),
+            r_in,
+            rec,
+            &self.shading_normal(rec),
+            attenuation,
+            scattered,
+            sampler,
+        )
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> Vec3 {
+        eval_with_shading_normal(self.base.as_ref(), r_in, rec, &self.shading_normal(rec), wi)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> f64 {
+        pdf_with_shading_normal(self.base.as_ref(), r_in, rec, &self.shading_normal(rec), wi)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Vec3) -> Vec3 {
+        self.base.emitted(r_in, rec, u, v, p)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.base.is_specular()
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        self.base.interior()
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{
+    model::{hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+};
+
+use super::{fresnel::fresnel_conductor, material::Material, microfacet::TrowbridgeReitz};
+
+/// Measured complex indices of refraction, averaged over the red, green and blue
+/// parts of the spectrum.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetalPreset {
+    Gold,
+    Silver,
+    Copper,
+    Aluminium,
+    Chromium,
+    Iron,
+    Titanium,
+}
+
+impl MetalPreset {
+    /// Returns `(eta, k)` for the preset.
+    pub fn ior(&self) -> (Vec3, Vec3) {
+        match self {
+            MetalPreset::Gold => (
+                Vec3::new(0.18299, 0.42108, 1.37340),
+                Vec3::new(3.42420, 2.34590, 1.77040),
+            ),
+            MetalPreset::Silver => (
+                Vec3::new(0.15943, 0.14512, 0.13547),
+                Vec3::new(3.92910, 3.19000, 2.38080),
+            ),
+            MetalPreset::Copper => (
+                Vec3::new(0.27105, 0.67693, 1.31640),
+                Vec3::new(3.60920, 2.62480, 2.29210),
+            ),
+            MetalPreset::Aluminium => (
+                Vec3::new(1.65740, 0.88036, 0.52120),
+                Vec3::new(9.22380, 6.26950, 4.83700),
+            ),
+            MetalPreset::Chromium => (
+                Vec3::new(3.10710, 3.18120, 2.32300),
+                Vec3::new(3.33140, 3.32910, 3.13500),
+            ),
+            MetalPreset::Iron => (
+                Vec3::new(2.91140, 2.94970, 2.58450),
+                Vec3::new(3.08930, 2.93180, 2.76700),
+            ),
+            MetalPreset::Titanium => (
+                Vec3::new(2.74070, 2.54180, 2.26700),
+                Vec3::new(3.81430, 3.43450, 3.03850),
+            ),
+        }
+    }
+}
+
+/// Rough conductor using the GGX microfacet model with visible-normal sampling.
+/// `roughness_u` runs along the surface tangent and `roughness_v` along the bitangent.
+pub struct Conductor {
+    pub eta: Vec3,
+    pub k: Vec3,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl Conductor {
+    pub fn new(eta: Vec3, k: Vec3, roughness: f64) -> Self {
+        Conductor::new_anisotropic(eta, k, roughness, roughness)
+    }
+
+    pub fn new_anisotropic(eta: Vec3, k: Vec3, roughness_u: f64, roughness_v: f64) -> Self {
+        Self {
+            eta,
+            k,
+            distribution: TrowbridgeReitz::new(
+                TrowbridgeReitz::roughness_to_alpha(roughness_u),
+                TrowbridgeReitz::roughness_to_alpha(roughness_v),
+            ),
+        }
+    }
+
+    pub fn new_with_preset(preset: MetalPreset, roughness: f64) -> Conductor {
+        let (eta, k) = preset.ior();
+        Conductor::new(eta, k, roughness)
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Vec3,
+        scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let uvw = Onb::build_from_w_and_tangent(&rec.normal, &rec.dpdu);
+        let wo = uvw.to_local(&(-r_in.dir().unit_vector()));
+        if wo.z() <= 0.0 {
+            return false;
+        }
+
+        let wm = self
+            .distribution
+            .sample_visible_normal(&wo, sampler.next_2d());
+        let wi = (-wo).reflect(&wm);
+        if wi.z() <= 0.0 {
+            return false;
+        }
+
+        // Sampling visible normals leaves only Fresnel and the shadowing ratio in the weight.
+        let fresnel = fresnel_conductor(wo.dot(&wm), &self.eta, &self.k);
+        *attenuation = fresnel * self.distribution.g(&wo, &wi) / self.distribution.g1(&wo);
+        *scattered = Ray::new(&rec.p, &uvw.local(&wi), r_in.time());
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> Vec3 {
+        let uvw = Onb::build_from_w_and_tangent(&rec.normal, &rec.dpdu);
+        let wo = uvw.to_local(&(-r_in.dir().unit_vector()));
+        let wi = uvw.to_local(wi);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        let wm = (wo + wi).unit_vector();
+        let fresnel = fresnel_conductor(wo.dot(&wm), &self.eta, &self.k);
+        fresnel * self.distribution.d(&wm) * self.distribution.g(&wo, &wi) / (4.0 * wo.z())
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> f64 {
+        let uvw = Onb::build_from_w_and_tangent(&rec.normal, &rec.dpdu);
+        let wo = uvw.to_local(&(-r_in.dir().unit_vector()));
+        let wi = uvw.to_local(wi);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+
+        let wm = (wo + wi).unit_vector();
+        self.distribution.g1(&wo) * self.distribution.d(&wm) / (4.0 * wo.z())
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distribution.is_smooth()
+    }
+}
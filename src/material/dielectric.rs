@@ -1,21 +1,40 @@
 use crate::{
     model::{hit::HitRecord, ray::Ray, vec3::Vec3},
-    util::rtweekend::random_double,
+    sampler::sampler::SamplerState,
 };
 
-use super::material::Material;
+use super::{interior::Interior, material::Material};
 
 pub struct Dielectric {
     pub ir: f64,
+    /// Decides which interior wins where this object overlaps another, see `Interior`.
+    pub priority: i32,
+    /// Absorption coefficient per unit of distance travelled inside the object.
+    pub absorption: Vec3,
 }
 
 impl Dielectric {
     pub fn new(index_of_refraction: f64) -> Self {
         Self {
             ir: index_of_refraction,
+            priority: 0,
+            absorption: Vec3::new(0.0, 0.0, 0.0),
         }
     }
 
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Tints the interior so that light keeps `color` after travelling `distance` inside.
+    pub fn with_tint(mut self, color: &Vec3, distance: f64) -> Self {
+        for c in 0..3 {
+            self.absorption[c] = -color[c].max(1e-6).ln() / distance;
+        }
+        self
+    }
+
     pub fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
         let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
         r0 = r0 * r0;
@@ -31,12 +50,13 @@ impl Material for Dielectric {
         rec: &HitRecord,
         attenuation: &mut Vec3,
         scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
     ) -> bool {
         *attenuation = Vec3::new(1.0, 1.0, 1.0);
         let refraction_ratio = if rec.front_face {
-            1.0 / self.ir
+            rec.exterior_ior / self.ir
         } else {
-            self.ir
+            self.ir / rec.exterior_ior
         };
 
         let unit_direction = r_in.dir().unit_vector();
@@ -44,15 +64,27 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction: Vec3;
-        if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > random_double()
+        let direction = if cannot_refract
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > sampler.next_1d()
         {
-            direction = unit_direction.reflect(&rec.normal);
+            unit_direction.reflect(&rec.normal)
         } else {
-            direction = unit_direction.refract(&rec.normal, refraction_ratio);
-        }
+            unit_direction.refract(&rec.normal, refraction_ratio)
+        };
 
         *scattered = Ray::new(&rec.p, &direction, r_in.time());
         return true;
     }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        Some(Interior {
+            ior: self.ir,
+            priority: self.priority,
+            absorption: self.absorption,
+        })
+    }
 }
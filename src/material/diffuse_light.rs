@@ -1,24 +1,160 @@
 use std::sync::Arc;
 
 use crate::{
-    model::vec3::Vec3,
+    model::{hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
     texture::{solid_color::SolidColor, texture::Texture},
+    util::{
+        ies::IesProfile,
+        rtweekend::{degrees_to_radians, PI},
+    },
 };
 
 use super::material::Material;
 
+/// Angular shape of the emitted radiance, relative to the normal of the emitting face.
+pub enum Falloff {
+    Uniform,
+    /// Radiance proportional to `cos^n` of the angle to the normal.
+    CosinePower(f64),
+    /// Full radiance inside the inner cone, smoothly fading to zero at the outer cone.
+    Spot {
+        cos_total_width: f64,
+        cos_falloff_start: f64,
+    },
+    /// Photometric profile, with its axis along the normal and horizontal angles
+    /// measured from the surface tangent `dpdu`.
+    Ies(Arc<IesProfile>),
+}
+
+impl Falloff {
+    /// Spotlight whose cone angles are given in degrees from the normal.
+    pub fn spot(total_width: f64, falloff_start: f64) -> Falloff {
+        Falloff::Spot {
+            cos_total_width: degrees_to_radians(total_width).cos(),
+            cos_falloff_start: degrees_to_radians(falloff_start.min(total_width)).cos(),
+        }
+    }
+
+    /// Weight for an outgoing direction given in the local frame of the emitting face.
+    fn weight(&self, local: &Vec3) -> f64 {
+        let cos_theta = local.z();
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+
+        match self {
+            Falloff::Uniform => 1.0,
+            Falloff::CosinePower(n) => cos_theta.powf(*n),
+            Falloff::Spot {
+                cos_total_width,
+                cos_falloff_start,
+            } => {
+                if cos_theta < *cos_total_width {
+                    0.0
+                } else if cos_theta >= *cos_falloff_start {
+                    1.0
+                } else {
+                    let t = (cos_theta - cos_total_width) / (cos_falloff_start - cos_total_width);
+                    t * t * (3.0 - 2.0 * t)
+                }
+            }
+            Falloff::Ies(profile) => {
+                let theta = cos_theta.min(1.0).acos().to_degrees();
+                let phi = local.y().atan2(local.x()).to_degrees();
+                profile.relative_intensity(theta, phi)
+            }
+        }
+    }
+
+    /// Cosine-weighted integral of the falloff over the hemisphere, which relates the
+    /// radiance of a face to the power it emits per unit area.
+    fn projected_solid_angle(&self) -> f64 {
+        match self {
+            Falloff::Uniform => PI,
+            Falloff::CosinePower(n) => 2.0 * PI / (n + 2.0),
+            _ => {
+                const THETA_STEPS: usize = 256;
+                const PHI_STEPS: usize = 64;
+                let d_theta = 0.5 * PI / THETA_STEPS as f64;
+                let d_phi = 2.0 * PI / PHI_STEPS as f64;
+
+                let mut sum = 0.0;
+                for i in 0..THETA_STEPS {
+                    let theta = (i as f64 + 0.5) * d_theta;
+                    for j in 0..PHI_STEPS {
+                        let phi = (j as f64 + 0.5) * d_phi;
+                        let local = Vec3::new(
+                            theta.sin() * phi.cos(),
+                            theta.sin() * phi.sin(),
+                            theta.cos(),
+                        );
+                        sum += self.weight(&local) * theta.cos() * theta.sin();
+                    }
+                }
+                sum * d_theta * d_phi
+            }
+        }
+    }
+}
+
 pub struct DiffuseLight {
     emit: Arc<dyn Texture + Sync + Send>,
+    scale: f64,
+    two_sided: bool,
+    falloff: Falloff,
+    /// Emitted power in watts and the emitter area, when the scale is derived from them.
+    power: Option<(f64, f64)>,
 }
 
 impl DiffuseLight {
     pub fn new(a: Arc<dyn Texture + Sync + Send>) -> Self {
-        Self { emit: a }
+        Self {
+            emit: a,
+            scale: 1.0,
+            two_sided: true,
+            falloff: Falloff::Uniform,
+            power: None,
+        }
     }
 
     pub fn new_with_color(c: Vec3) -> DiffuseLight {
-        DiffuseLight {
-            emit: Arc::new(SolidColor::new(&c)),
+        DiffuseLight::new(Arc::new(SolidColor::new(&c)))
+    }
+
+    /// Multiplies the emitted texture, giving the radiance in nits for a white texture.
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self.power = None;
+        self
+    }
+
+    /// Scales the radiance so that an emitter of the given `area` radiates `watts` in
+    /// total, with the texture acting as a tint. The scale follows later changes to the
+    /// sidedness or falloff.
+    pub fn with_power(mut self, watts: f64, area: f64) -> Self {
+        self.power = Some((watts, area));
+        self.update_scale();
+        self
+    }
+
+    /// One-sided emitters only radiate from the front face, along the outward normal.
+    pub fn with_two_sided(mut self, two_sided: bool) -> Self {
+        self.two_sided = two_sided;
+        self.update_scale();
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self.update_scale();
+        self
+    }
+
+    fn update_scale(&mut self) {
+        if let Some((watts, area)) = self.power {
+            let sides = if self.two_sided { 2.0 } else { 1.0 };
+            self.scale = watts / (area * sides * self.falloff.projected_solid_angle());
         }
     }
 }
@@ -30,11 +166,27 @@ impl Material for DiffuseLight {
         rec: &crate::model::hit::HitRecord,
         attenuation: &mut Vec3,
         scattered: &mut crate::model::ray::Ray,
+        _sampler: &mut dyn SamplerState,
     ) -> bool {
         return false;
     }
 
-    fn emitted(&self, u: f64, v: f64, p: &Vec3) -> Vec3 {
-        self.emit.value(u, v, p)
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Vec3) -> Vec3 {
+        if !rec.front_face && !self.two_sided {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        let weight = match self.falloff {
+            Falloff::Uniform => 1.0,
+            _ => {
+                // `rec.normal` always faces the incoming ray, i.e. it is the normal of the
+                // face the light leaves from.
+                let uvw = Onb::build_from_w_and_tangent(&rec.normal, &rec.dpdu);
+                self.falloff
+                    .weight(&uvw.to_local(&-r_in.dir().unit_vector()))
+            }
+        };
+
+        self.scale * weight * self.emit.value(u, v, p)
     }
 }
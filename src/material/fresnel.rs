@@ -0,0 +1,55 @@
+use crate::model::vec3::Vec3;
+
+/// Exact unpolarized Fresnel reflectance of a conductor with complex index of
+/// refraction `eta + i k`, seen from a medium of index 1.
+pub fn fresnel_conductor(cos_theta_i: f64, eta: &Vec3, k: &Vec3) -> Vec3 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let cos2 = cos_theta_i * cos_theta_i;
+    let sin2 = 1.0 - cos2;
+
+    let mut result = Vec3::default();
+    for c in 0..3 {
+        let eta2 = eta[c] * eta[c];
+        let k2 = k[c] * k[c];
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_theta_i * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+
+        result[c] = 0.5 * (rp + rs);
+    }
+
+    result
+}
+
+/// Exact unpolarized Fresnel reflectance at a dielectric interface, where `eta` is
+/// the ratio of the incident index over the transmitted one.
+pub fn fresnel_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_t = eta * eta * (1.0 - cos_theta_i * cos_theta_i);
+
+    // Total internal reflection
+    if sin2_theta_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    let rs = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let rp = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+
+    0.5 * (rs * rs + rp * rp)
+}
+
+/// Schlick's approximation of the Fresnel reflectance for a normal-incidence reflectance `f0`.
+pub fn fresnel_schlick(cos_theta: f64, f0: &Vec3) -> Vec3 {
+    let weight = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+
+    f0 + (Vec3::new(1.0, 1.0, 1.0) - f0) * weight
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use crate::model::{hit::HitRecord, vec3::Vec3};
+
+/// Most interiors a path can be inside at once; deeper nesting is ignored.
+const MAX_NESTING: usize = 8;
+
+/// What fills a closed dielectric object, as seen by paths crossing its surface.
+/// Where objects overlap, e.g. a liquid modelled slightly into the glass holding it,
+/// the interior with the highest `priority` wins and the other's surfaces are skipped.
+#[derive(Clone, Copy)]
+pub struct Interior {
+    pub ior: f64,
+    pub priority: i32,
+    /// Absorption coefficient per unit of distance travelled inside.
+    pub absorption: Vec3,
+}
+
+impl Interior {
+    pub fn new(ior: f64) -> Self {
+        Self {
+            ior,
+            priority: 0,
+            absorption: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// Interiors a path is currently inside, in the order they were entered. Paths start
+/// outside everything, in a vacuum of index 1.
+#[derive(Clone, Copy)]
+pub struct MediumStack {
+    entries: [(usize, Interior); MAX_NESTING],
+    len: usize,
+}
+
+impl MediumStack {
+    pub fn new() -> Self {
+        Self {
+            entries: [(0, Interior::new(1.0)); MAX_NESTING],
+            len: 0,
+        }
+    }
+
+    /// Identifies the object hit at `rec` on the stack, by its material, so objects
+    /// sharing one material instance must not overlap.
+    pub fn id(rec: &HitRecord) -> usize {
+        Arc::as_ptr(&rec.material) as *const u8 as usize
+    }
+
+    /// The interior that decides the optical properties along the path: the one with the
+    /// highest priority, and the most recently entered among equals.
+    pub fn current(&self) -> Option<&Interior> {
+        self.current_without(None)
+    }
+
+    /// Fraction of light left after travelling `distance` inside the current interior.
+    pub fn transmittance(&self, distance: f64) -> Vec3 {
+        let absorption = self
+            .current()
+            .map_or(Vec3::new(0.0, 0.0, 0.0), |interior| interior.absorption);
+        if absorption.near_zero() {
+            return Vec3::new(1.0, 1.0, 1.0);
+        }
+
+        Vec3::new(
+            (-absorption.x() * distance).exp(),
+            (-absorption.y() * distance).exp(),
+            (-absorption.z() * distance).exp(),
+        )
+    }
+
+    /// Index of refraction on the far side of the surface of object `id` that the path
+    /// is entering or leaving, or `None` if the surface lies inside an interior of
+    /// higher priority and should be passed through as if it were not there.
+    pub fn exterior_ior(&self, id: usize, interior: &Interior) -> Option<f64> {
+        let other = self.current_without(Some(id));
+        if other.is_some_and(|other| other.priority > interior.priority) {
+            return None;
+        }
+
+        Some(other.map_or(1.0, |other| other.ior))
+    }
+
+    /// Records the path crossing the surface of object `id`.
+    pub fn cross(&mut self, id: usize, interior: &Interior, entering: bool) {
+        if entering {
+            if self.len < MAX_NESTING {
+                self.entries[self.len] = (id, *interior);
+                self.len += 1;
+            }
+        } else if let Some(i) = self.entries[..self.len].iter().rposition(|e| e.0 == id) {
+            self.entries.copy_within(i + 1..self.len, i);
+            self.len -= 1;
+        }
+    }
+
+    fn current_without(&self, id: Option<usize>) -> Option<&Interior> {
+        let mut current: Option<&Interior> = None;
+        for (entry_id, interior) in self.entries[..self.len].iter() {
+            if Some(*entry_id) == id {
+                continue;
+            }
+            if current.is_none_or(|c| interior.priority >= c.priority) {
+                current = Some(interior);
+            }
+        }
+
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_liquid_in_glass_uses_relative_ior_and_skips_hidden_surfaces() {
+        let glass = Interior {
+            priority: 1,
+            ..Interior::new(1.5)
+        };
+        let water = Interior::new(1.33);
+        let (glass_id, water_id) = (1, 2);
+        let mut stack = MediumStack::new();
+
+        // Into the glass wall from the air.
+        assert_eq!(stack.exterior_ior(glass_id, &glass), Some(1.0));
+        stack.cross(glass_id, &glass, true);
+
+        // The water surface overlapping the wall is ignored but still entered.
+        assert_eq!(stack.exterior_ior(water_id, &water), None);
+        stack.cross(water_id, &water, true);
+
+        // Out of the wall straight into the water.
+        assert_eq!(stack.exterior_ior(glass_id, &glass), Some(1.33));
+        stack.cross(glass_id, &glass, false);
+        assert_eq!(stack.current().map(|i| i.ior), Some(1.33));
+
+        // And out of the water back into the air.
+        assert_eq!(stack.exterior_ior(water_id, &water), Some(1.0));
+        stack.cross(water_id, &water, false);
+        assert!(stack.current().is_none());
+    }
+}
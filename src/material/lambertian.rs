@@ -1,8 +1,10 @@
 use std::sync::Arc;
 
 use crate::{
-    model::{hit::HitRecord, ray::Ray, vec3::Vec3},
+    model::{hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
     texture::{solid_color::SolidColor, texture::Texture},
+    util::rtweekend::PI,
 };
 
 use super::material::Material;
@@ -30,8 +32,10 @@ impl Material for Lambertian {
         rec: &HitRecord,
         attenuation: &mut Vec3,
         scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
     ) -> bool {
-        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
+        let uvw = Onb::build_from_w(&rec.normal);
+        let mut scatter_direction = uvw.local(&Vec3::sample_cosine_direction(sampler.next_2d()));
 
         // Catch degenerate scatter direction
         if scatter_direction.near_zero() {
@@ -39,7 +43,16 @@ impl Material for Lambertian {
         }
 
         *scattered = Ray::new(&rec.p, &scatter_direction, r_in.time());
-        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+        *attenuation = self.albedo.filtered_value(rec);
         return true;
     }
+
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> Vec3 {
+        let cos_theta = wi.dot(&rec.normal).max(0.0);
+        self.albedo.filtered_value(rec) * cos_theta / PI
+    }
+
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> f64 {
+        wi.dot(&rec.normal).max(0.0) / PI
+    }
 }
@@ -1,17 +1,58 @@
-use crate::model::{hit::HitRecord, ray::Ray, vec3::Vec3};
+use crate::{
+    model::{hit::HitRecord, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+};
+
+use super::interior::Interior;
 
 use Vec3 as Point3;
 
 pub trait Material {
+    /// Picks the direction the path goes on in, drawing the numbers it needs from
+    /// `sampler`.
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
         attenuation: &mut Vec3,
         scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
     ) -> bool;
 
-    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Vec3 {
+    /// Radiance leaving the surface at `rec` back along `r_in`.
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, u: f64, v: f64, p: &Point3) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    /// BSDF times the cosine term for light arriving along the unit direction `wi` and
+    /// leaving back along `r_in`. Lights are sampled directly through it, so materials
+    /// whose `scatter` is purely specular keep the default of zero.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _wi: &Vec3) -> Vec3 {
         Vec3::new(0.0, 0.0, 0.0)
     }
+
+    /// Density per unit solid angle with which `scatter` picks `wi`, used to weigh
+    /// sampled lights against scattered rays. Zero for materials without an `eval`.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    /// Whether `scatter` follows a single sharp lobe, as for mirrors and glass.
+    /// Photon mapping stores photons only on non-specular surfaces.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// Whether this scatters light inside a participating medium, where the hit record
+    /// has no meaningful normal. Photon maps neither store nor gather photons there.
+    fn is_volume(&self) -> bool {
+        false
+    }
+
+    /// What fills closed objects made of this material, for refractive materials whose
+    /// paths track which objects they are inside. Such materials read the index of
+    /// refraction on the far side of the surface from `rec.exterior_ior`.
+    fn interior(&self) -> Option<Interior> {
+        None
+    }
 }
@@ -1,4 +1,7 @@
-use crate::model::{hit::HitRecord, ray::Ray, vec3::Vec3};
+use crate::{
+    model::{hit::HitRecord, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+};
 
 use super::material::Material;
 
@@ -23,14 +26,16 @@ impl Material for Metal {
         rec: &HitRecord,
         attenuation: &mut Vec3,
         scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
     ) -> bool {
         let reflected = r_in.dir().unit_vector().reflect(&rec.normal);
-        *scattered = Ray::new(
-            &rec.p,
-            &(reflected + self.fuzz * Vec3::random_in_unit_sphere()),
-            r_in.time(),
-        );
+        let fuzz = Vec3::sample_in_unit_sphere(sampler.next_2d(), sampler.next_1d());
+        *scattered = Ray::new(&rec.p, &(reflected + self.fuzz * fuzz), r_in.time());
         *attenuation = self.albedo.clone();
         scattered.dir().dot(&rec.normal) > 0.0
     }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
 }
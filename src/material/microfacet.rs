@@ -0,0 +1,139 @@
+use crate::{model::vec3::Vec3, util::rtweekend::PI};
+
+/// Trowbridge-Reitz (GGX) microfacet distribution. Directions are expressed in a
+/// local shading frame where `z` is the surface normal and `x` the tangent.
+#[derive(Clone, Copy)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        // Perfectly smooth surfaces are better served by a delta lobe; keep a tiny
+        // roughness so the distribution stays finite.
+        Self {
+            alpha_x: alpha_x.max(1e-4),
+            alpha_y: alpha_y.max(1e-4),
+        }
+    }
+
+    /// Maps the perceptual roughness in [0, 1] to the distribution's alpha.
+    pub fn roughness_to_alpha(roughness: f64) -> f64 {
+        roughness * roughness
+    }
+
+    /// Whether the distribution is close enough to a mirror that it is sampled and
+    /// weighted as a delta lobe rather than through `d`.
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    pub fn d(&self, m: &Vec3) -> f64 {
+        if m.z() <= 0.0 {
+            return 0.0;
+        }
+
+        let x = m.x() / self.alpha_x;
+        let y = m.y() / self.alpha_y;
+        let denom = x * x + y * y + m.z() * m.z();
+
+        1.0 / (PI * self.alpha_x * self.alpha_y * denom * denom)
+    }
+
+    pub fn lambda(&self, w: &Vec3) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 <= 0.0 {
+            return 0.0;
+        }
+
+        let x = self.alpha_x * w.x();
+        let y = self.alpha_y * w.y();
+        let tan2_alpha2 = (x * x + y * y) / cos2;
+
+        0.5 * (-1.0 + (1.0 + tan2_alpha2).sqrt())
+    }
+
+    pub fn g1(&self, w: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height-correlated masking-shadowing term.
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Samples a microfacet normal visible from `wo` (Heitz 2018), for two numbers in
+    /// [0, 1).
+    pub fn sample_visible_normal(&self, wo: &Vec3, u: (f64, f64)) -> Vec3 {
+        // Stretch the view direction to the hemisphere configuration.
+        let vh = Vec3::new(self.alpha_x * wo.x(), self.alpha_y * wo.y(), wo.z()).unit_vector();
+
+        let lensq = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = if lensq > 0.0 {
+            Vec3::new(-vh.y(), vh.x(), 0.0) / lensq.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(&t1);
+
+        // Sample the projected area of the visible hemisphere.
+        let r = u.0.sqrt();
+        let phi = 2.0 * PI * u.1;
+        let p1 = r * phi.cos();
+        let mut p2 = r * phi.sin();
+        let s = 0.5 * (1.0 + vh.z());
+        p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * p2;
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        // Unstretch back to the ellipsoid configuration.
+        Vec3::new(
+            self.alpha_x * nh.x(),
+            self.alpha_y * nh.y(),
+            nh.z().max(1e-6),
+        )
+        .unit_vector()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::rtweekend::random_double;
+
+    use super::*;
+
+    #[test]
+    fn test_ggx_normals_are_normalized() {
+        // The projected microfacet area must equal the macro surface: integral of D(m) cos(m) = 1.
+        let distribution = TrowbridgeReitz::new(0.3, 0.6);
+        let n = 200_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let m = Vec3::random_cosine_direction();
+            // Cosine sampling pdf is cos / pi, so D * cos / pdf = D * pi.
+            sum += distribution.d(&m) * PI;
+        }
+
+        assert!((sum / n as f64 - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_only_mirror_like_distributions_are_smooth() {
+        assert!(TrowbridgeReitz::new(0.0, 0.0).is_smooth());
+        assert!(!TrowbridgeReitz::new(0.0, 0.1).is_smooth());
+        assert!(!TrowbridgeReitz::new(TrowbridgeReitz::roughness_to_alpha(0.2), 0.04).is_smooth());
+    }
+
+    #[test]
+    fn test_ggx_visible_normals_face_the_viewer() {
+        let distribution = TrowbridgeReitz::new(0.5, 0.5);
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        for _ in 0..1000 {
+            let m = distribution.sample_visible_normal(&wo, (random_double(), random_double()));
+            assert!(m.z() > 0.0);
+            assert!(wo.dot(&m) >= -1e-9);
+            assert!((m.length() - 1.0).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use crate::{
+    model::{hit::HitRecord, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+    texture::texture::Texture,
+};
+
+use super::{interior::Interior, material::Material};
+
+/// Blends two materials: each hit picks `b` with the probability given by the first
+/// channel of `weight`, and `a` otherwise.
+pub struct MixMaterial {
+    pub a: Arc<dyn Material + Sync + Send>,
+    pub b: Arc<dyn Material + Sync + Send>,
+    pub weight: Arc<dyn Texture + Sync + Send>,
+}
+
+impl MixMaterial {
+    pub fn new(
+        a: Arc<dyn Material + Sync + Send>,
+        b: Arc<dyn Material + Sync + Send>,
+        weight: Arc<dyn Texture + Sync + Send>,
+    ) -> Self {
+        Self { a, b, weight }
+    }
+
+    fn weight(&self, u: f64, v: f64, p: &Vec3) -> f64 {
+        self.weight.value(u, v, p).x().clamp(0.0, 1.0)
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Vec3,
+        scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        if sampler.next_1d() < self.weight(rec.u, rec.v, &rec.p) {
+            self.b.scatter(r_in, rec, attenuation, scattered, sampler)
+        } else {
+            self.a.scatter(r_in, rec, attenuation, scattered, sampler)
+        }
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> Vec3 {
+        let w = self.weight(rec.u, rec.v, &rec.p);
+        (1.0 - w) * self.a.eval(r_in, rec, wi) + w * self.b.eval(r_in, rec, wi)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> f64 {
+        let w = self.weight(rec.u, rec.v, &rec.p);
+        (1.0 - w) * self.a.pdf(r_in, rec, wi) + w * self.b.pdf(r_in, rec, wi)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Vec3) -> Vec3 {
+        let w = self.weight(u, v, p);
+        (1.0 - w) * self.a.emitted(r_in, rec, u, v, p) + w * self.b.emitted(r_in, rec, u, v, p)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.a.is_specular() && self.b.is_specular()
+    }
+
+    /// The interior of `a`, or of `b` if `a` has none.
+    fn interior(&self) -> Option<Interior> {
+        self.a.interior().or_else(|| self.b.interior())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        material::{dielectric::Dielectric, lambertian::Lambertian},
+        texture::solid_color::SolidColor,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_mix_keeps_the_interior_of_glass() {
+        let glass: Arc<dyn Material + Sync + Send> = Arc::new(Dielectric::new(1.5));
+        let paint: Arc<dyn Material + Sync + Send> =
+            Arc::new(Lambertian::new(&Vec3::new(0.5, 0.5, 0.5)));
+        let weight = Arc::new(SolidColor::new_with_values(0.5, 0.5, 0.5));
+
+        let mix = MixMaterial::new(glass.clone(), paint.clone(), weight.clone());
+        assert_eq!(mix.interior().map(|i| i.ior), Some(1.5));
+        let mix = MixMaterial::new(paint.clone(), glass, weight.clone());
+        assert_eq!(mix.interior().map(|i| i.ior), Some(1.5));
+        assert!(MixMaterial::new(paint.clone(), paint, weight)
+            .interior()
+            .is_none());
+    }
+}
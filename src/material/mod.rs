@@ -1,6 +1,17 @@
+pub mod bump_map;
+pub mod coated;
+pub mod conductor;
 pub mod dielectric;
 pub mod diffuse_light;
-pub mod isotropic;
+pub mod fresnel;
+pub mod interior;
 pub mod lambertian;
 pub mod material;
 pub mod metal;
+pub mod microfacet;
+pub mod mix;
+pub mod normal_map;
+pub mod phase_function;
+pub mod principled;
+pub mod rough_dielectric;
+pub mod volume_scattering;
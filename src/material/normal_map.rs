@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use crate::{
+    model::{hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+    texture::texture::Texture,
+};
+
+use super::{interior::Interior, material::Material};
+
+/// Wraps a material and perturbs its shading normal with a tangent-space normal map,
+/// stored the usual way: red along `dpdu`, green along the bitangent, blue outward.
+/// Maps usually come from an `ImageTexture`, but any texture will do.
+pub struct NormalMap {
+    pub base: Arc<dyn Material + Sync + Send>,
+    pub map: Arc<dyn Texture + Sync + Send>,
+    pub strength: f64,
+}
+
+impl NormalMap {
+    pub fn new(base: Arc<dyn Material + Sync + Send>, map: Arc<dyn Texture + Sync + Send>) -> Self {
+        Self {
+            base,
+            map,
+            strength: 1.0,
+        }
+    }
+
+    /// Scales the tangential part of the mapped normals to exaggerate or soften the detail.
+    pub fn with_strength(mut self, strength: f64) -> Self {
+        self.strength = strength;
+        self
+    }
+}
+
+impl NormalMap {
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let texel = self.map.value(rec.u, rec.v, &rec.p);
+        let local = Vec3::new(
+            self.strength * (2.0 * texel.x() - 1.0),
+            self.strength * (2.0 * texel.y() - 1.0),
+            2.0 * texel.z() - 1.0,
+        );
+
+        // Build the frame from the outward normal so the map does not depend on the side hit.
+        let outward_normal = if rec.front_face {
+            rec.normal
+        } else {
+            -rec.normal
+        };
+        let tbn = Onb::build_from_w_and_tangent(&outward_normal, &rec.dpdu);
+        let shading_normal = tbn.local(&local).unit_vector();
+        if rec.front_face {
+            shading_normal
+        } else {
+            -shading_normal
+        }
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Vec3,
+        scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        scatter_with_shading_normal(
+            self.base.as_ref(),
+            r_in,
+            rec,
+            &self.shading_normal(rec),
+            attenuation,
+            scattered,
+            sampler,
+        )
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> Vec3 {
+        eval_with_shading_normal(self.base.as_ref(), r_in, rec, &self.shading_normal(rec), wi)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> f64 {
+        pdf_with_shading_normal(self.base.as_ref(), r_in, rec, &self.shading_normal(rec), wi)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Vec3) -> Vec3 {
+        self.base.emitted(r_in, rec, u, v, p)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.base.is_specular()
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        self.base.interior()
+    }
+}
+
+/// Lets `base` scatter as if the surface had `shading_normal`, which must face the same
+/// side as `rec.normal`. Directions that end up on opposite sides of the shading and the
+/// geometric surface are absorbed, so perturbed normals cannot leak light through walls.
+pub fn scatter_with_shading_normal(
+    base: &(dyn Material + Sync + Send),
+    r_in: &Ray,
+    rec: &HitRecord,
+    shading_normal: &Vec3,
+    attenuation: &mut Vec3,
+    scattered: &mut Ray,
+    sampler: &mut dyn SamplerState,
+) -> bool {
+    let shading_rec = shading_record(r_in, rec, shading_normal);
+    if !base.scatter(r_in, &shading_rec, attenuation, scattered, sampler) {
+        return false;
+    }
+
+    let dir = scattered.dir();
+    (dir.dot(&rec.normal) > 0.0) == (dir.dot(&shading_rec.normal) > 0.0)
+}
+
+/// Counterpart of `scatter_with_shading_normal` for evaluating the BSDF of `base`.
+pub fn eval_with_shading_normal(
+    base: &(dyn Material + Sync + Send),
+    r_in: &Ray,
+    rec: &HitRecord,
+    shading_normal: &Vec3,
+    wi: &Vec3,
+) -> Vec3 {
+    let shading_rec = shading_record(r_in, rec, shading_normal);
+    if (wi.dot(&rec.normal) > 0.0) != (wi.dot(&shading_rec.normal) > 0.0) {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    base.eval(r_in, &shading_rec, wi)
+}
+
+/// Counterpart of `scatter_with_shading_normal` for the sampling density of `base`.
+pub fn pdf_with_shading_normal(
+    base: &(dyn Material + Sync + Send),
+    r_in: &Ray,
+    rec: &HitRecord,
+    shading_normal: &Vec3,
+    wi: &Vec3,
+) -> f64 {
+    let shading_rec = shading_record(r_in, rec, shading_normal);
+    if (wi.dot(&rec.normal) > 0.0) != (wi.dot(&shading_rec.normal) > 0.0) {
+        return 0.0;
+    }
+
+    base.pdf(r_in, &shading_rec, wi)
+}
+
+fn shading_record(r_in: &Ray, rec: &HitRecord, shading_normal: &Vec3) -> HitRecord {
+    let geometric_normal = rec.normal;
+    let wo = -r_in.dir().unit_vector();
+
+    // A shading normal facing away from the viewer cannot be lit; fall back to the geometry.
+    let normal = if shading_normal.dot(&wo) > 0.0 && shading_normal.dot(&geometric_normal) > 0.0 {
+        *shading_normal
+    } else {
+        geometric_normal
+    };
+
+    let mut shading_rec = rec.clone();
+    shading_rec.normal = normal;
+    shading_rec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::lambertian::Lambertian, sampler::independent::IndependentSampler,
+        texture::solid_color::SolidColor,
+    };
+
+    #[test]
+    fn test_flat_normal_map_keeps_scattering_above_surface() {
+        let flat = Arc::new(SolidColor::new_with_values(0.5, 0.5, 1.0));
+        let material = NormalMap::new(Arc::new(Lambertian::new(&Vec3::new(0.5, 0.5, 0.5))), flat);
+
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            dpdu: Vec3::new(1.0, 0.0, 0.0),
+            front_face: true,
+            ..Default::default()
+        };
+        let r_in = Ray::new(&Vec3::new(0.0, 1.0, 1.0), &Vec3::new(0.0, -1.0, -1.0), 0.0);
+
+        for _ in 0..1000 {
+            let mut attenuation = Vec3::default();
+            let mut scattered = Ray::new(&Vec3::default(), &Vec3::default(), 0.0);
+            assert!(material.scatter(
+                &r_in,
+                &rec,
+                &mut attenuation,
+                &mut scattered,
+                &mut IndependentSampler
+            ));
+            assert!(scattered.dir().y() > -1e-9);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    model::{onb::Onb, vec3::Vec3},
+    sampler::sampler::SamplerState,
+    util::rtweekend::PI,
+};
+
+/// Angular distribution of light scattered inside a participating medium. `wo` is the
+/// direction the incoming light travels and `wi` the one it leaves in, so forward
+/// scattering keeps `wi` close to `wo`. Values are per steradian and integrate to one.
+pub trait PhaseFunction {
+    fn p(&self, wo: &Vec3, wi: &Vec3) -> f64;
+
+    /// Draws a scattered direction, returning it as a unit vector.
+    fn sample(&self, wo: &Vec3, sampler: &mut dyn SamplerState) -> Vec3;
+
+    /// Solid angle density with which `sample` picks `wi`.
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        self.p(wo, wi)
+    }
+}
+
+/// Scatters equally in all directions.
+pub struct IsotropicPhase;
+
+impl PhaseFunction for IsotropicPhase {
+    fn p(&self, _wo: &Vec3, _wi: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn sample(&self, _wo: &Vec3, sampler: &mut dyn SamplerState) -> Vec3 {
+        Vec3::sample_unit_vector(sampler.next_2d())
+    }
+}
+
+/// Henyey-Greenstein lobe, whose asymmetry `g` in (-1, 1) is the mean cosine of the
+/// scattering angle: positive values scatter forward as in fog and clouds, negative
+/// ones back towards the light, and zero is isotropic.
+pub struct HenyeyGreenstein {
+    pub g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64) -> Self {
+        Self {
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+}
+
+impl PhaseFunction for HenyeyGreenstein {
+    fn p(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        henyey_greenstein(cos_theta(wo, wi), self.g)
+    }
+
+    fn sample(&self, wo: &Vec3, sampler: &mut dyn SamplerState) -> Vec3 {
+        sample_henyey_greenstein(wo, self.g, sampler.next_2d())
+    }
+}
+
+/// Blend of a forward and a backward Henyey-Greenstein lobe, `weight` giving the share
+/// of the first. Matches the strong forward peak and faint backscatter of smoke and
+/// water droplets better than a single lobe.
+pub struct DoubleHenyeyGreenstein {
+    pub g1: f64,
+    pub g2: f64,
+    pub weight: f64,
+}
+
+impl DoubleHenyeyGreenstein {
+    pub fn new(g1: f64, g2: f64, weight: f64) -> Self {
+        Self {
+            g1: g1.clamp(-0.999, 0.999),
+            g2: g2.clamp(-0.999, 0.999),
+            weight: weight.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl PhaseFunction for DoubleHenyeyGreenstein {
+    fn p(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        let cos_theta = cos_theta(wo, wi);
+        self.weight * henyey_greenstein(cos_theta, self.g1)
+            + (1.0 - self.weight) * henyey_greenstein(cos_theta, self.g2)
+    }
+
+    fn sample(&self, wo: &Vec3, sampler: &mut dyn SamplerState) -> Vec3 {
+        let g = if sampler.next_1d() < self.weight {
+            self.g1
+        } else {
+            self.g2
+        };
+        sample_henyey_greenstein(wo, g, sampler.next_2d())
+    }
+}
+
+/// Scattering by particles much smaller than the wavelength, such as air molecules,
+/// with equal forward and backward peaks.
+pub struct Rayleigh;
+
+impl PhaseFunction for Rayleigh {
+    fn p(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        let cos_theta = cos_theta(wo, wi);
+        3.0 / (16.0 * PI) * (1.0 + cos_theta * cos_theta)
+    }
+
+    fn sample(&self, wo: &Vec3, sampler: &mut dyn SamplerState) -> Vec3 {
+        // The CDF over the cosine is (cos³ + 3 cos + 4) / 8; invert the cubic with
+        // Cardano's formula, which has a single real root here.
+        let (u, v) = sampler.next_2d();
+        let q = 4.0 - 8.0 * u;
+        let d = (0.25 * q * q + 1.0).sqrt();
+        let cos_theta = ((-0.5 * q + d).cbrt() + (-0.5 * q - d).cbrt()).clamp(-1.0, 1.0);
+
+        direction_around(wo, cos_theta, v)
+    }
+}
+
+fn cos_theta(wo: &Vec3, wi: &Vec3) -> f64 {
+    wo.unit_vector().dot(&wi.unit_vector())
+}
+
+fn henyey_greenstein(cos_theta: f64, g: f64) -> f64 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+}
+
+fn sample_henyey_greenstein(wo: &Vec3, g: f64, (xi, v): (f64, f64)) -> Vec3 {
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - 2.0 * xi
+    } else {
+        let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+        (1.0 + g * g - s * s) / (2.0 * g)
+    };
+
+    direction_around(wo, cos_theta.clamp(-1.0, 1.0), v)
+}
+
+/// Unit direction at angle `acos(cos_theta)` from `wo`, at the fraction `v` of the way
+/// around it.
+fn direction_around(wo: &Vec3, cos_theta: f64, v: f64) -> Vec3 {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+    let uvw = Onb::build_from_w(wo);
+
+    uvw.local(&Vec3::new(
+        sin_theta * phi.cos(),
+        sin_theta * phi.sin(),
+        cos_theta,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampler::independent::IndependentSampler;
+
+    use super::*;
+
+    #[test]
+    fn test_phase_functions_are_normalized_and_sampled_with_their_mean_cosine() {
+        let wo = Vec3::new(0.0, 0.0, 1.0);
+        let phase_functions: Vec<(Box<dyn PhaseFunction>, f64)> = vec![
+            (Box::new(IsotropicPhase), 0.0),
+            (Box::new(HenyeyGreenstein::new(0.7)), 0.7),
+            (Box::new(HenyeyGreenstein::new(-0.4)), -0.4),
+            (
+                Box::new(DoubleHenyeyGreenstein::new(0.8, -0.3, 0.75)),
+                0.525,
+            ),
+            (Box::new(Rayleigh), 0.0),
+        ];
+
+        for (phase, mean_cos) in phase_functions.iter() {
+            // Integrate over the sphere with the midpoint rule in cos(theta).
+            let n = 2000;
+            let mut integral = 0.0;
+            for i in 0..n {
+                let cos_theta = -1.0 + 2.0 * (i as f64 + 0.5) / n as f64;
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let wi = Vec3::new(sin_theta, 0.0, cos_theta);
+                integral += 2.0 * PI * phase.p(&wo, &wi) * 2.0 / n as f64;
+            }
+            assert!((integral - 1.0).abs() < 1e-3);
+
+            let samples = 100_000;
+            let mut sum = 0.0;
+            for _ in 0..samples {
+                let wi = phase.sample(&wo, &mut IndependentSampler);
+                assert!((wi.length() - 1.0).abs() < 1e-9);
+                sum += wi.z();
+            }
+            assert!((sum / samples as f64 - mean_cos).abs() < 0.01);
+        }
+    }
+}
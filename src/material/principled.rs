@@ -0,0 +1,427 @@
+use std::sync::Arc;
+
+use crate::{
+    model::{hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+    texture::{solid_color::SolidColor, texture::Texture},
+    util::rtweekend::PI,
+};
+
+use super::{
+    fresnel::fresnel_schlick, interior::Interior, material::Material, microfacet::TrowbridgeReitz,
+    rough_dielectric::sample_rough_interface,
+};
+
+/// Principled "uber" material in the spirit of the Disney BSDF. Every parameter is a
+/// texture; scalar parameters are read from the first channel and expected in [0, 1].
+///
+/// The BSDF is a blend of a glass lobe, weighted by `(1 - metallic) * transmission`, and
+/// an opaque part made of Disney diffuse, sheen, a GGX specular lobe shared between the
+/// dielectric and metallic cases, and a GGX clear coat.
+pub struct Principled {
+    pub base_color: Arc<dyn Texture + Sync + Send>,
+    pub metallic: Arc<dyn Texture + Sync + Send>,
+    pub roughness: Arc<dyn Texture + Sync + Send>,
+    pub specular: Arc<dyn Texture + Sync + Send>,
+    pub sheen: Arc<dyn Texture + Sync + Send>,
+    pub clearcoat: Arc<dyn Texture + Sync + Send>,
+    pub transmission: Arc<dyn Texture + Sync + Send>,
+    pub emission: Arc<dyn Texture + Sync + Send>,
+    pub ir: f64,
+    /// Decides which interior wins where this object overlaps another, see `Interior`.
+    pub priority: i32,
+    /// Absorption coefficient per unit of distance travelled inside the object.
+    pub absorption: Vec3,
+}
+
+/// Parameters of a `Principled` material looked up at one hit point.
+struct Lobes {
+    base_color: Vec3,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    sheen: f64,
+    clearcoat: f64,
+    transmission: f64,
+}
+
+const CLEARCOAT_ALPHA: f64 = 0.05;
+
+impl Principled {
+    /// A plastic-like dielectric with the given base color; tweak the other
+    /// parameters with the `with_*` methods.
+    pub fn new(base_color: &Vec3) -> Self {
+        Principled::new_with_texture(Arc::new(SolidColor::new(base_color)))
+    }
+
+    pub fn new_with_texture(base_color: Arc<dyn Texture + Sync + Send>) -> Principled {
+        let constant = |x: f64| -> Arc<dyn Texture + Sync + Send> {
+            Arc::new(SolidColor::new_with_values(x, x, x))
+        };
+
+        Principled {
+            base_color,
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            sheen: constant(0.0),
+            clearcoat: constant(0.0),
+            transmission: constant(0.0),
+            emission: constant(0.0),
+            ir: 1.5,
+            priority: 0,
+            absorption: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn with_metallic(mut self, metallic: Arc<dyn Texture + Sync + Send>) -> Self {
+        self.metallic = metallic;
+        self
+    }
+
+    pub fn with_roughness(mut self, roughness: Arc<dyn Texture + Sync + Send>) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn with_specular(mut self, specular: Arc<dyn Texture + Sync + Send>) -> Self {
+        self.specular = specular;
+        self
+    }
+
+    pub fn with_sheen(mut self, sheen: Arc<dyn Texture + Sync + Send>) -> Self {
+        self.sheen = sheen;
+        self
+    }
+
+    pub fn with_clearcoat(mut self, clearcoat: Arc<dyn Texture + Sync + Send>) -> Self {
+        self.clearcoat = clearcoat;
+        self
+    }
+
+    pub fn with_transmission(mut self, transmission: Arc<dyn Texture + Sync + Send>) -> Self {
+        self.transmission = transmission;
+        self
+    }
+
+    pub fn with_emission(mut self, emission: Arc<dyn Texture + Sync + Send>) -> Self {
+        self.emission = emission;
+        self
+    }
+
+    pub fn with_ior(mut self, ir: f64) -> Self {
+        self.ir = ir;
+        self
+    }
+
+    fn lobes(&self, rec: &HitRecord) -> Lobes {
+        let scalar = |texture: &Arc<dyn Texture + Sync + Send>| {
+            texture.value(rec.u, rec.v, &rec.p).x().clamp(0.0, 1.0)
+        };
+
+        Lobes {
+            base_color: self.base_color.filtered_value(rec),
+            metallic: scalar(&self.metallic),
+            roughness: scalar(&self.roughness),
+            specular: scalar(&self.specular),
+            sheen: scalar(&self.sheen),
+            clearcoat: scalar(&self.clearcoat),
+            transmission: scalar(&self.transmission),
+        }
+    }
+}
+
+impl Lobes {
+    fn specular_distribution(&self) -> TrowbridgeReitz {
+        let alpha = TrowbridgeReitz::roughness_to_alpha(self.roughness);
+        TrowbridgeReitz::new(alpha, alpha)
+    }
+
+    fn clearcoat_distribution(&self) -> TrowbridgeReitz {
+        TrowbridgeReitz::new(CLEARCOAT_ALPHA, CLEARCOAT_ALPHA)
+    }
+
+    fn specular_f0(&self) -> Vec3 {
+        let dielectric = 0.08 * self.specular * Vec3::new(1.0, 1.0, 1.0);
+        (1.0 - self.metallic) * dielectric + self.metallic * self.base_color
+    }
+
+    /// Probabilities of sampling the diffuse (with sheen), specular and clear coat lobes.
+    fn sampling_weights(&self) -> (f64, f64, f64) {
+        let diffuse = (1.0 - self.metallic) * self.base_color.luminance().max(0.05);
+        let specular = self.specular_f0().luminance().max(0.2);
+        let clearcoat = 0.25 * self.clearcoat;
+        let total = diffuse + specular + clearcoat;
+
+        (diffuse / total, specular / total, clearcoat / total)
+    }
+
+    /// BSDF of the opaque part times the cosine term, in the local shading frame.
+    fn eval_opaque(&self, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        let wh = (wo + wi).unit_vector();
+        let cos_d = wi.dot(&wh);
+        let schlick_weight = |cos: f64| (1.0 - cos.clamp(0.0, 1.0)).powi(5);
+
+        // Disney diffuse with its grazing retro-reflection, plus sheen.
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let fl = schlick_weight(wi.z());
+        let fv = schlick_weight(wo.z());
+        let diffuse = self.base_color / PI * (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv);
+        let sheen = self.sheen * schlick_weight(cos_d) * Vec3::new(1.0, 1.0, 1.0);
+        let mut f = (1.0 - self.metallic) * (diffuse + sheen);
+
+        let specular = self.specular_distribution();
+        let fresnel = fresnel_schlick(cos_d, &self.specular_f0());
+        f += fresnel * specular.d(&wh) * specular.g(wo, wi) / (4.0 * wo.z() * wi.z());
+
+        if self.clearcoat > 0.0 {
+            let clearcoat = self.clearcoat_distribution();
+            let fresnel = fresnel_schlick(cos_d, &Vec3::new(0.04, 0.04, 0.04));
+            f += 0.25 * self.clearcoat * fresnel * clearcoat.d(&wh) * clearcoat.g(wo, wi)
+                / (4.0 * wo.z() * wi.z());
+        }
+
+        f * wi.z()
+    }
+
+    /// Density of sampling `wi` from the opaque lobes, mixed by their sampling weights.
+    fn pdf_opaque(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+
+        let (diffuse, specular, clearcoat) = self.sampling_weights();
+        let wh = (wo + wi).unit_vector();
+        let reflection_pdf = |distribution: TrowbridgeReitz| {
+            distribution.g1(wo) * distribution.d(&wh) / (4.0 * wo.z())
+        };
+
+        diffuse * wi.z() / PI
+            + specular * reflection_pdf(self.specular_distribution())
+            + clearcoat * reflection_pdf(self.clearcoat_distribution())
+    }
+
+    fn sample_opaque(&self, wo: &Vec3, sampler: &mut dyn SamplerState) -> Vec3 {
+        let (diffuse, specular, _) = self.sampling_weights();
+        let choice = sampler.next_1d();
+        let u = sampler.next_2d();
+
+        if choice < diffuse {
+            Vec3::sample_cosine_direction(u)
+        } else if choice < diffuse + specular {
+            let wm = self.specular_distribution().sample_visible_normal(wo, u);
+            (-wo).reflect(&wm)
+        } else {
+            let wm = self.clearcoat_distribution().sample_visible_normal(wo, u);
+            (-wo).reflect(&wm)
+        }
+    }
+}
+
+impl Material for Principled {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Vec3,
+        scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let lobes = self.lobes(rec);
+        let uvw = Onb::build_from_w(&rec.normal);
+        let wo = uvw.to_local(&(-r_in.dir().unit_vector()));
+        if wo.z() <= 0.0 {
+            return false;
+        }
+
+        // The glass lobe is picked with exactly its blend weight, so each branch
+        // only has to account for its own sampling.
+        let glass = (1.0 - lobes.metallic) * lobes.transmission;
+        let wi = if sampler.next_1d() < glass {
+            let eta = if rec.front_face {
+                rec.exterior_ior / self.ir
+            } else {
+                self.ir / rec.exterior_ior
+            };
+            let (wi, weight, refracted) =
+                match sample_rough_interface(&lobes.specular_distribution(), &wo, eta, sampler) {
+                    Some(sample) => sample,
+                    None => return false,
+                };
+
+            *attenuation = if refracted {
+                weight * lobes.base_color
+            } else {
+                Vec3::new(weight, weight, weight)
+            };
+            wi
+        } else {
+            let wi = lobes.sample_opaque(&wo, sampler);
+            let pdf = lobes.pdf_opaque(&wo, &wi);
+            if pdf <= 0.0 {
+                return false;
+            }
+
+            *attenuation = lobes.eval_opaque(&wo, &wi) / pdf;
+            wi
+        };
+
+        *scattered = Ray::new(&rec.p, &uvw.local(&wi), r_in.time());
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> Vec3 {
+        // Only the opaque part; the glass lobe is left to the scattered paths.
+        let lobes = self.lobes(rec);
+        let uvw = Onb::build_from_w(&rec.normal);
+        let wo = uvw.to_local(&(-r_in.dir().unit_vector()));
+        let glass = (1.0 - lobes.metallic) * lobes.transmission;
+        (1.0 - glass) * lobes.eval_opaque(&wo, &uvw.to_local(wi))
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> f64 {
+        let lobes = self.lobes(rec);
+        let uvw = Onb::build_from_w(&rec.normal);
+        let wo = uvw.to_local(&(-r_in.dir().unit_vector()));
+        let glass = (1.0 - lobes.metallic) * lobes.transmission;
+        (1.0 - glass) * lobes.pdf_opaque(&wo, &uvw.to_local(wi))
+    }
+
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, u: f64, v: f64, p: &Vec3) -> Vec3 {
+        self.emission.value(u, v, p)
+    }
+
+    /// Smooth metal and smooth glass reduce to a single mirror or refraction lobe.
+    /// Without a hit point the parameters are read at the texture origin, so they
+    /// are expected to be constant for this to hold.
+    fn is_specular(&self) -> bool {
+        let scalar = |texture: &Arc<dyn Texture + Sync + Send>| {
+            texture
+                .value(0.0, 0.0, &Vec3::default())
+                .x()
+                .clamp(0.0, 1.0)
+        };
+        let metallic = scalar(&self.metallic);
+        let alpha = TrowbridgeReitz::roughness_to_alpha(scalar(&self.roughness));
+        let glass = (1.0 - metallic) * scalar(&self.transmission);
+        let metal = metallic >= 1.0 && scalar(&self.clearcoat) <= 0.0;
+
+        TrowbridgeReitz::new(alpha, alpha).is_smooth() && (metal || glass >= 1.0)
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        Some(Interior {
+            ior: self.ir,
+            priority: self.priority,
+            absorption: self.absorption,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampler::independent::IndependentSampler;
+
+    use super::*;
+
+    fn mean_albedo(material: &Principled, cos_theta: f64) -> Vec3 {
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            front_face: true,
+            ..Default::default()
+        };
+        let dir = Vec3::new((1.0 - cos_theta * cos_theta).sqrt(), 0.0, -cos_theta);
+        let r_in = Ray::new(&Vec3::new(0.0, 0.0, 1.0), &dir, 0.0);
+
+        let n = 50_000;
+        let mut sum = Vec3::new(0.0, 0.0, 0.0);
+        for _ in 0..n {
+            let mut attenuation = Vec3::new(0.0, 0.0, 0.0);
+            let mut scattered = Ray::new(&Vec3::default(), &Vec3::default(), 0.0);
+            if material.scatter(
+                &r_in,
+                &rec,
+                &mut attenuation,
+                &mut scattered,
+                &mut IndependentSampler,
+            ) {
+                sum += attenuation;
+            }
+        }
+
+        sum / n as f64
+    }
+
+    #[test]
+    fn test_principled_white_metal_conserves_energy() {
+        let material = Principled::new(&Vec3::new(1.0, 1.0, 1.0))
+            .with_metallic(Arc::new(SolidColor::new_with_values(1.0, 1.0, 1.0)))
+            .with_roughness(Arc::new(SolidColor::new_with_values(0.3, 0.3, 0.3)));
+
+        for cos_theta in [0.2, 0.6, 1.0] {
+            let albedo = mean_albedo(&material, cos_theta);
+            assert!(albedo.x() <= 1.01, "albedo {} at cos {}", albedo, cos_theta);
+            assert!(albedo.x() > 0.85, "albedo {} at cos {}", albedo, cos_theta);
+        }
+    }
+
+    #[test]
+    fn test_principled_black_dielectric_only_reflects_specular() {
+        let material = Principled::new(&Vec3::new(0.0, 0.0, 0.0));
+
+        let albedo = mean_albedo(&material, 1.0);
+        assert!(albedo.x() > 0.02 && albedo.x() < 0.06, "albedo {}", albedo);
+    }
+
+    fn smooth_glass() -> Principled {
+        Principled::new(&Vec3::new(1.0, 1.0, 1.0))
+            .with_transmission(Arc::new(SolidColor::new_with_values(1.0, 1.0, 1.0)))
+            .with_roughness(Arc::new(SolidColor::new_with_values(0.0, 0.0, 0.0)))
+    }
+
+    #[test]
+    fn test_principled_glass_refracts_against_the_exterior() {
+        let material = smooth_glass();
+        assert_eq!(material.interior().map(|i| i.ior), Some(1.5));
+
+        // Glass immersed in glass of the same index is an invisible interface.
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            front_face: true,
+            exterior_ior: 1.5,
+            ..Default::default()
+        };
+        let dir = Vec3::new(0.8, 0.0, -0.6);
+        let r_in = Ray::new(&Vec3::new(0.0, 0.0, 1.0), &dir, 0.0);
+        for _ in 0..100 {
+            let mut attenuation = Vec3::new(0.0, 0.0, 0.0);
+            let mut scattered = Ray::new(&Vec3::default(), &Vec3::default(), 0.0);
+            assert!(material.scatter(
+                &r_in,
+                &rec,
+                &mut attenuation,
+                &mut scattered,
+                &mut IndependentSampler,
+            ));
+            assert!((scattered.dir().unit_vector() - dir).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_only_smooth_metal_and_glass_are_specular() {
+        let one = || Arc::new(SolidColor::new_with_values(1.0, 1.0, 1.0));
+        let smooth_metal = Principled::new(&Vec3::new(0.9, 0.6, 0.3))
+            .with_metallic(one())
+            .with_roughness(Arc::new(SolidColor::new_with_values(0.0, 0.0, 0.0)));
+
+        assert!(smooth_glass().is_specular());
+        assert!(smooth_metal.is_specular());
+        assert!(!smooth_metal.with_clearcoat(one()).is_specular());
+        assert!(!smooth_glass().with_roughness(one()).is_specular());
+        assert!(!Principled::new(&Vec3::new(0.5, 0.5, 0.5)).is_specular());
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use crate::{
+    model::{hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+    texture::{solid_color::SolidColor, texture::Texture},
+};
+
+use super::{
+    fresnel::fresnel_dielectric, interior::Interior, material::Material,
+    microfacet::TrowbridgeReitz,
+};
+
+/// Frosted glass: microfacet reflection and transmission after Walter et al. 2007,
+/// with exact Fresnel. Roughness is read from the first channel of a texture, and
+/// light travelling inside is absorbed following the Beer-Lambert law.
+pub struct RoughDielectric {
+    pub ir: f64,
+    pub roughness: Arc<dyn Texture + Sync + Send>,
+    /// Absorption coefficient per unit of distance travelled inside the object.
+    pub absorption: Vec3,
+    /// Decides which interior wins where this object overlaps another, see `Interior`.
+    pub priority: i32,
+}
+
+impl RoughDielectric {
+    pub fn new(index_of_refraction: f64, roughness: f64) -> Self {
+        RoughDielectric::new_with_texture(
+            index_of_refraction,
+            Arc::new(SolidColor::new_with_values(roughness, roughness, roughness)),
+        )
+    }
+
+    pub fn new_with_texture(
+        index_of_refraction: f64,
+        roughness: Arc<dyn Texture + Sync + Send>,
+    ) -> RoughDielectric {
+        RoughDielectric {
+            ir: index_of_refraction,
+            roughness,
+            absorption: Vec3::new(0.0, 0.0, 0.0),
+            priority: 0,
+        }
+    }
+
+    /// Colored glass whose transmittance is `color` after travelling `distance` inside it.
+    pub fn new_tinted(
+        index_of_refraction: f64,
+        roughness: Arc<dyn Texture + Sync + Send>,
+        color: &Vec3,
+        distance: f64,
+    ) -> RoughDielectric {
+        let mut absorption = Vec3::default();
+        for c in 0..3 {
+            absorption[c] = -color[c].max(1e-6).ln() / distance;
+        }
+
+        RoughDielectric {
+            ir: index_of_refraction,
+            roughness,
+            absorption,
+            priority: 0,
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Vec3,
+        scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let roughness = self
+            .roughness
+            .value(rec.u, rec.v, &rec.p)
+            .x()
+            .clamp(0.0, 1.0);
+        let alpha = TrowbridgeReitz::roughness_to_alpha(roughness);
+        let distribution = TrowbridgeReitz::new(alpha, alpha);
+        let eta = if rec.front_face {
+            rec.exterior_ior / self.ir
+        } else {
+            self.ir / rec.exterior_ior
+        };
+
+        // The normal always faces the incoming ray, so `wo` lies in the upper hemisphere.
+        let uvw = Onb::build_from_w(&rec.normal);
+        let wo = uvw.to_local(&(-r_in.dir().unit_vector()));
+        if wo.z() <= 0.0 {
+            return false;
+        }
+
+        let (wi, weight, _) = match sample_rough_interface(&distribution, &wo, eta, sampler) {
+            Some(sample) => sample,
+            None => return false,
+        };
+        *attenuation = Vec3::new(weight, weight, weight);
+
+        *scattered = Ray::new(&rec.p, &uvw.local(&wi), r_in.time());
+        true
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        Some(Interior {
+            ior: self.ir,
+            priority: self.priority,
+            absorption: self.absorption,
+        })
+    }
+}
+
+/// Samples reflection or refraction through a rough dielectric interface seen from the
+/// local direction `wo`, where `eta` is the incident over transmitted index ratio.
+/// Returns the local scattered direction, its weight and whether it was refracted.
+pub fn sample_rough_interface(
+    distribution: &TrowbridgeReitz,
+    wo: &Vec3,
+    eta: f64,
+    sampler: &mut dyn SamplerState,
+) -> Option<(Vec3, f64, bool)> {
+    let wm = distribution.sample_visible_normal(wo, sampler.next_2d());
+    let refracted = sampler.next_1d() >= fresnel_dielectric(wo.dot(&wm), eta);
+    let wi = if refracted {
+        (-wo).refract(&wm, eta)
+    } else {
+        (-wo).reflect(&wm)
+    };
+
+    if (wi.z() < 0.0) != refracted || wi.z() == 0.0 {
+        return None;
+    }
+
+    // Fresnel was used to choose the lobe, so only the shadowing ratio remains.
+    Some((wi, distribution.g(wo, &wi) / distribution.g1(wo), refracted))
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use crate::{
+    model::{hit::HitRecord, ray::Ray, vec3::Vec3},
+    sampler::sampler::SamplerState,
+    texture::{solid_color::SolidColor, texture::Texture},
+};
+
+use super::{
+    material::Material,
+    phase_function::{IsotropicPhase, PhaseFunction},
+};
+
+/// Material of scattering events inside a participating medium: the light keeps the
+/// fraction `albedo` and leaves in a direction drawn from the phase function. A medium
+/// with an `emission` glows in proportion to what it absorbs, `1 - albedo`.
+pub struct VolumeScattering {
+    pub albedo: Arc<dyn Texture + Sync + Send>,
+    pub phase_function: Arc<dyn PhaseFunction + Sync + Send>,
+    pub emission: Vec3,
+}
+
+impl VolumeScattering {
+    pub fn new(
+        albedo: Arc<dyn Texture + Sync + Send>,
+        phase_function: Arc<dyn PhaseFunction + Sync + Send>,
+    ) -> Self {
+        Self {
+            albedo,
+            phase_function,
+            emission: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn new_with_color(c: Vec3) -> Self {
+        Self::new(Arc::new(SolidColor::new(&c)), Arc::new(IsotropicPhase))
+    }
+
+    pub fn with_emission(mut self, emission: &Vec3) -> Self {
+        self.emission = *emission;
+        self
+    }
+}
+
+impl Material for VolumeScattering {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Vec3,
+        scattered: &mut Ray,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        // Directions are sampled exactly in proportion to the phase function, which
+        // therefore cancels out of the weight.
+        let direction = self.phase_function.sample(r_in.dir(), sampler);
+        *scattered = Ray::new(&rec.p, &direction, r_in.time());
+        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+
+        true
+    }
+
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, u: f64, v: f64, p: &Vec3) -> Vec3 {
+        if self.emission.near_zero() {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        (Vec3::new(1.0, 1.0, 1.0) - self.albedo.value(u, v, p)) * self.emission
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vec3) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, &rec.p) * self.phase_function.p(r_in.dir(), wi)
+    }
+
+    fn pdf(&self, r_in: &Ray, _rec: &HitRecord, wi: &Vec3) -> f64 {
+        self.phase_function.pdf(r_in.dir(), wi)
+    }
+
+    fn is_volume(&self) -> bool {
+        true
+    }
+}
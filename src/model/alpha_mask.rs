@@ -0,0 +1,222 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::{
+    sampler::{independent::IndependentSampler, sampler::SamplerState},
+    texture::texture::Texture,
+};
+
+use super::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    ray::Ray,
+};
+
+/// Upper bound on the transparent texels skipped along a single ray, and on the
+/// transparent points rejected when sampling the surface.
+const MAX_SKIPPED: usize = 64;
+/// Points on the surface used to estimate how much of it the mask keeps.
+const COVERAGE_SAMPLES: usize = 4096;
+
+/// Cuts holes into a hittable: intersections where the first channel of `mask` is
+/// below `threshold` are ignored and the ray carries on to the next surface. Because
+/// the mask is applied inside `hit`, it holds for every ray, camera, scattered or
+/// photon alike, wherever the wrapper sits in the scene hierarchy. Masked hittables
+/// can be lights, emitting only from their opaque parts.
+pub struct AlphaMask {
+    pub hittable: Arc<dyn Hittable + Sync + Send>,
+    pub mask: Arc<dyn Texture + Sync + Send>,
+    /// Opacity threshold, or `None` to treat the mask as a probability of being hit,
+    /// which renders semi-transparent texels correctly on average.
+    pub threshold: Option<f64>,
+    /// Fraction of the area the mask keeps, estimated when first needed.
+    coverage: OnceLock<f64>,
+}
+
+impl AlphaMask {
+    pub fn new(
+        hittable: Arc<dyn Hittable + Sync + Send>,
+        mask: Arc<dyn Texture + Sync + Send>,
+    ) -> Self {
+        Self {
+            hittable,
+            mask,
+            threshold: Some(0.5),
+            coverage: OnceLock::new(),
+        }
+    }
+
+    pub fn new_stochastic(
+        hittable: Arc<dyn Hittable + Sync + Send>,
+        mask: Arc<dyn Texture + Sync + Send>,
+    ) -> AlphaMask {
+        AlphaMask {
+            hittable,
+            mask,
+            threshold: None,
+            coverage: OnceLock::new(),
+        }
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        let opacity = self.mask.value(rec.u, rec.v, &rec.p).x();
+        match self.threshold {
+            Some(threshold) if opacity >= threshold => 1.0,
+            Some(_) => 0.0,
+            None => opacity.clamp(0.0, 1.0),
+        }
+    }
+
+    fn is_opaque(&self, rec: &HitRecord, sampler: &mut dyn SamplerState) -> bool {
+        match self.threshold {
+            Some(_) => self.opacity(rec) > 0.0,
+            None => sampler.next_1d() < self.opacity(rec),
+        }
+    }
+
+    /// Mean opacity over uniformly distributed points of the wrapped surface.
+    fn coverage(&self) -> f64 {
+        *self.coverage.get_or_init(|| {
+            let mut rec = HitRecord::default();
+            let mut total = 0.0;
+            for _ in 0..COVERAGE_SAMPLES {
+                if self
+                    .hittable
+                    .random_surface_point(0.0, &mut rec, &mut IndependentSampler)
+                {
+                    total += self.opacity(&rec);
+                }
+            }
+            total / COVERAGE_SAMPLES as f64
+        })
+    }
+}
+
+impl Hittable for AlphaMask {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let mut temp_rec = HitRecord::default();
+        let mut t_min = t_min;
+
+        for _ in 0..MAX_SKIPPED {
+            if !self.hittable.hit(r, t_min, t_max, &mut temp_rec, sampler) {
+                return false;
+            }
+
+            if self.is_opaque(&temp_rec, sampler) {
+                *rec = temp_rec;
+                return true;
+            }
+
+            // Step just past the transparent hit and look for the next one.
+            t_min = temp_rec.t + 1e-9 * temp_rec.t.abs().max(1.0);
+        }
+
+        false
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        self.hittable.bounding_box(time0, time1, output_box)
+    }
+
+    /// Area of the opaque part, divided by the chance that `random_surface_point` finds
+    /// a point on it at all. Lights sampled through the mask then lose no power to the
+    /// points it gives up on, however sparse the mask.
+    fn area(&self) -> f64 {
+        let area = self.hittable.area();
+        let coverage = self.coverage();
+        if area <= 0.0 || coverage <= 0.0 {
+            return 0.0;
+        }
+
+        let found = 1.0 - (1.0 - coverage).powi(MAX_SKIPPED as i32);
+        area * coverage / found
+    }
+
+    /// Rejects points by their opacity, so that the points returned are uniformly
+    /// distributed over the opaque part of the surface.
+    fn random_surface_point(
+        &self,
+        time: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        for _ in 0..MAX_SKIPPED {
+            if !self.hittable.random_surface_point(time, rec, sampler) {
+                return false;
+            }
+            if self.is_opaque(rec, sampler) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        material::lambertian::Lambertian,
+        model::{vec3::Vec3, xy_rect::XyRect},
+        texture::{checker::CheckerTexture, solid_color::SolidColor},
+        util::rtweekend::PI,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_only_opaque_parts_are_sampled() {
+        // At this depth the checker alternates with x and y, covering half the square.
+        let k = PI / 20.0;
+        let side = PI / 5.0;
+        let square = Arc::new(XyRect::new(
+            0.0,
+            side,
+            0.0,
+            side,
+            k,
+            Arc::new(Lambertian::new(&Vec3::new(1.0, 1.0, 1.0))),
+        ));
+        let checker = Arc::new(CheckerTexture::new_with_color(
+            &Vec3::new(1.0, 1.0, 1.0),
+            &Vec3::new(0.0, 0.0, 0.0),
+        ));
+
+        let masked = AlphaMask::new(square.clone(), checker.clone());
+        assert!((masked.area() / square.area() - 0.5).abs() < 0.05);
+        let mut rec = HitRecord::default();
+        for _ in 0..100 {
+            assert!(masked.random_surface_point(0.0, &mut rec, &mut IndependentSampler));
+            assert_eq!(checker.value(rec.u, rec.v, &rec.p).x(), 1.0);
+        }
+
+        // Partly transparent masks scale the area by their opacity.
+        let faded = AlphaMask::new_stochastic(
+            square.clone(),
+            Arc::new(SolidColor::new_with_values(0.25, 0.25, 0.25)),
+        );
+        assert!((faded.area() / square.area() - 0.25).abs() < 0.05);
+
+        // Sparse masks often give up on finding a point, which the area makes up for.
+        let sparse = AlphaMask::new_stochastic(
+            square.clone(),
+            Arc::new(SolidColor::new_with_values(0.03, 0.03, 0.03)),
+        );
+        let trials = 20_000;
+        let found = (0..trials)
+            .filter(|_| sparse.random_surface_point(0.0, &mut rec, &mut IndependentSampler))
+            .count();
+        let emitting = sparse.area() * found as f64 / trials as f64;
+        assert!((emitting / square.area() - 0.03).abs() < 0.003);
+
+        let hidden = AlphaMask::new(square, Arc::new(SolidColor::new_with_values(0.0, 0.0, 0.0)));
+        assert_eq!(hidden.area(), 0.0);
+        assert!(!hidden.random_surface_point(0.0, &mut rec, &mut IndependentSampler));
+    }
+}
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use Vec3 as Point3;
 
-use crate::material::material::Material;
+use crate::{material::material::Material, sampler::sampler::SamplerState};
 
 use super::{
     aabb::Aabb,
@@ -91,12 +91,26 @@ impl Hittable for Box {
         t_min: f64,
         t_max: f64,
         rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
     ) -> bool {
-        self.sides.hit(r, t_min, t_max, rec)
+        self.sides.hit(r, t_min, t_max, rec, sampler)
     }
 
     fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut super::aabb::Aabb) -> bool {
         *output_box = Aabb::new(self.box_min, self.box_max);
         return true;
     }
+
+    fn area(&self) -> f64 {
+        self.sides.area()
+    }
+
+    fn random_surface_point(
+        &self,
+        time: f64,
+        rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        self.sides.random_surface_point(time, rec, sampler)
+    }
 }
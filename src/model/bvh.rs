@@ -4,7 +4,7 @@ use std::{
     sync::Arc,
 };
 
-use crate::util::rtweekend::random_int;
+use crate::{sampler::sampler::SamplerState, util::rtweekend::random_int};
 
 use super::{
     aabb::Aabb,
@@ -16,6 +16,8 @@ pub struct BvhNode {
     pub left: Arc<dyn Hittable + Sync + Send>,
     pub right: Arc<dyn Hittable + Sync + Send>,
     pub bounding_box: Aabb,
+    /// Cached `contains_media` of the children, so paths skip subtrees without media.
+    contains_media: bool,
 }
 
 impl BvhNode {
@@ -73,10 +75,12 @@ impl BvhNode {
         }
 
         let bounding_box = box_left.surrounding_box(&box_right);
+        let contains_media = left.contains_media() || right.contains_media();
         Self {
             left,
             right,
             bounding_box,
+            contains_media,
         }
     }
 }
@@ -88,13 +92,17 @@ impl Hittable for BvhNode {
         t_min: f64,
         t_max: f64,
         rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
     ) -> bool {
-        if self.bounding_box.hit(r, t_min, t_max) {
+        if !self.bounding_box.hit(r, t_min, t_max) {
             return false;
         }
 
-        let hit_left = self.left.hit(r, t_min, t_max, rec);
-        let hit_right = self.right.hit(r, t_min, t_max, rec);
+        // The right child only has to beat the closest hit found on the left.
+        let hit_left = self.left.hit(r, t_min, t_max, rec, sampler);
+        let hit_right =
+            self.right
+                .hit(r, t_min, if hit_left { rec.t } else { t_max }, rec, sampler);
 
         hit_left || hit_right
     }
@@ -103,6 +111,41 @@ impl Hittable for BvhNode {
         *output_box = self.bounding_box.clone();
         return true;
     }
+
+    fn transmittance(
+        &self,
+        r: &super::ray::Ray,
+        t_min: f64,
+        t_max: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        if !self.bounding_box.hit(r, t_min, t_max) {
+            return Vec3::new(1.0, 1.0, 1.0);
+        }
+
+        self.left.transmittance(r, t_min, t_max, sampler)
+            * self.right.transmittance(r, t_min, t_max, sampler)
+    }
+
+    fn contains_media(&self) -> bool {
+        self.contains_media
+    }
+
+    fn medium_weight(
+        &self,
+        r: &super::ray::Ray,
+        t_min: f64,
+        t_max: f64,
+        event: Option<&super::hit::HitRecord>,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        if !self.contains_media || !self.bounding_box.hit(r, t_min, t_max) {
+            return Vec3::new(1.0, 1.0, 1.0);
+        }
+
+        self.left.medium_weight(r, t_min, t_max, event, sampler)
+            * self.right.medium_weight(r, t_min, t_max, event, sampler)
+    }
 }
 
 fn box_compare(
@@ -1,72 +0,0 @@
-use crate::util::rtweekend::{degrees_to_radians, random_double, random_double_by_range};
-
-use super::{ray::Ray, vec3::Vec3};
-
-use Vec3 as Point3;
-
-pub struct Camera {
-    origin: Point3,
-    lower_left_corner: Point3,
-    horizontal: Vec3,
-    vertical: Vec3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
-    lens_radius: f64,
-    time0: f64,
-    time1: f64,
-}
-
-impl Camera {
-    pub fn new(
-        lookfrom: &Point3,
-        lookat: &Point3,
-        vup: &Vec3,
-        vfov: f64, // vertical field-of-view in degrees
-        aspect_ratio: f64,
-        aperture: f64,
-        focus_dist: f64,
-        _time0: f64,
-        _time1: f64,
-    ) -> Self {
-        let theta = degrees_to_radians(vfov);
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h;
-        let viewport_width = aspect_ratio * viewport_height;
-
-        let w = (lookfrom - lookat).unit_vector();
-        let u = (vup.cross(&w)).unit_vector();
-        let v = w.cross(&u);
-
-        let origin = lookfrom.clone();
-        let horizontal = focus_dist * viewport_width * u;
-        let vertical = focus_dist * viewport_height * v;
-        let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w;
-        let lens_radius = aperture / 2.0;
-        Self {
-            origin,
-            horizontal,
-            vertical,
-            lower_left_corner,
-            u,
-            v,
-            w,
-            lens_radius,
-            time0: _time0,
-            time1: _time1,
-        }
-    }
-
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_unit_disk();
-        let offset = self.u * rd.x() + self.v * rd.y();
-
-        Ray::new(
-            &(self.origin + offset),
-            &(self.lower_left_corner + s * self.horizontal + t * self.vertical
-                - self.origin
-                - offset),
-            random_double_by_range(self.time0, self.time1),
-        )
-    }
-}
@@ -4,10 +4,18 @@ use super::vec3::Vec3;
 
 pub trait Color {
     fn as_color_repr(&self, samples_per_pixel: usize) -> String;
+
+    /// Averaged, gamma corrected and quantised to 8 bits per channel.
+    fn as_rgb8(&self, samples_per_pixel: usize) -> [u8; 3];
 }
 
 impl Color for Vec3 {
     fn as_color_repr(&self, samples_per_pixel: usize) -> String {
+        let [r, g, b] = self.as_rgb8(samples_per_pixel);
+        format!("{} {} {}\n", r, g, b)
+    }
+
+    fn as_rgb8(&self, samples_per_pixel: usize) -> [u8; 3] {
         let mut r = self.x();
         let mut g = self.y();
         let mut b = self.z();
@@ -18,11 +26,10 @@ impl Color for Vec3 {
         g = (scale * g).sqrt();
         b = (scale * b).sqrt();
 
-        format!(
-            "{} {} {}\n",
-            (256.0 * clamp(r, 0.0, 0.999)) as i32,
-            (256.0 * clamp(g, 0.0, 0.999)) as i32,
-            (256.0 * clamp(b, 0.0, 0.999)) as i32,
-        )
+        [
+            (256.0 * clamp(r, 0.0, 0.999)) as u8,
+            (256.0 * clamp(g, 0.0, 0.999)) as u8,
+            (256.0 * clamp(b, 0.0, 0.999)) as u8,
+        ]
     }
 }
@@ -1,20 +1,33 @@
 use std::sync::Arc;
 
 use crate::{
-    material::{isotropic::Isotropic, material::Material},
-    texture::texture::Texture,
-    util::rtweekend::{random_double, INFINITY},
+    material::{
+        phase_function::{IsotropicPhase, PhaseFunction},
+        volume_scattering::VolumeScattering,
+    },
+    sampler::sampler::SamplerState,
+    texture::{solid_color::SolidColor, texture::Texture},
+    util::rtweekend::INFINITY,
 };
 
 use super::{
+    aabb::Aabb,
     hit::{HitRecord, Hittable},
+    ray::Ray,
     vec3::Vec3,
 };
 
+/// Participating medium of uniform density filling `boundary`, isotropic unless
+/// given another phase function with `with_phase_function`. The extinction
+/// coefficient may differ per channel, e.g. for coloured liquids that absorb some
+/// wavelengths much more strongly than others.
 pub struct ConstantMedium {
     pub boundary: Arc<dyn Hittable + Sync + Send>,
-    pub phase_function: Arc<dyn Material + Sync + Send>,
-    pub neg_inv_density: f64,
+    pub scattering: Arc<VolumeScattering>,
+    /// Extinction coefficient per channel, the sum of absorption and scattering.
+    pub sigma_t: Vec3,
+    /// Set for media that neither scatter nor glow, which only attenuate paths.
+    absorbs_only: bool,
 }
 
 impl ConstantMedium {
@@ -25,87 +38,280 @@ impl ConstantMedium {
     ) -> ConstantMedium {
         ConstantMedium {
             boundary: b,
-            neg_inv_density: -1.0 / d,
-            phase_function: Arc::new(Isotropic::new(a)),
+            sigma_t: Vec3::new(d, d, d),
+            absorbs_only: false,
+            scattering: Arc::new(VolumeScattering::new(a, Arc::new(IsotropicPhase))),
         }
     }
 
     pub fn new(b: Arc<dyn Hittable + Sync + Send>, d: f64, c: Vec3) -> Self {
+        Self::new_with_texture(b, d, Arc::new(SolidColor::new(&c)))
+    }
+
+    /// Medium with separate absorption and scattering coefficients per channel, in
+    /// inverse scene units.
+    pub fn new_with_coefficients(
+        b: Arc<dyn Hittable + Sync + Send>,
+        sigma_a: &Vec3,
+        sigma_s: &Vec3,
+    ) -> Self {
+        let sigma_t = sigma_a + sigma_s;
+        let mut albedo = Vec3::default();
+        for c in 0..3 {
+            if sigma_t[c] > 0.0 {
+                albedo[c] = sigma_s[c] / sigma_t[c];
+            }
+        }
+
         Self {
             boundary: b,
-            neg_inv_density: -1.0 / d,
-            phase_function: Arc::new(Isotropic::new_with_color(c)),
+            sigma_t,
+            absorbs_only: sigma_s.near_zero(),
+            scattering: Arc::new(VolumeScattering::new(
+                Arc::new(SolidColor::new(&albedo)),
+                Arc::new(IsotropicPhase),
+            )),
         }
     }
+
+    pub fn with_phase_function(
+        mut self,
+        phase_function: Arc<dyn PhaseFunction + Sync + Send>,
+    ) -> Self {
+        self.scattering = Arc::new(
+            VolumeScattering::new(self.scattering.albedo.clone(), phase_function)
+                .with_emission(&self.scattering.emission),
+        );
+        self
+    }
+
+    /// Makes the medium glow, emitting `radiance` in proportion to its absorption so a
+    /// thick enough volume looks like a surface of that radiance.
+    pub fn with_emission(mut self, radiance: &Vec3) -> Self {
+        self.scattering = Arc::new(
+            VolumeScattering::new(
+                self.scattering.albedo.clone(),
+                self.scattering.phase_function.clone(),
+            )
+            .with_emission(radiance),
+        );
+        self
+    }
+
+    /// Whether `hit` samples collisions at all. Purely absorbing media are crossed in
+    /// one go and attenuate the path in `medium_weight` instead.
+    fn samples_collisions(&self) -> bool {
+        !self.absorbs_only || !self.scattering.emission.near_zero()
+    }
+
+    fn is_chromatic(&self) -> bool {
+        self.sigma_t.x() != self.sigma_t.y() || self.sigma_t.x() != self.sigma_t.z()
+    }
+
+    fn channel_transmittance(&self, distance: f64) -> Vec3 {
+        Vec3::new(
+            (-self.sigma_t.x() * distance).exp(),
+            (-self.sigma_t.y() * distance).exp(),
+            (-self.sigma_t.z() * distance).exp(),
+        )
+    }
 }
 
 impl Hittable for ConstantMedium {
     fn hit(
         &self,
-        r: &super::ray::Ray,
+        r: &Ray,
         t_min: f64,
         t_max: f64,
-        rec: &mut super::hit::HitRecord,
+        rec: &mut HitRecord,
+        sampler: &mut dyn SamplerState,
     ) -> bool {
-        let enable_debugging = false;
-        let debugging = enable_debugging && random_double() < 0.00001;
-
-        let mut rec1 = HitRecord::default();
-        let mut rec2: HitRecord = HitRecord::default();
-
-        if !self.boundary.hit(&r, -INFINITY, INFINITY, &mut rec1) {
+        let (t_enter, t_exit) =
+            match boundary_interval(self.boundary.as_ref(), r, t_min, t_max, sampler) {
+                Some(interval) => interval,
+                None => return false,
+            };
+        if !self.samples_collisions() {
             return false;
         }
 
-        if !self.boundary.hit(&r, rec1.t + 0.0001, INFINITY, &mut rec2) {
+        // Free path lengths are exponentially distributed with mean 1 / sigma_t. With
+        // different coefficients per channel one is picked at random to sample with,
+        // and `medium_weight` accounts for all of them.
+        let sigma_t = self.sigma_t[((3.0 * sampler.next_1d()) as i32).min(2)];
+        if sigma_t <= 0.0 {
             return false;
         }
 
-        if debugging {
-            eprintln!("\nt_min={}, t_max={}", rec1.t, rec2.t);
-        }
+        let ray_length = r.dir().length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let hit_distance = -(1.0 - sampler.next_1d()).ln() / sigma_t;
 
-        if rec1.t < t_min {
-            rec1.t = t_min;
+        if hit_distance > distance_inside_boundary {
+            return false;
         }
 
-        if rec2.t > t_max {
-            rec2.t = t_max;
+        rec.t = t_enter + hit_distance / ray_length;
+        rec.p = r.at(rec.t);
+        rec.normal = Vec3::new(1.0, 0.0, 0.0);
+        rec.front_face = true;
+        rec.material = self.scattering.clone();
+
+        return true;
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        self.boundary.bounding_box(time0, time1, output_box)
+    }
+
+    fn transmittance(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        let distance = match boundary_interval(self.boundary.as_ref(), r, t_min, t_max, sampler) {
+            Some((t_enter, t_exit)) => (t_exit - t_enter) * r.dir().length(),
+            None => 0.0,
+        };
+
+        self.channel_transmittance(distance)
+    }
+
+    fn contains_media(&self) -> bool {
+        self.is_chromatic() || !self.samples_collisions()
+    }
+
+    fn medium_weight(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        event: Option<&HitRecord>,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        if !self.contains_media() {
+            return Vec3::new(1.0, 1.0, 1.0);
         }
+        let distance = match boundary_interval(self.boundary.as_ref(), r, t_min, t_max, sampler) {
+            Some((t_enter, t_exit)) => (t_exit - t_enter) * r.dir().length(),
+            None => return Vec3::new(1.0, 1.0, 1.0),
+        };
 
-        if rec1.t >= rec2.t {
-            return false;
+        // Paths are sampled with the average of the per-channel densities, which is
+        // the density of a collision at `distance` if this medium produced the event,
+        // and the probability of passing through otherwise.
+        let transmittance = self.channel_transmittance(distance);
+        if !self.samples_collisions() {
+            return transmittance;
         }
+        let scattered_here = event.is_some_and(|rec| {
+            Arc::as_ptr(&rec.material) as *const u8 == Arc::as_ptr(&self.scattering) as *const u8
+        });
 
-        if rec1.t < 0.0 {
-            rec1.t = 0.0;
+        if scattered_here {
+            let pdf = self.sigma_t.dot(&transmittance) / 3.0;
+            transmittance * self.sigma_t / pdf
+        } else {
+            let probability = (transmittance.x() + transmittance.y() + transmittance.z()) / 3.0;
+            transmittance / probability
         }
+    }
+}
 
-        let ray_length = r.dir().length();
-        let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-        let hit_distance = self.neg_inv_density * random_double().log10();
+/// Part of `[t_min, t_max]` along `r` that lies inside the closed `boundary`, assuming
+/// the ray crosses it at most once in each direction.
+pub fn boundary_interval(
+    boundary: &(dyn Hittable + Sync + Send),
+    r: &Ray,
+    t_min: f64,
+    t_max: f64,
+    sampler: &mut dyn SamplerState,
+) -> Option<(f64, f64)> {
+    let mut rec1 = HitRecord::default();
+    let mut rec2 = HitRecord::default();
 
-        if hit_distance > distance_inside_boundary {
-            return false;
-        }
+    if !boundary.hit(r, -INFINITY, INFINITY, &mut rec1, sampler) {
+        return None;
+    }
 
-        rec.t = rec1.t + hit_distance / ray_length;
-        rec.p = r.at(rec.t);
+    if !boundary.hit(r, rec1.t + 0.0001, INFINITY, &mut rec2, sampler) {
+        return None;
+    }
+
+    let t_enter = rec1.t.max(t_min).max(0.0);
+    let t_exit = rec2.t.min(t_max);
+    if t_enter >= t_exit {
+        return None;
+    }
+
+    Some((t_enter, t_exit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::dielectric::Dielectric, model::sphere::Sphere,
+        sampler::independent::IndependentSampler,
+    };
 
-        if debugging {
-            eprintln!("hit_distance = {}", hit_distance);
-            eprintln!("rec.t = {}", rec.t);
-            eprintln!("rec.p = {}", rec.p);
+    /// Hands out the same number every time.
+    struct Fixed(f64);
+
+    impl SamplerState for Fixed {
+        fn next_1d(&mut self) -> f64 {
+            self.0
         }
+    }
 
-        rec.normal = Vec3::new(1.0, 0.0, 0.0);
-        rec.front_face = true;
-        rec.material = self.phase_function.clone();
+    #[test]
+    fn test_free_paths_follow_the_sampler() {
+        let boundary = Arc::new(Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            10.0,
+            Arc::new(Dielectric::new(1.0)),
+        ));
+        let medium = ConstantMedium::new(boundary, 0.5, Vec3::new(1.0, 1.0, 1.0));
+        let r = Ray::new(&Vec3::new(0.0, 0.0, -20.0), &Vec3::new(0.0, 0.0, 1.0), 0.0);
 
-        return true;
+        // Half the paths make it further than ln 2 / sigma into the medium.
+        let mut rec = HitRecord::default();
+        assert!(medium.hit(&r, 0.001, INFINITY, &mut rec, &mut Fixed(0.5)));
+        assert!((rec.t - (10.0 + 2.0_f64.ln() / 0.5)).abs() < 1e-6);
+        assert!(!medium.hit(&r, 0.001, INFINITY, &mut rec, &mut Fixed(0.9999999)));
     }
 
-    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut super::aabb::Aabb) -> bool {
-        self.boundary.bounding_box(time0, time1, output_box)
+    #[test]
+    fn test_chromatic_medium_weights_match_transmittance() {
+        let boundary = Arc::new(Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Dielectric::new(1.0)),
+        ));
+        let medium = ConstantMedium::new_with_coefficients(
+            boundary,
+            &Vec3::new(0.1, 0.5, 1.5),
+            &Vec3::new(0.2, 0.2, 0.2),
+        );
+        let r = Ray::new(&Vec3::new(0.0, 0.0, -5.0), &Vec3::new(0.0, 0.0, 2.0), 0.0);
+
+        // Paths that make it through, reweighted, carry the exact transmittance.
+        let samples = 200_000;
+        let mut passed = Vec3::default();
+        for _ in 0..samples {
+            let mut rec = HitRecord::default();
+            if !medium.hit(&r, 0.001, INFINITY, &mut rec, &mut IndependentSampler) {
+                passed += medium.medium_weight(&r, 0.001, INFINITY, None, &mut IndependentSampler);
+            }
+        }
+        passed /= samples as f64;
+
+        let expected = medium.transmittance(&r, 0.001, INFINITY, &mut IndependentSampler);
+        for c in 0..3 {
+            assert!((expected[c] - (-2.0 * medium.sigma_t[c]).exp()).abs() < 1e-9);
+            assert!((passed[c] - expected[c]).abs() < 0.01);
+        }
     }
 }
@@ -0,0 +1,196 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+};
+
+use crate::texture::perlin::Perlin;
+
+use super::vec3::Vec3;
+
+use Vec3 as Point3;
+
+/// Spatially varying density of a participating medium.
+pub trait Density {
+    fn density(&self, p: &Point3) -> f64;
+
+    /// Upper bound of `density` everywhere, used as the majorant when tracking.
+    fn max_density(&self) -> f64;
+}
+
+/// Voxel grid of densities stretched over the box from `minimum` to `maximum` and
+/// interpolated trilinearly, zero outside the box.
+pub struct DensityGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    /// Voxel values with x varying fastest, then y, then z.
+    pub data: Vec<f64>,
+    pub minimum: Point3,
+    pub maximum: Point3,
+    max_value: f64,
+}
+
+impl DensityGrid {
+    pub fn new(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        data: Vec<f64>,
+        minimum: &Point3,
+        maximum: &Point3,
+    ) -> Self {
+        assert_eq!(data.len(), nx * ny * nz);
+        let max_value = data.iter().cloned().fold(0.0, f64::max);
+
+        Self {
+            nx,
+            ny,
+            nz,
+            data,
+            minimum: *minimum,
+            maximum: *maximum,
+            max_value,
+        }
+    }
+
+    /// Loads a grid in the raw density format: an ASCII header line
+    /// `DENSITY <nx> <ny> <nz>` followed by `nx * ny * nz` little-endian 32-bit floats.
+    pub fn load(filename: &str, minimum: &Point3, maximum: &Point3) -> io::Result<DensityGrid> {
+        DensityGrid::parse(&fs::read(filename)?, minimum, maximum)
+    }
+
+    pub fn parse(bytes: &[u8], minimum: &Point3, maximum: &Point3) -> io::Result<DensityGrid> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+        let end = bytes
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or_else(|| invalid("missing density grid header"))?;
+        let header = String::from_utf8_lossy(&bytes[..end]);
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        if fields.len() != 4 || fields[0] != "DENSITY" {
+            return Err(invalid("bad density grid header"));
+        }
+
+        let mut size = [0; 3];
+        for (n, field) in size.iter_mut().zip(&fields[1..]) {
+            *n = field
+                .parse::<usize>()
+                .map_err(|_| invalid("bad density grid resolution"))?;
+        }
+        let [nx, ny, nz] = size;
+
+        let raster = &bytes[end + 1..];
+        if nx * ny * nz == 0 || raster.len() != 4 * nx * ny * nz {
+            return Err(invalid("density grid size does not match its header"));
+        }
+
+        let data = raster
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]).max(0.0) as f64)
+            .collect();
+
+        Ok(DensityGrid::new(nx, ny, nz, data, minimum, maximum))
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.data[(z * self.ny + y) * self.nx + x]
+    }
+}
+
+impl Density for DensityGrid {
+    fn density(&self, p: &Point3) -> f64 {
+        let size = [self.nx, self.ny, self.nz];
+        let mut index = [0; 3];
+        let mut weight = [0.0; 3];
+
+        // Voxel values sit at voxel centres; clamp to the outermost ones at the edges.
+        for a in 0..3 {
+            let extent = self.maximum[a as i32] - self.minimum[a as i32];
+            let t = (p[a as i32] - self.minimum[a as i32]) / extent;
+            if !(0.0..=1.0).contains(&t) {
+                return 0.0;
+            }
+
+            let x = (t * size[a] as f64 - 0.5).clamp(0.0, (size[a] - 1) as f64);
+            index[a] = (x as usize).min(size[a].saturating_sub(2));
+            weight[a] = if size[a] > 1 {
+                x - index[a] as f64
+            } else {
+                0.0
+            };
+        }
+
+        let mut result = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut w = 1.0;
+            let mut voxel = [0; 3];
+            for a in 0..3 {
+                w *= if offset[a] == 1 {
+                    weight[a]
+                } else {
+                    1.0 - weight[a]
+                };
+                voxel[a] = (index[a] + offset[a]).min(size[a] - 1);
+            }
+            if w > 0.0 {
+                result += w * self.voxel(voxel[0], voxel[1], voxel[2]);
+            }
+        }
+
+        result
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_value
+    }
+}
+
+/// Procedural density from Perlin turbulence, for wispy smoke and clouds, ranging
+/// from zero to one.
+pub struct NoiseDensity {
+    pub noise: Perlin,
+    pub scale: f64,
+}
+
+impl NoiseDensity {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+        }
+    }
+}
+
+impl Density for NoiseDensity {
+    fn density(&self, p: &Point3) -> f64 {
+        (2.0 * self.noise.turb(&(self.scale * p), 5) - 0.2).clamp(0.0, 1.0)
+    }
+
+    fn max_density(&self) -> f64 {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_density_grid_interpolates_between_voxel_centres() {
+        let mut bytes = b"DENSITY 2 1 1\n".to_vec();
+        bytes.extend_from_slice(&0.0f32.to_le_bytes());
+        bytes.extend_from_slice(&4.0f32.to_le_bytes());
+        let minimum = Point3::new(0.0, 0.0, 0.0);
+        let maximum = Point3::new(2.0, 1.0, 1.0);
+
+        let grid = DensityGrid::parse(&bytes, &minimum, &maximum).unwrap();
+        assert_eq!(grid.max_density(), 4.0);
+        assert_eq!(grid.density(&Point3::new(0.25, 0.5, 0.5)), 0.0);
+        assert!((grid.density(&Point3::new(1.0, 0.5, 0.5)) - 2.0).abs() < 1e-12);
+        assert_eq!(grid.density(&Point3::new(1.9, 0.5, 0.5)), 4.0);
+        assert_eq!(grid.density(&Point3::new(3.0, 0.5, 0.5)), 0.0);
+        assert!(DensityGrid::parse(b"DENSITY 2 2 2\n", &minimum, &maximum).is_err());
+    }
+}
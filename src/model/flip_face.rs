@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use crate::sampler::sampler::SamplerState;
+
+use super::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// Swaps the front and back faces of a hittable, e.g. to point a one-sided
+/// `XzRect` light downwards.
+pub struct FlipFace {
+    hittable: Arc<dyn Hittable + Sync + Send>,
+}
+
+impl FlipFace {
+    pub fn new(p: Arc<dyn Hittable + Sync + Send>) -> Self {
+        Self { hittable: p }
+    }
+}
+
+impl Hittable for FlipFace {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        if !self.hittable.hit(r, t_min, t_max, rec, sampler) {
+            return false;
+        }
+
+        rec.front_face = !rec.front_face;
+        true
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        self.hittable.bounding_box(time0, time1, output_box)
+    }
+
+    fn area(&self) -> f64 {
+        self.hittable.area()
+    }
+
+    fn random_surface_point(
+        &self,
+        time: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        if !self.hittable.random_surface_point(time, rec, sampler) {
+            return false;
+        }
+
+        rec.normal = -rec.normal;
+        true
+    }
+
+    fn transmittance(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        self.hittable.transmittance(r, t_min, t_max, sampler)
+    }
+
+    fn contains_media(&self) -> bool {
+        self.hittable.contains_media()
+    }
+
+    fn medium_weight(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        event: Option<&HitRecord>,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        self.hittable.medium_weight(r, t_min, t_max, event, sampler)
+    }
+}
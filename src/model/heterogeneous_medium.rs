@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use crate::{
+    material::{phase_function::PhaseFunction, volume_scattering::VolumeScattering},
+    sampler::sampler::SamplerState,
+};
+
+use super::{
+    aabb::Aabb,
+    constant_medium::boundary_interval,
+    density::Density,
+    hit::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// Participating medium whose extinction coefficient, `scale` times the density field,
+/// varies through the volume enclosed by `boundary`. Collisions are found by delta
+/// tracking and transmittance along shadow rays is estimated by ratio tracking, both
+/// against the majorant `scale * density.max_density()`.
+pub struct HeterogeneousMedium {
+    pub boundary: Arc<dyn Hittable + Sync + Send>,
+    pub density: Arc<dyn Density + Sync + Send>,
+    pub scale: f64,
+    pub scattering: Arc<VolumeScattering>,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(
+        boundary: Arc<dyn Hittable + Sync + Send>,
+        density: Arc<dyn Density + Sync + Send>,
+        scale: f64,
+        c: Vec3,
+    ) -> Self {
+        Self {
+            boundary,
+            density,
+            scale,
+            scattering: Arc::new(VolumeScattering::new_with_color(c)),
+        }
+    }
+
+    pub fn with_phase_function(
+        mut self,
+        phase_function: Arc<dyn PhaseFunction + Sync + Send>,
+    ) -> Self {
+        self.scattering = Arc::new(VolumeScattering::new(
+            self.scattering.albedo.clone(),
+            phase_function,
+        ));
+        self
+    }
+
+    fn majorant(&self) -> f64 {
+        self.scale * self.density.max_density()
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return false;
+        }
+        let (t_enter, t_exit) =
+            match boundary_interval(self.boundary.as_ref(), r, t_min, t_max, sampler) {
+                Some(interval) => interval,
+                None => return false,
+            };
+
+        // Sample tentative collisions against the majorant and accept each one with the
+        // ratio of the real density to it; rejected ones are null collisions.
+        let ray_length = r.dir().length();
+        let mut t = t_enter;
+        loop {
+            t -= (1.0 - sampler.next_1d()).ln() / (majorant * ray_length);
+            if t >= t_exit {
+                return false;
+            }
+
+            let p = r.at(t);
+            if sampler.next_1d() * majorant < self.scale * self.density.density(&p) {
+                rec.t = t;
+                rec.p = p;
+                rec.normal = Vec3::new(1.0, 0.0, 0.0);
+                rec.front_face = true;
+                rec.material = self.scattering.clone();
+                return true;
+            }
+        }
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        self.boundary.bounding_box(time0, time1, output_box)
+    }
+
+    fn transmittance(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        let majorant = self.majorant();
+        let interval = boundary_interval(self.boundary.as_ref(), r, t_min, t_max, sampler);
+        let (t_enter, t_exit) = match interval {
+            Some(interval) if majorant > 0.0 => interval,
+            _ => return Vec3::new(1.0, 1.0, 1.0),
+        };
+
+        // Ratio tracking: weight by the null-collision probability at each tentative collision.
+        let ray_length = r.dir().length();
+        let mut transmittance = 1.0;
+        let mut t = t_enter;
+        loop {
+            t -= (1.0 - sampler.next_1d()).ln() / (majorant * ray_length);
+            if t >= t_exit {
+                break;
+            }
+
+            transmittance *= 1.0 - self.scale * self.density.density(&r.at(t)) / majorant;
+        }
+
+        Vec3::new(transmittance, transmittance, transmittance)
+    }
+}
@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
-use crate::material::{lambertian::Lambertian, material::Material};
+use crate::{
+    material::{lambertian::Lambertian, material::Material},
+    sampler::sampler::SamplerState,
+};
 
 use super::{aabb::Aabb, ray::Ray, vec3::Vec3};
 use Vec3 as Point3;
@@ -13,7 +16,22 @@ pub struct HitRecord {
     pub t: f64,
     pub u: f64,
     pub v: f64,
+    /// Partial derivatives of the surface point with respect to `u` and `v`, giving
+    /// the tangent frame used by anisotropic materials and normal mapping. They stay
+    /// zero for hittables without a parameterization.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    /// How fast `u` and `v` change from one pixel to the next, across and up the
+    /// image, for texture filtering. They stay zero where the ray carries no
+    /// differentials or the hittable no parameterization.
+    pub dudx: f64,
+    pub dudy: f64,
+    pub dvdx: f64,
+    pub dvdy: f64,
     pub front_face: bool,
+    /// Index of refraction on the other side of the surface from the hit object's
+    /// interior, which the integrator sets from the objects the path is inside.
+    pub exterior_ior: f64,
 }
 
 impl HitRecord {
@@ -25,6 +43,48 @@ impl HitRecord {
             -outward_normal
         };
     }
+
+    /// Sets the texture coordinate derivatives from the differentials of `r`, the ray
+    /// that hit, by meeting the offset rays with the tangent plane at the hit point.
+    pub fn set_differentials(&mut self, r: &Ray) {
+        (self.dudx, self.dudy, self.dvdx, self.dvdy) = (0.0, 0.0, 0.0, 0.0);
+        let Some(differential) = r.differential() else {
+            return;
+        };
+
+        let plane_point = |origin: &Point3, dir: &Vec3| {
+            let denominator = self.normal.dot(dir);
+            if denominator.abs() < 1e-12 {
+                return None;
+            }
+            let t = self.normal.dot(&(self.p - *origin)) / denominator;
+            Some(*origin + t * *dir)
+        };
+        let (Some(px), Some(py)) = (
+            plane_point(&differential.rx_origin, &differential.rx_dir),
+            plane_point(&differential.ry_origin, &differential.ry_dir),
+        ) else {
+            return;
+        };
+
+        // Least squares fit of dp = dpdu du + dpdv dv, for the offsets to both points.
+        let ata00 = self.dpdu.dot(&self.dpdu);
+        let ata01 = self.dpdu.dot(&self.dpdv);
+        let ata11 = self.dpdv.dot(&self.dpdv);
+        let determinant = ata00 * ata11 - ata01 * ata01;
+        if determinant.abs() < 1e-12 {
+            return;
+        }
+        let solve = |dp: Vec3| {
+            let (atb0, atb1) = (self.dpdu.dot(&dp), self.dpdv.dot(&dp));
+            (
+                (ata11 * atb0 - ata01 * atb1) / determinant,
+                (ata00 * atb1 - ata01 * atb0) / determinant,
+            )
+        };
+        (self.dudx, self.dvdx) = solve(px - self.p);
+        (self.dudy, self.dvdy) = solve(py - self.p);
+    }
 }
 
 impl Default for HitRecord {
@@ -37,13 +97,83 @@ impl Default for HitRecord {
             front_face: Default::default(),
             u: Default::default(),
             v: Default::default(),
+            dpdu: Default::default(),
+            dpdv: Default::default(),
+            dudx: 0.0,
+            dudy: 0.0,
+            dvdx: 0.0,
+            dvdy: 0.0,
+            exterior_ior: 1.0,
         }
     }
 }
 
 pub trait Hittable {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+    /// Closest intersection along `r` between `t_min` and `t_max`. Media and
+    /// stochastic masks decide where the ray stops with numbers from `sampler`.
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool;
     fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool;
+
+    /// Surface area, used to weight light sampling. Hittables that cannot be sampled report zero.
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    /// Fills `rec` with a uniformly distributed point on the surface, facing outward.
+    fn random_surface_point(
+        &self,
+        _time: f64,
+        _rec: &mut HitRecord,
+        _sampler: &mut dyn SamplerState,
+    ) -> bool {
+        false
+    }
+
+    /// Fraction of light carried along `r` between `t_min` and `t_max`, for shadow rays.
+    /// Surfaces block it entirely; participating media override this with an estimate
+    /// of how much gets through them.
+    fn transmittance(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        let mut rec = HitRecord::default();
+        if self.hit(r, t_min, t_max, &mut rec, sampler) {
+            Vec3::new(0.0, 0.0, 0.0)
+        } else {
+            Vec3::new(1.0, 1.0, 1.0)
+        }
+    }
+
+    /// Whether this hittable is or contains a participating medium whose `hit` needs the
+    /// path reweighted with `medium_weight`.
+    fn contains_media(&self) -> bool {
+        false
+    }
+
+    /// Weight a path picks up from media between `t_min` and `t_max` along `r`, where
+    /// the segment ends at `event` if the closest hit was found there, or escapes the
+    /// scene otherwise. Media whose sampled free paths do not follow the transmittance
+    /// of every channel exactly return the ratio of the two.
+    fn medium_weight(
+        &self,
+        _r: &Ray,
+        _t_min: f64,
+        _t_max: f64,
+        _event: Option<&HitRecord>,
+        _sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        Vec3::new(1.0, 1.0, 1.0)
+    }
 }
 
 pub struct HittableList {
@@ -67,13 +197,20 @@ impl HittableList {
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
         let mut temp_rec = HitRecord::default();
         let mut hit_anything = false;
         let mut closest_so_far = t_max;
 
         for object in self.objects.iter() {
-            if object.hit(r, t_min, closest_so_far, &mut temp_rec) {
+            if object.hit(r, t_min, closest_so_far, &mut temp_rec, sampler) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
                 *rec = temp_rec.clone();
@@ -104,4 +241,103 @@ impl Hittable for HittableList {
         }
         return true;
     }
+
+    fn area(&self) -> f64 {
+        self.objects.iter().map(|object| object.area()).sum()
+    }
+
+    fn random_surface_point(
+        &self,
+        time: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let total_area = self.area();
+        if total_area <= 0.0 {
+            return false;
+        }
+
+        // Pick an object proportionally to its area so the point stays uniform over the union.
+        let mut target = sampler.next_1d() * total_area;
+        for object in self.objects.iter() {
+            let area = object.area();
+            if area <= 0.0 {
+                continue;
+            }
+            if target < area {
+                return object.random_surface_point(time, rec, sampler);
+            }
+            target -= area;
+        }
+
+        false
+    }
+
+    fn transmittance(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        let mut result = Vec3::new(1.0, 1.0, 1.0);
+        for object in self.objects.iter() {
+            result *= object.transmittance(r, t_min, t_max, sampler);
+            if result.near_zero() {
+                break;
+            }
+        }
+
+        result
+    }
+
+    fn contains_media(&self) -> bool {
+        self.objects.iter().any(|object| object.contains_media())
+    }
+
+    fn medium_weight(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        event: Option<&HitRecord>,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        let mut result = Vec3::new(1.0, 1.0, 1.0);
+        for object in self.objects.iter() {
+            result *= object.medium_weight(r, t_min, t_max, event, sampler);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_differentials_follow_the_footprint() {
+        // Looking straight down at a floor spanning two units per unit of u and v,
+        // with the neighbouring pixels a tenth of a unit over in x and z.
+        let mut rec = HitRecord {
+            p: Point3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            dpdu: Vec3::new(2.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, 2.0),
+            ..Default::default()
+        };
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let r = Ray::new(&Point3::new(0.0, 1.0, 0.0), &down, 0.0).with_differential(
+            &Ray::new(&Point3::new(0.1, 1.0, 0.0), &down, 0.0),
+            &Ray::new(&Point3::new(0.0, 1.0, 0.1), &down, 0.0),
+        );
+
+        rec.set_differentials(&r);
+        assert!((rec.dudx - 0.05).abs() < 1e-12 && rec.dvdx.abs() < 1e-12);
+        assert!((rec.dvdy - 0.05).abs() < 1e-12 && rec.dudy.abs() < 1e-12);
+
+        rec.set_differentials(&Ray::new(&Point3::new(0.0, 1.0, 0.0), &down, 0.0));
+        assert_eq!(rec.dudx, 0.0);
+    }
 }
@@ -1,11 +1,15 @@
 pub mod aabb;
+pub mod alpha_mask;
 pub mod r#box;
 pub mod bvh;
-pub mod camera;
 pub mod color;
 pub mod constant_medium;
+pub mod density;
+pub mod flip_face;
+pub mod heterogeneous_medium;
 pub mod hit;
 pub mod moving_sphere;
+pub mod onb;
 pub mod ray;
 pub mod rotate;
 pub mod sphere;
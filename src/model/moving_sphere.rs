@@ -1,8 +1,8 @@
 use std::sync::Arc;
 
-use crate::material::material::Material;
+use crate::{material::material::Material, sampler::sampler::SamplerState, util::rtweekend::PI};
 
-use super::{aabb::Aabb, hit::Hittable, vec3::Vec3};
+use super::{aabb::Aabb, hit::Hittable, sphere::Sphere, vec3::Vec3};
 
 use Vec3 as Point3;
 
@@ -47,6 +47,7 @@ impl Hittable for MovingSphere {
         t_min: f64,
         t_max: f64,
         rec: &mut super::hit::HitRecord,
+        _sampler: &mut dyn SamplerState,
     ) -> bool {
         let oc = r.origin() - self.center(r.time());
         let a = r.dir().length_squared();
@@ -91,4 +92,28 @@ impl Hittable for MovingSphere {
         *output_box = box0.surrounding_box(&box1);
         return true;
     }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn random_surface_point(
+        &self,
+        time: f64,
+        rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let outward_normal = Vec3::sample_unit_vector(sampler.next_2d());
+        let (u, v) = Sphere::get_sphere_uv(&outward_normal);
+
+        rec.p = self.center(time) + self.radius * outward_normal;
+        rec.normal = outward_normal;
+        rec.front_face = true;
+        rec.t = 0.0;
+        rec.u = u;
+        rec.v = v;
+        (rec.dpdu, rec.dpdv) = Sphere::get_sphere_dpduv(&outward_normal, self.radius);
+        rec.material = self.material.clone();
+        true
+    }
 }
@@ -0,0 +1,48 @@
+use super::vec3::Vec3;
+
+/// Orthonormal basis used to move directions between world space and a local
+/// frame whose `w` axis is aligned with a surface normal.
+#[derive(Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn build_from_w(n: &Vec3) -> Self {
+        let w = n.unit_vector();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).unit_vector();
+        let u = w.cross(&v);
+
+        Self { u, v, w }
+    }
+
+    /// Basis with `w` along `n` and `u` along the part of `tangent` orthogonal to it,
+    /// falling back to an arbitrary `u` when the tangent is degenerate.
+    pub fn build_from_w_and_tangent(n: &Vec3, tangent: &Vec3) -> Self {
+        let w = n.unit_vector();
+        let t = tangent - w * w.dot(tangent);
+        if t.near_zero() {
+            return Onb::build_from_w(n);
+        }
+
+        let u = t.unit_vector();
+        let v = w.cross(&u);
+
+        Self { u, v, w }
+    }
+
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+
+    pub fn to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(a.dot(&self.u), a.dot(&self.v), a.dot(&self.w))
+    }
+}
@@ -5,6 +5,17 @@ pub struct Ray {
     origin: Point3,
     dir: Vec3,
     tm: f64,
+    differential: Option<RayDifferential>,
+}
+
+/// Rays through the neighbouring pixels to the right and above, which tell how large
+/// the pixel of a camera ray looks wherever it lands.
+#[derive(Clone, Copy)]
+pub struct RayDifferential {
+    pub rx_origin: Point3,
+    pub rx_dir: Vec3,
+    pub ry_origin: Point3,
+    pub ry_dir: Vec3,
 }
 
 impl Ray {
@@ -13,8 +24,20 @@ impl Ray {
             origin: origin.clone(),
             dir: dir.clone(),
             tm: time,
+            differential: None,
         }
     }
+
+    pub fn with_differential(mut self, rx: &Ray, ry: &Ray) -> Self {
+        self.differential = Some(RayDifferential {
+            rx_origin: rx.origin,
+            rx_dir: rx.dir,
+            ry_origin: ry.origin,
+            ry_dir: ry.dir,
+        });
+        self
+    }
+
     pub fn origin(&self) -> &Point3 {
         &self.origin
     }
@@ -30,4 +53,8 @@ impl Ray {
     pub fn time(&self) -> f64 {
         self.tm
     }
+
+    pub fn differential(&self) -> Option<&RayDifferential> {
+        self.differential.as_ref()
+    }
 }
@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use crate::sampler::sampler::SamplerState;
+
 use crate::util::rtweekend::{degrees_to_radians, INFINITY};
 
 use super::{aabb::Aabb, hit::Hittable, ray::Ray, vec3::Vec3};
@@ -55,16 +57,8 @@ impl RotateY {
             bbox,
         }
     }
-}
 
-impl Hittable for RotateY {
-    fn hit(
-        &self,
-        r: &super::ray::Ray,
-        t_min: f64,
-        t_max: f64,
-        rec: &mut super::hit::HitRecord,
-    ) -> bool {
+    fn rotate_ray(&self, r: &Ray) -> Ray {
         let mut origin = r.origin().clone();
         let mut direction = r.dir().clone();
 
@@ -74,9 +68,30 @@ impl Hittable for RotateY {
         direction[0] = self.cos_theta * r.dir()[0] - self.sin_theta * r.dir()[2];
         direction[2] = self.sin_theta * r.dir()[0] + self.cos_theta * r.dir()[2];
 
-        let rotated_r = Ray::new(&origin, &direction, r.time());
+        Ray::new(&origin, &direction, r.time())
+    }
+
+    fn rotate_to_world(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x() + self.sin_theta * v.z(),
+            v.y(),
+            -self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
+}
+
+impl Hittable for RotateY {
+    fn hit(
+        &self,
+        r: &super::ray::Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let rotated_r = self.rotate_ray(r);
 
-        if !self.hittable.hit(&rotated_r, t_min, t_max, rec) {
+        if !self.hittable.hit(&rotated_r, t_min, t_max, rec, sampler) {
             return false;
         }
 
@@ -89,8 +104,12 @@ impl Hittable for RotateY {
         normal[0] = self.cos_theta * rec.normal[0] + self.sin_theta * rec.normal[2];
         normal[2] = -self.sin_theta * rec.normal[0] + self.cos_theta * rec.normal[2];
 
+        // The inner hittable already oriented the normal against the ray and set
+        // `front_face`, both of which a rotation preserves.
         rec.p = p;
-        rec.set_face_normal(&rotated_r, &normal);
+        rec.normal = normal;
+        rec.dpdu = self.rotate_to_world(&rec.dpdu);
+        rec.dpdv = self.rotate_to_world(&rec.dpdv);
 
         return true;
     }
@@ -99,4 +118,61 @@ impl Hittable for RotateY {
         *output_box = self.bbox.clone();
         return self.has_box;
     }
+
+    fn area(&self) -> f64 {
+        self.hittable.area()
+    }
+
+    fn random_surface_point(
+        &self,
+        time: f64,
+        rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        if !self.hittable.random_surface_point(time, rec, sampler) {
+            return false;
+        }
+
+        let mut p = rec.p;
+        let mut normal = rec.normal;
+
+        p[0] = self.cos_theta * rec.p[0] + self.sin_theta * rec.p[2];
+        p[2] = -self.sin_theta * rec.p[0] + self.cos_theta * rec.p[2];
+
+        normal[0] = self.cos_theta * rec.normal[0] + self.sin_theta * rec.normal[2];
+        normal[2] = -self.sin_theta * rec.normal[0] + self.cos_theta * rec.normal[2];
+
+        rec.p = p;
+        rec.normal = normal;
+        rec.dpdu = self.rotate_to_world(&rec.dpdu);
+        rec.dpdv = self.rotate_to_world(&rec.dpdv);
+        true
+    }
+
+    fn transmittance(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        self.hittable
+            .transmittance(&self.rotate_ray(r), t_min, t_max, sampler)
+    }
+
+    fn contains_media(&self) -> bool {
+        self.hittable.contains_media()
+    }
+
+    fn medium_weight(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        event: Option<&super::hit::HitRecord>,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        self.hittable
+            .medium_weight(&self.rotate_ray(r), t_min, t_max, event, sampler)
+    }
 }
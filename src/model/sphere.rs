@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::{material::material::Material, util::rtweekend::PI};
+use crate::{material::material::Material, sampler::sampler::SamplerState, util::rtweekend::PI};
 
 use super::{aabb::Aabb, hit::Hittable, vec3::Vec3};
 
@@ -21,12 +21,33 @@ impl Sphere {
         }
     }
 
-    fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+    pub fn get_sphere_uv(p: &Point3) -> (f64, f64) {
         let theta = (-p.y()).acos();
         let phi = (-p.z()).atan2(p.x()) + PI;
 
         (phi / (2.0 * PI), theta / PI)
     }
+
+    /// Partial derivatives of the surface point with respect to the `u` and `v`
+    /// of `get_sphere_uv`, given the outward normal `n`.
+    pub fn get_sphere_dpduv(n: &Vec3, radius: f64) -> (Vec3, Vec3) {
+        let dpdu = 2.0 * PI * radius * Vec3::new(n.z(), 0.0, -n.x());
+
+        // At the poles the v derivative is only defined up to the direction of u.
+        let sin_theta = (1.0 - n.y() * n.y()).max(0.0).sqrt();
+        let dpdv = if sin_theta > 1e-8 {
+            PI * radius
+                * Vec3::new(
+                    -n.x() * n.y() / sin_theta,
+                    sin_theta,
+                    -n.z() * n.y() / sin_theta,
+                )
+        } else {
+            Vec3::new(0.0, 0.0, PI * radius)
+        };
+
+        (dpdu, dpdv)
+    }
 }
 
 impl Hittable for Sphere {
@@ -36,6 +57,7 @@ impl Hittable for Sphere {
         t_min: f64,
         t_max: f64,
         rec: &mut super::hit::HitRecord,
+        _sampler: &mut dyn SamplerState,
     ) -> bool {
         let oc = r.origin() - self.center;
         let a = r.dir().length_squared();
@@ -65,6 +87,7 @@ impl Hittable for Sphere {
         let (u, v) = Sphere::get_sphere_uv(&outward_normal);
         rec.u = u;
         rec.v = v;
+        (rec.dpdu, rec.dpdv) = Sphere::get_sphere_dpduv(&outward_normal, self.radius);
 
         return true;
     }
@@ -77,4 +100,28 @@ impl Hittable for Sphere {
 
         return true;
     }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn random_surface_point(
+        &self,
+        _time: f64,
+        rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        let outward_normal = Vec3::sample_unit_vector(sampler.next_2d());
+        let (u, v) = Sphere::get_sphere_uv(&outward_normal);
+
+        rec.p = self.center + self.radius * outward_normal;
+        rec.normal = outward_normal;
+        rec.front_face = true;
+        rec.t = 0.0;
+        rec.u = u;
+        rec.v = v;
+        (rec.dpdu, rec.dpdv) = Sphere::get_sphere_dpduv(&outward_normal, self.radius);
+        rec.material = self.material.clone();
+        true
+    }
 }
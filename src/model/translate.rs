@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use crate::sampler::sampler::SamplerState;
+
 use super::{aabb::Aabb, hit::Hittable, ray::Ray, vec3::Vec3};
 
 pub struct Translate {
@@ -23,14 +25,15 @@ impl Hittable for Translate {
         t_min: f64,
         t_max: f64,
         rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
     ) -> bool {
         let moved_r = Ray::new(&(r.origin() - self.offset), r.dir(), r.time());
-        if !self.hittable.hit(&moved_r, t_min, t_max, rec) {
+        if !self.hittable.hit(&moved_r, t_min, t_max, rec, sampler) {
             return false;
         }
 
+        // A translation leaves the normal and the face that was hit unchanged.
         rec.p += self.offset;
-        rec.set_face_normal(&moved_r, &rec.normal.clone());
 
         return true;
     }
@@ -46,4 +49,50 @@ impl Hittable for Translate {
         );
         return true;
     }
+
+    fn area(&self) -> f64 {
+        self.hittable.area()
+    }
+
+    fn random_surface_point(
+        &self,
+        time: f64,
+        rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        if !self.hittable.random_surface_point(time, rec, sampler) {
+            return false;
+        }
+
+        rec.p += self.offset;
+        true
+    }
+
+    fn transmittance(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        let moved_r = Ray::new(&(r.origin() - self.offset), r.dir(), r.time());
+        self.hittable.transmittance(&moved_r, t_min, t_max, sampler)
+    }
+
+    fn contains_media(&self) -> bool {
+        self.hittable.contains_media()
+    }
+
+    fn medium_weight(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        event: Option<&super::hit::HitRecord>,
+        sampler: &mut dyn SamplerState,
+    ) -> Vec3 {
+        let moved_r = Ray::new(&(r.origin() - self.offset), r.dir(), r.time());
+        self.hittable
+            .medium_weight(&moved_r, t_min, t_max, event, sampler)
+    }
 }
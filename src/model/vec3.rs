@@ -3,7 +3,7 @@ use std::{
     ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
-use crate::util::rtweekend::{random_double, random_double_by_range};
+use crate::util::rtweekend::{random_double, random_double_by_range, PI};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vec3 {
@@ -63,6 +63,49 @@ impl Vec3 {
         )
     }
 
+    /// Uniformly distributed direction, for two numbers in [0, 1).
+    pub fn sample_unit_vector(u: (f64, f64)) -> Vec3 {
+        let z = 1.0 - 2.0 * u.0;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    /// Uniformly distributed point inside the unit sphere, for three numbers in [0, 1).
+    pub fn sample_in_unit_sphere(u: (f64, f64), w: f64) -> Vec3 {
+        w.cbrt() * Vec3::sample_unit_vector(u)
+    }
+
+    /// Uniformly distributed point inside the unit disk in the xy plane, for two numbers
+    /// in [0, 1), by the concentric mapping of Shirley and Chiu.
+    pub fn sample_unit_disk(u: (f64, f64)) -> Vec3 {
+        let a = 2.0 * u.0 - 1.0;
+        let b = 2.0 * u.1 - 1.0;
+        if a == 0.0 && b == 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, PI / 4.0 * (b / a))
+        } else {
+            (b, PI / 2.0 - PI / 4.0 * (a / b))
+        };
+        Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+    }
+
+    /// Direction about +z with density proportional to its cosine, for two numbers in
+    /// [0, 1).
+    pub fn sample_cosine_direction(u: (f64, f64)) -> Vec3 {
+        let phi = 2.0 * PI * u.0;
+
+        Vec3::new(
+            phi.cos() * u.1.sqrt(),
+            phi.sin() * u.1.sqrt(),
+            (1.0 - u.1).sqrt(),
+        )
+    }
+
     pub fn random_in_unit_sphere() -> Vec3 {
         loop {
             let p = Vec3::random_by_range(-1.0, 1.0);
@@ -77,18 +120,16 @@ impl Vec3 {
         Vec3::random_in_unit_sphere().unit_vector()
     }
 
-    pub fn random_unit_disk() -> Vec3 {
-        loop {
-            let p = Vec3::new(
-                random_double_by_range(-1.0, 1.0),
-                random_double_by_range(-1.0, 1.0),
-                0.0,
-            );
-            if p.length_squared() >= 1.0 {
-                continue;
-            }
-            return p;
-        }
+    pub fn random_cosine_direction() -> Vec3 {
+        Vec3::sample_cosine_direction((random_double(), random_double()))
+    }
+
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.e[0] + 0.7152 * self.e[1] + 0.0722 * self.e[2]
+    }
+
+    pub fn max_component(&self) -> f64 {
+        self.e[0].max(self.e[1]).max(self.e[2])
     }
 
     pub fn near_zero(&self) -> bool {
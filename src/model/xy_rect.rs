@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::material::material::Material;
+use crate::{material::material::Material, sampler::sampler::SamplerState};
 
 use super::{aabb::Aabb, hit::Hittable, vec3::Vec3};
 
@@ -40,6 +40,7 @@ impl Hittable for XyRect {
         t_min: f64,
         t_max: f64,
         rec: &mut super::hit::HitRecord,
+        _sampler: &mut dyn SamplerState,
     ) -> bool {
         let t = (self.k - r.origin().z()) / r.dir().z();
         if t < t_min || t > t_max {
@@ -54,6 +55,8 @@ impl Hittable for XyRect {
         rec.u = (x - self.x0) / (self.x1 - self.x0);
         rec.v = (y - self.y0) / (self.y1 - self.y0);
         rec.t = t;
+        rec.dpdu = Vec3::new(self.x1 - self.x0, 0.0, 0.0);
+        rec.dpdv = Vec3::new(0.0, self.y1 - self.y0, 0.0);
 
         let outward_normal = Vec3::new(0.0, 0.0, 1.0);
         rec.set_face_normal(r, &outward_normal);
@@ -69,4 +72,28 @@ impl Hittable for XyRect {
         );
         return true;
     }
+
+    fn area(&self) -> f64 {
+        (self.x1 - self.x0) * (self.y1 - self.y0)
+    }
+
+    fn random_surface_point(
+        &self,
+        _time: f64,
+        rec: &mut super::hit::HitRecord,
+        sampler: &mut dyn SamplerState,
+    ) -> bool {
+        (rec.u, rec.v) = sampler.next_2d();
+        let a = self.x0 + rec.u * (self.x1 - self.x0);
+        let b = self.y0 + rec.v * (self.y1 - self.y0);
+
+        rec.p = Vec3::new(a, b, self.k);
+        rec.normal = Vec3::new(0.0, 0.0, 1.0);
+        rec.dpdu = Vec3::new(self.x1 - self.x0, 0.0, 0.0);
+        rec.dpdv = Vec3::new(0.0, self.y1 - self.y0, 0.0);
+        rec.front_face = true;
+        rec.t = 0.0;
+        rec.material = self.mp.clone();
+        true
+    }
 }
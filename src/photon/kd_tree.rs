@@ -0,0 +1,138 @@
+use crate::model::vec3::Vec3;
+
+use Vec3 as Point3;
+
+#[derive(Clone, Copy)]
+pub struct Photon {
+    pub position: Point3,
+    /// Direction the photon was travelling when it landed.
+    pub direction: Vec3,
+    pub power: Vec3,
+}
+
+/// Balanced kd-tree stored implicitly in a flat array: the median of every range
+/// sits in the middle of that range and the split axis is kept alongside it.
+pub struct KdTree {
+    photons: Vec<Photon>,
+    axes: Vec<u8>,
+}
+
+impl KdTree {
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        let mut axes = vec![0; photons.len()];
+        let len = photons.len();
+        KdTree::build(&mut photons, &mut axes, 0, len);
+
+        Self { photons, axes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    fn build(photons: &mut [Photon], axes: &mut [u8], start: usize, end: usize) {
+        if end - start <= 1 {
+            return;
+        }
+
+        // Split along the axis with the largest extent.
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(-f64::INFINITY, -f64::INFINITY, -f64::INFINITY);
+        for photon in photons[start..end].iter() {
+            for a in 0..3 {
+                min[a] = min[a].min(photon.position[a]);
+                max[a] = max[a].max(photon.position[a]);
+            }
+        }
+        let extent = max - min;
+        let axis = if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        };
+
+        let mid = start + (end - start) / 2;
+        photons[start..end].select_nth_unstable_by(mid - start, |a, b| {
+            a.position[axis].total_cmp(&b.position[axis])
+        });
+        axes[mid] = axis as u8;
+
+        KdTree::build(photons, axes, start, mid);
+        KdTree::build(photons, axes, mid + 1, end);
+    }
+
+    /// Calls `f` for every photon within `radius` of `p`.
+    pub fn for_each_in_radius<F: FnMut(&Photon)>(&self, p: &Point3, radius: f64, mut f: F) {
+        self.visit(p, radius * radius, 0, self.photons.len(), &mut f);
+    }
+
+    fn visit<F: FnMut(&Photon)>(
+        &self,
+        p: &Point3,
+        radius_squared: f64,
+        start: usize,
+        end: usize,
+        f: &mut F,
+    ) {
+        if start >= end {
+            return;
+        }
+
+        let mid = start + (end - start) / 2;
+        let photon = &self.photons[mid];
+        if (photon.position - p).length_squared() <= radius_squared {
+            f(photon);
+        }
+        if end - start == 1 {
+            return;
+        }
+
+        let axis = self.axes[mid] as i32;
+        let delta = p[axis] - photon.position[axis];
+        let (near, far) = if delta < 0.0 {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+
+        self.visit(p, radius_squared, near.0, near.1, f);
+        if delta * delta <= radius_squared {
+            self.visit(p, radius_squared, far.0, far.1, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::rtweekend::random_double_by_range;
+
+    #[test]
+    fn test_kd_tree_matches_linear_search() {
+        let photons: Vec<Photon> = (0..500)
+            .map(|_| Photon {
+                position: Vec3::random_by_range(-10.0, 10.0),
+                direction: Vec3::new(0.0, -1.0, 0.0),
+                power: Vec3::new(1.0, 1.0, 1.0),
+            })
+            .collect();
+        let tree = KdTree::new(photons.clone());
+        assert_eq!(500, tree.len());
+
+        for _ in 0..20 {
+            let p = Vec3::random_by_range(-10.0, 10.0);
+            let radius = random_double_by_range(0.5, 5.0);
+
+            let expected = photons
+                .iter()
+                .filter(|photon| (photon.position - p).length() <= radius)
+                .count();
+            let mut found = 0;
+            tree.for_each_in_radius(&p, radius, |_| found += 1);
+
+            assert_eq!(expected, found);
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod kd_tree;
+pub mod photon_map;
@@ -0,0 +1,368 @@
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    material::interior::MediumStack,
+    model::{
+        hit::{HitRecord, Hittable, HittableList},
+        onb::Onb,
+        ray::Ray,
+        vec3::Vec3,
+    },
+    sampler::{independent::IndependentSampler, sampler::SamplerState},
+    util::rtweekend::{INFINITY, PI},
+};
+
+use super::kd_tree::{KdTree, Photon};
+
+/// Photon map holding caustic photons, i.e. photons that reached a diffuse
+/// surface after one or more specular bounces (light paths L S+ D).
+pub struct PhotonMap {
+    tree: KdTree,
+    pub radius: f64,
+}
+
+impl PhotonMap {
+    /// Shoots `photon_count` photons from the emissive hittables in `lights` through `world`.
+    /// `lights` must hold every emitter of the scene, since the renderer drops the caustic
+    /// paths it expects the photon map to cover.
+    pub fn new(
+        world: &(dyn Hittable + Sync + Send),
+        lights: &HittableList,
+        photon_count: usize,
+        radius: f64,
+        max_depth: i32,
+    ) -> Self {
+        let powers = light_powers(lights);
+        let total_power: f64 = powers.iter().sum();
+
+        let photons: Vec<Photon> = if total_power > 0.0 {
+            (0..photon_count)
+                .into_par_iter()
+                .filter_map(|_| {
+                    trace_photon(
+                        world,
+                        lights,
+                        &powers,
+                        total_power,
+                        photon_count,
+                        max_depth,
+                        &mut IndependentSampler,
+                    )
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            tree: KdTree::new(photons),
+            radius,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Caustic radiance scattered along `-r_in` at a surface hit, weighting each photon
+    /// by the material's BSDF with a constant kernel over the disc of `radius` around
+    /// `rec.p`.
+    pub fn estimate(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        let mut radiance = Vec3::new(0.0, 0.0, 0.0);
+        self.tree.for_each_in_radius(&rec.p, self.radius, |photon| {
+            // `eval` includes the cosine at the incoming direction, which the photon
+            // power already accounts for. Grazing photons carry no reliable weight.
+            let wi = -photon.direction;
+            let cos_theta = wi.dot(&rec.normal).abs();
+            if cos_theta > 1e-4 {
+                radiance += rec.material.eval(r_in, rec, &wi) / cos_theta * photon.power;
+            }
+        });
+
+        radiance / (PI * self.radius * self.radius)
+    }
+}
+
+/// Rough estimate of the power of each light, used to pick lights proportionally to it.
+fn light_powers(lights: &HittableList) -> Vec<f64> {
+    let mut powers = Vec::with_capacity(lights.objects.len());
+    for light in lights.objects.iter() {
+        let mut rec = HitRecord::default();
+        let power = if light.random_surface_point(0.0, &mut rec, &mut IndependentSampler) {
+            // Radiance straight along the outward normal.
+            let r = Ray::new(&(rec.p + rec.normal), &-rec.normal, 0.0);
+            rec.material
+                .emitted(&r, &rec, rec.u, rec.v, &rec.p)
+                .luminance()
+                * light.area()
+        } else {
+            0.0
+        };
+        powers.push(power.max(0.0));
+    }
+
+    powers
+}
+
+/// Starts a photon on a light picked by power, returning the ray leaving the light and
+/// the photon's share of the power emitted by all `lights` out of `photon_count`.
+fn emit_photon(
+    lights: &HittableList,
+    powers: &[f64],
+    total_power: f64,
+    photon_count: usize,
+    sampler: &mut dyn SamplerState,
+) -> Option<(Ray, Vec3)> {
+    let mut target = sampler.next_1d() * total_power;
+    let mut index = powers.len() - 1;
+    for (i, power) in powers.iter().enumerate() {
+        if target < *power {
+            index = i;
+            break;
+        }
+        target -= power;
+    }
+    let light = &lights.objects[index];
+    let pmf = powers[index] / total_power;
+
+    let time = sampler.next_1d();
+    let mut rec = HitRecord::default();
+    if !light.random_surface_point(time, &mut rec, sampler) {
+        return None;
+    }
+
+    // Emitters may radiate from both faces, so pick one and double the power.
+    // The material decides how much leaves that face in the sampled direction.
+    if sampler.next_1d() < 0.5 {
+        rec.normal = -rec.normal;
+        rec.front_face = false;
+    }
+    let direction =
+        Onb::build_from_w(&rec.normal).local(&Vec3::sample_cosine_direction(sampler.next_2d()));
+    let r = Ray::new(&(rec.p + direction), &-direction, time);
+    let emitted = rec.material.emitted(&r, &rec, rec.u, rec.v, &rec.p);
+    if emitted.near_zero() {
+        return None;
+    }
+    let power = 2.0 * emitted * PI * light.area() / (pmf * photon_count as f64);
+
+    Some((Ray::new(&rec.p, &direction, time), power))
+}
+
+fn trace_photon(
+    world: &(dyn Hittable + Sync + Send),
+    lights: &HittableList,
+    powers: &[f64],
+    total_power: f64,
+    photon_count: usize,
+    max_depth: i32,
+    sampler: &mut dyn SamplerState,
+) -> Option<Photon> {
+    let (mut ray, mut power) = emit_photon(lights, powers, total_power, photon_count, sampler)?;
+    let mut bounced_specular = false;
+    let mut media = MediumStack::new();
+    let mut depth = 0;
+
+    while depth < max_depth {
+        let mut hit = HitRecord::default();
+        if !world.hit(&ray, 0.001, INFINITY, &mut hit, sampler) {
+            return None;
+        }
+        power *= world.medium_weight(&ray, 0.001, hit.t, Some(&hit), sampler)
+            * media.transmittance(hit.t * ray.dir().length());
+
+        // Nested dielectrics are tracked as in the renderer, skipped surfaces not
+        // counting as bounces.
+        let interior = hit.material.interior();
+        if let Some(interior) = interior.as_ref() {
+            let id = MediumStack::id(&hit);
+            match media.exterior_ior(id, interior) {
+                Some(ior) => hit.exterior_ior = ior,
+                None => {
+                    media.cross(id, interior, hit.front_face);
+                    ray = Ray::new(&hit.p, ray.dir(), ray.time());
+                    continue;
+                }
+            }
+        }
+
+        // Only surfaces store photons, a medium ends the caustic path.
+        if !hit.material.is_specular() {
+            if !bounced_specular || hit.material.is_volume() {
+                return None;
+            }
+
+            return Some(Photon {
+                position: hit.p,
+                direction: ray.dir().unit_vector(),
+                power,
+            });
+        }
+
+        let mut attenuation = Vec3::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 0.0), 0.0);
+        if !hit
+            .material
+            .scatter(&ray, &hit, &mut attenuation, &mut scattered, sampler)
+        {
+            return None;
+        }
+
+        // Russian roulette keeps photon powers roughly constant.
+        let survival = attenuation.max_component().min(1.0);
+        if survival <= 0.0 || sampler.next_1d() > survival {
+            return None;
+        }
+        power *= attenuation / survival;
+        bounced_specular = true;
+        if let Some(interior) = interior.as_ref() {
+            if scattered.dir().dot(&hit.normal) < 0.0 {
+                media.cross(MediumStack::id(&hit), interior, hit.front_face);
+            }
+        }
+        ray = scattered;
+        depth += 1;
+    }
+
+    None
+}
+
+/// Progressive photon mapping in the probabilistic formulation of Knaus and Zwicker:
+/// every pass uses a fresh photon map with a slowly shrinking gather radius, so the
+/// average over passes converges to the correct caustics.
+pub struct ProgressivePhotonMapping {
+    pub passes: usize,
+    pub photons_per_pass: usize,
+    pub initial_radius: f64,
+    pub alpha: f64,
+    pub max_depth: i32,
+}
+
+impl ProgressivePhotonMapping {
+    pub fn new(passes: usize, photons_per_pass: usize, initial_radius: f64) -> Self {
+        Self {
+            passes,
+            photons_per_pass,
+            initial_radius,
+            alpha: 0.7,
+            max_depth: 20,
+        }
+    }
+
+    pub fn radius(&self, pass: usize) -> f64 {
+        let mut radius_squared = self.initial_radius * self.initial_radius;
+        for i in 1..=pass {
+            radius_squared *= (i as f64 + self.alpha) / (i as f64 + 1.0);
+        }
+
+        radius_squared.sqrt()
+    }
+
+    pub fn photon_map(
+        &self,
+        pass: usize,
+        world: &(dyn Hittable + Sync + Send),
+        lights: &HittableList,
+    ) -> PhotonMap {
+        PhotonMap::new(
+            world,
+            lights,
+            self.photons_per_pass,
+            self.radius(pass),
+            self.max_depth,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        material::{diffuse_light::DiffuseLight, lambertian::Lambertian},
+        model::{moving_sphere::MovingSphere, r#box::Box},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_emitted_power_matches_the_lights() {
+        let light = |watts: f64, area: f64| {
+            Arc::new(DiffuseLight::new_with_color(Vec3::new(1.0, 1.0, 1.0)).with_power(watts, area))
+        };
+        let sphere = Arc::new(MovingSphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            0.0,
+            1.0,
+            0.5,
+            light(30.0, PI),
+        ));
+        let mut lights = HittableList::new();
+        lights.add(Arc::new(Box::new(
+            &Vec3::new(-1.0, -1.0, -1.0),
+            &Vec3::new(1.0, 2.0, 1.0),
+            light(60.0, 32.0),
+        )));
+        lights.add(sphere.clone());
+
+        let powers = light_powers(&lights);
+        let total_power: f64 = powers.iter().sum();
+        let photon_count = 20_000;
+        let mut emitted = Vec3::new(0.0, 0.0, 0.0);
+        for _ in 0..photon_count {
+            let (_, power) = emit_photon(
+                &lights,
+                &powers,
+                total_power,
+                photon_count,
+                &mut IndependentSampler,
+            )
+            .unwrap();
+            emitted += power;
+        }
+
+        for c in 0..3 {
+            assert!((emitted[c] - 90.0).abs() < 0.1, "emitted {}", emitted);
+        }
+
+        // Points on the moving sphere follow it through the shutter interval.
+        for time in [0.0, 0.3, 1.0] {
+            let mut rec = HitRecord::default();
+            assert!(sphere.random_surface_point(time, &mut rec, &mut IndependentSampler));
+            assert!(((rec.p - sphere.center(time)).length() - 0.5).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_estimate_weights_photons_by_the_bsdf() {
+        let photon = |direction: Vec3| Photon {
+            position: Vec3::new(0.0, 0.0, 0.0),
+            direction,
+            power: Vec3::new(1.0, 1.0, 1.0),
+        };
+        let mut photons: Vec<Photon> = (0..10).map(|_| photon(Vec3::new(0.6, 0.0, -0.8))).collect();
+        // Photons reaching the back of an opaque surface are not seen from the front.
+        photons.push(photon(Vec3::new(0.0, 0.0, 1.0)));
+        let caustics = PhotonMap {
+            tree: KdTree::new(photons),
+            radius: 0.5,
+        };
+
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            front_face: true,
+            material: Arc::new(Lambertian::new(&Vec3::new(0.5, 0.5, 0.5))),
+            ..Default::default()
+        };
+        let r_in = Ray::new(&Vec3::new(0.0, 0.0, 1.0), &Vec3::new(0.0, 0.0, -1.0), 0.0);
+
+        let expected = 0.5 / PI * 10.0 / (PI * 0.25);
+        let radiance = caustics.estimate(&r_in, &rec);
+        assert!(
+            (radiance.x() - expected).abs() < 1e-9,
+            "radiance {}",
+            radiance
+        );
+    }
+}
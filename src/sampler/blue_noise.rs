@@ -0,0 +1,167 @@
+use std::sync::OnceLock;
+
+use super::{
+    sampler::{hash, hash_to_unit, Sampler},
+    sobol::SobolSampler,
+};
+
+/// Side of the tiled blue noise mask, in pixels.
+const MASK_SIZE: usize = 64;
+
+/// Every pixel follows the same scrambled Sobol sequence, shifted per dimension by a
+/// threshold from a blue noise mask (Georgiev and Fajardo, 2016). Neighbouring pixels
+/// then get very different offsets, which at low sample counts leaves noise without
+/// low frequencies, that reads as finer grain and blurs away easily.
+pub struct BlueNoiseSampler {
+    sequence: SobolSampler,
+}
+
+impl BlueNoiseSampler {
+    pub fn new() -> Self {
+        Self {
+            sequence: SobolSampler::new(0),
+        }
+    }
+}
+
+impl Default for BlueNoiseSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn sample(&self, x: usize, y: usize, index: usize, dimension: usize) -> f64 {
+        // Each dimension reads the mask at its own toroidal shift.
+        let shift = hash(&[dimension as u64]);
+        let mx = (x + (shift & 0xffff) as usize) % MASK_SIZE;
+        let my = (y + (shift >> 16 & 0xffff) as usize) % MASK_SIZE;
+        let offset = mask()[my * MASK_SIZE + mx];
+
+        let value = self.sequence.sample(0, 0, index, dimension) + offset;
+        (value - value.floor()).min(1.0 - f64::EPSILON)
+    }
+}
+
+/// The mask, made on first use.
+fn mask() -> &'static [f64] {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    MASK.get_or_init(|| void_and_cluster(MASK_SIZE, 1.5))
+}
+
+/// Threshold mask with a blue noise spectrum, ranking every pixel of a tileable
+/// `size` by `size` square (Ulichney, "The void-and-cluster method", 1993). Each
+/// pixel is given a rank by how early it joins a set of points kept as evenly spread
+/// as possible; ranks are returned scaled to [0, 1).
+pub fn void_and_cluster(size: usize, sigma: f64) -> Vec<f64> {
+    let n = size * size;
+
+    // Gaussian splat over the torus, looked up by offset.
+    let kernel: Vec<f64> = (0..n)
+        .map(|i| {
+            let wrap = |d: usize| d.min(size - d) as f64;
+            let (dx, dy) = (wrap(i % size), wrap(i / size));
+            (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let splat = |energy: &mut [f64], p: usize, sign: f64| {
+        let (px, py) = (p % size, p / size);
+        for (q, e) in energy.iter_mut().enumerate() {
+            let dx = (q % size + size - px) % size;
+            let dy = (q / size + size - py) % size;
+            *e += sign * kernel[dy * size + dx];
+        }
+    };
+    let tightest_cluster = |energy: &[f64], points: &[bool]| {
+        (0..n)
+            .filter(|&p| points[p])
+            .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+    let largest_void = |energy: &[f64], points: &[bool]| {
+        (0..n)
+            .filter(|&p| !points[p])
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+
+    // Initial pattern: a tenth of the pixels, spread out by moving points from the
+    // tightest cluster to the largest void until that changes nothing.
+    let initial_count = (n / 10).max(1);
+    let mut points = vec![false; n];
+    let mut energy = vec![0.0; n];
+    let mut placed = 0;
+    let mut attempt = 0;
+    while placed < initial_count {
+        let p = (hash(&[attempt]) % n as u64) as usize;
+        attempt += 1;
+        if !points[p] {
+            points[p] = true;
+            splat(&mut energy, p, 1.0);
+            placed += 1;
+        }
+    }
+    for _ in 0..n {
+        let cluster = tightest_cluster(&energy, &points);
+        points[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        let void = largest_void(&energy, &points);
+        points[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; n];
+
+    // Ranks below the initial count, removing the tightest clusters first.
+    let (mut phase_points, mut phase_energy) = (points.clone(), energy.clone());
+    for r in (0..initial_count).rev() {
+        let cluster = tightest_cluster(&phase_energy, &phase_points);
+        phase_points[cluster] = false;
+        splat(&mut phase_energy, cluster, -1.0);
+        rank[cluster] = r;
+    }
+
+    // Ranks above it, filling the largest voids until every pixel is taken.
+    for r in initial_count..n {
+        let void = largest_void(&energy, &points);
+        points[void] = true;
+        splat(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+
+    rank.iter()
+        .enumerate()
+        .map(|(p, &r)| (r as f64 + hash_to_unit(hash(&[p as u64, 1]))) / n as f64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_ranks_every_pixel_once() {
+        let size = 16;
+        let mask = void_and_cluster(size, 1.5);
+        let mut ranks: Vec<usize> = mask
+            .iter()
+            .map(|v| (v * (size * size) as f64) as usize)
+            .collect();
+        ranks.sort();
+        assert_eq!(ranks, (0..size * size).collect::<Vec<_>>());
+
+        // Neighbours differ a lot more than they would in white noise, whose mean
+        // absolute difference is a third.
+        let mean_difference = (0..size * size)
+            .map(|p| {
+                let right = p / size * size + (p + 1) % size;
+                (mask[p] - mask[right]).abs()
+            })
+            .sum::<f64>()
+            / (size * size) as f64;
+        assert!(mean_difference > 0.4);
+    }
+}
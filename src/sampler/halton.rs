@@ -0,0 +1,52 @@
+use super::sampler::{hash, hash_to_unit, Sampler};
+
+/// Bases of the dimensions that follow the Halton sequence. Later primes give poorly
+/// distributed points at low sample counts, so the dimensions past them are random.
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// The Halton sequence, radical inverses in successive prime bases, shifted by a
+/// random offset per pixel and dimension so that neighbouring pixels do not repeat
+/// the same pattern.
+pub struct HaltonSampler;
+
+impl Sampler for HaltonSampler {
+    fn sample(&self, x: usize, y: usize, index: usize, dimension: usize) -> f64 {
+        let seed = hash(&[x as u64, y as u64, dimension as u64]);
+        match PRIMES.get(dimension) {
+            Some(&base) => {
+                let value = radical_inverse(base, index as u64) + hash_to_unit(seed);
+                (value - value.floor()).min(1.0 - f64::EPSILON)
+            }
+            None => hash_to_unit(hash(&[seed, index as u64])),
+        }
+    }
+}
+
+/// Digits of `a` in `base` mirrored around the radix point.
+pub fn radical_inverse(base: u64, mut a: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_n = 1.0;
+    let mut reversed = 0_u64;
+    while a > 0 {
+        let next = a / base;
+        reversed = reversed * base + (a - next * base);
+        inv_base_n *= inv_base;
+        a = next;
+    }
+    (reversed as f64 * inv_base_n).min(1.0 - f64::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_radical_inverse() {
+        assert_eq!(radical_inverse(2, 1), 0.5);
+        assert_eq!(radical_inverse(2, 6), 0.375);
+        assert!((radical_inverse(3, 5) - 7.0 / 9.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,19 @@
+use rand::Rng;
+
+use super::sampler::{Sampler, SamplerState};
+
+/// Uncorrelated random numbers for every dimension, as if there were no sampler. It
+/// also serves as the state of paths that belong to no pixel, such as photons.
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn sample(&self, _x: usize, _y: usize, _index: usize, _dimension: usize) -> f64 {
+        rand::thread_rng().gen::<f64>()
+    }
+}
+
+impl SamplerState for IndependentSampler {
+    fn next_1d(&mut self) -> f64 {
+        rand::thread_rng().gen::<f64>()
+    }
+}
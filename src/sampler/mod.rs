@@ -0,0 +1,6 @@
+pub mod blue_noise;
+pub mod halton;
+pub mod independent;
+pub mod sampler;
+pub mod sobol;
+pub mod stratified;
@@ -0,0 +1,131 @@
+use crate::util::rtweekend::random_double;
+
+/// Source of the random numbers for the paths through a pixel. Each path draws its
+/// numbers as consecutive dimensions of one sample, so a sampler that spreads the
+/// samples of a pixel evenly in every dimension stratifies the pixel jitter, the lens
+/// and the bounces that follow at once.
+pub trait Sampler {
+    /// Coordinate `dimension` of sample `index` of the pixel at `(x, y)`, in [0, 1).
+    fn sample(&self, x: usize, y: usize, index: usize, dimension: usize) -> f64;
+}
+
+/// Random numbers of the path being traced, handed down to the camera, lights and
+/// materials that decide where it goes.
+pub trait SamplerState {
+    /// Next number of the path, in [0, 1).
+    fn next_1d(&mut self) -> f64;
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let u = self.next_1d();
+        (u, self.next_1d())
+    }
+
+    /// Moves on to the numbers of bounce `bounce`, which start at the same dimension
+    /// however many numbers the camera and earlier bounces drew.
+    fn start_bounce(&mut self, _bounce: usize) {}
+}
+
+/// Dimensions the camera draws from: the position on the film, the lens and the time.
+const CAMERA_DIMENSIONS: usize = 6;
+/// Dimensions set aside for each bounce. Numbers drawn past them are independent
+/// random numbers, which keeps them from repeating those of the next bounce.
+const BOUNCE_DIMENSIONS: usize = 8;
+
+/// Dimensions of sample `index` of the pixel at `(x, y)`, drawn in order.
+pub struct PixelSample<'a> {
+    sampler: &'a (dyn Sampler + Sync + Send),
+    x: usize,
+    y: usize,
+    index: usize,
+    dimension: usize,
+    end: usize,
+}
+
+impl<'a> PixelSample<'a> {
+    pub fn new(sampler: &'a (dyn Sampler + Sync + Send), x: usize, y: usize, index: usize) -> Self {
+        Self {
+            sampler,
+            x,
+            y,
+            index,
+            dimension: 0,
+            end: CAMERA_DIMENSIONS,
+        }
+    }
+}
+
+impl SamplerState for PixelSample<'_> {
+    fn next_1d(&mut self) -> f64 {
+        if self.dimension >= self.end {
+            return random_double();
+        }
+
+        let value = self
+            .sampler
+            .sample(self.x, self.y, self.index, self.dimension);
+        self.dimension += 1;
+        value
+    }
+
+    fn start_bounce(&mut self, bounce: usize) {
+        self.dimension = CAMERA_DIMENSIONS + bounce * BOUNCE_DIMENSIONS;
+        self.end = self.dimension + BOUNCE_DIMENSIONS;
+    }
+}
+
+/// Well mixed 64 bit hash of a few integers, for seeding per pixel and dimension.
+pub fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e37_79b9_7f4a_7c15, |h, &v| {
+        mix_bits(h ^ v.wrapping_add(0x9e37_79b9_7f4a_7c15))
+    })
+}
+
+/// Finaliser of SplitMix64.
+pub fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5_d329_728e_a185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81da_def4_bc2d_d44d);
+    v ^ (v >> 33)
+}
+
+/// Uniform value in [0, 1) from the top 53 bits of a hash.
+pub fn hash_to_unit(h: u64) -> f64 {
+    (h >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+/// Fixed point fraction in [0, 1), never rounding up to 1.
+pub fn u32_to_unit(v: u32) -> f64 {
+    v as f64 / 4_294_967_296.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter;
+
+    impl Sampler for Counter {
+        fn sample(&self, x: usize, y: usize, index: usize, dimension: usize) -> f64 {
+            (x + 10 * y + 100 * index + 1000 * dimension) as f64 / 100000.0
+        }
+    }
+
+    #[test]
+    fn test_bounces_start_at_fixed_dimensions() {
+        let mut state = PixelSample::new(&Counter, 1, 2, 3);
+        assert_eq!(state.next_2d(), (0.00321, 0.01321));
+
+        // However much the camera drew, the first bounce starts after it.
+        state.start_bounce(0);
+        assert_eq!(state.next_1d(), 0.06321);
+        state.start_bounce(1);
+        assert_eq!(state.next_1d(), 0.14321);
+
+        // Past its dimensions a bounce draws numbers from no dimension at all.
+        for _ in 1..BOUNCE_DIMENSIONS {
+            state.next_1d();
+        }
+        assert_ne!(state.next_1d(), 0.22321);
+    }
+}
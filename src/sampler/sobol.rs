@@ -0,0 +1,84 @@
+use super::sampler::{hash, u32_to_unit, Sampler};
+
+/// Owen scrambled Sobol points (Burley, "Practical Hash-based Owen Scrambling",
+/// 2020). Dimensions are taken in pairs from the first two dimensions of the Sobol
+/// sequence, each pair with its own scramble and its own shuffle of the sample order,
+/// which keeps every pair well stratified for any number of dimensions. Sample counts
+/// that are powers of two work best.
+pub struct SobolSampler {
+    seed: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn sample(&self, x: usize, y: usize, index: usize, dimension: usize) -> f64 {
+        let pair = (dimension / 2) as u64;
+        let pair_seed = hash(&[self.seed, x as u64, y as u64, pair]) as u32;
+        let shuffled = nested_uniform_scramble(index as u32, pair_seed);
+
+        let point = if dimension.is_multiple_of(2) {
+            shuffled.reverse_bits()
+        } else {
+            sobol_second_dimension(shuffled)
+        };
+        let dimension_seed = hash(&[self.seed, x as u64, y as u64, dimension as u64, 1]) as u32;
+        u32_to_unit(nested_uniform_scramble(point, dimension_seed))
+    }
+}
+
+/// Second dimension of the Sobol sequence, whose generator matrix is Pascal's
+/// triangle modulo 2.
+fn sobol_second_dimension(mut index: u32) -> u32 {
+    let mut v = 1_u32 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+/// Hash that only lets bits affect less significant ones, so applied to reversed bits
+/// it acts as an Owen scramble (Laine and Karras, 2011).
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+pub fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairs_are_stratified() {
+        // Sixteen points of any pair fall one in each cell of a 4 by 4 grid.
+        let sampler = SobolSampler::new(7);
+        for pair in 0..3 {
+            let mut cells: Vec<usize> = (0..16)
+                .map(|i| {
+                    let u = sampler.sample(5, 9, i, 2 * pair);
+                    let v = sampler.sample(5, 9, i, 2 * pair + 1);
+                    4 * (4.0 * v) as usize + (4.0 * u) as usize
+                })
+                .collect();
+            cells.sort();
+            assert_eq!(cells, (0..16).collect::<Vec<_>>());
+        }
+    }
+}
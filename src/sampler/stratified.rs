@@ -0,0 +1,81 @@
+use super::sampler::{hash, hash_to_unit, Sampler};
+
+/// Jittered strata in every dimension: the samples of a pixel fall in distinct
+/// `1 / samples_per_pixel` wide strata of each dimension, shuffled independently per
+/// dimension so that dimensions do not line up with each other.
+pub struct StratifiedSampler {
+    samples_per_pixel: usize,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize) -> Self {
+        Self {
+            samples_per_pixel: samples_per_pixel.max(1),
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn sample(&self, x: usize, y: usize, index: usize, dimension: usize) -> f64 {
+        let n = self.samples_per_pixel;
+        // Samples past the planned count start another round of strata.
+        let round = index / n;
+        let seed = hash(&[x as u64, y as u64, dimension as u64, round as u64]);
+        let stratum = permutation_element((index % n) as u32, n as u32, seed as u32);
+        let jitter = hash_to_unit(hash(&[seed, index as u64]));
+
+        ((stratum as f64 + jitter) / n as f64).min(1.0 - f64::EPSILON)
+    }
+}
+
+/// Element `i` of a random permutation of `0..l` chosen by `p`, without storing it
+/// (Kensler, "Correlated Multi-Jittered Sampling", 2013).
+pub fn permutation_element(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % l
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_stratum_is_hit_once() {
+        let sampler = StratifiedSampler::new(10);
+        for dimension in 0..4 {
+            let mut strata: Vec<usize> = (0..10)
+                .map(|i| (sampler.sample(3, 7, i, dimension) * 10.0) as usize)
+                .collect();
+            strata.sort();
+            assert_eq!(strata, (0..10).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use crate::model::vec3::Vec3;
+
+use super::{image::ImageTexture, texture::Texture};
+
+/// Grey texture reading the alpha channel of an image, for use as an opacity mask.
+pub struct AlphaChannel {
+    pub image: Arc<ImageTexture>,
+}
+
+impl AlphaChannel {
+    pub fn new(image: Arc<ImageTexture>) -> Self {
+        Self { image }
+    }
+}
+
+impl Texture for AlphaChannel {
+    fn value(&self, u: f64, v: f64, _p: &Vec3) -> Vec3 {
+        let alpha = self.image.alpha(u, v);
+        Vec3::new(alpha, alpha, alpha)
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::model::vec3::Vec3;
+
+/// High dynamic range image with linear floating point texels, loaded from Radiance
+/// `.hdr` (RGBE) or Portable Float Map `.pfm` files. Rows are stored top to bottom.
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<Vec3>,
+}
+
+impl HdrImage {
+    pub fn new(width: usize, height: usize, data: Vec<Vec3>) -> Self {
+        assert_eq!(data.len(), width * height);
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Loads an image, choosing the format from the file extension.
+    pub fn load(filename: &str) -> io::Result<HdrImage> {
+        let bytes = fs::read(filename)?;
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("hdr") | Some("pic") => parse_radiance(&bytes),
+            Some("pfm") => parse_pfm(&bytes),
+            _ => Err(invalid_data(&format!(
+                "unsupported HDR image format '{}'",
+                filename
+            ))),
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3 {
+        self.data[y * self.width + x]
+    }
+}
+
+/// Parser for the Radiance RGBE format, with or without run-length encoded scanlines.
+fn parse_radiance(bytes: &[u8]) -> io::Result<HdrImage> {
+    let mut pos = 0;
+    let mut next_line = || -> io::Result<String> {
+        let end = bytes[pos..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or_else(|| invalid_data("truncated header"))?;
+        let line = String::from_utf8_lossy(&bytes[pos..pos + end]).into_owned();
+        pos += end + 1;
+        Ok(line)
+    };
+
+    if !next_line()?.starts_with("#?") {
+        return Err(invalid_data("missing Radiance signature"));
+    }
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(&format!("unsupported format '{}'", format)));
+            }
+        }
+    }
+
+    // Only the standard orientation, top to bottom and left to right, is supported.
+    let resolution = next_line()?;
+    let fields: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match fields.as_slice() {
+        ["-Y", h, "+X", w] => (
+            h.parse::<usize>().map_err(|_| invalid_data("bad height"))?,
+            w.parse::<usize>().map_err(|_| invalid_data("bad width"))?,
+        ),
+        _ => {
+            return Err(invalid_data(&format!(
+                "unsupported resolution line '{}'",
+                resolution
+            )))
+        }
+    };
+
+    let mut data = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+    let mut byte = || -> io::Result<u8> {
+        let b = *bytes
+            .get(pos)
+            .ok_or_else(|| invalid_data("truncated pixel data"))?;
+        pos += 1;
+        Ok(b)
+    };
+
+    for _ in 0..height {
+        let header = [byte()?, byte()?, byte()?, byte()?];
+        let rle = (8..0x8000).contains(&width) && header[0] == 2 && header[1] == 2;
+
+        if rle {
+            if ((header[2] as usize) << 8 | header[3] as usize) != width {
+                return Err(invalid_data("scanline width mismatch"));
+            }
+
+            // Each channel is stored separately as runs and literal spans.
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = byte()? as usize;
+                    if count > 128 {
+                        let run = count - 128;
+                        let value = byte()?;
+                        if x + run > width {
+                            return Err(invalid_data("run overflows scanline"));
+                        }
+                        for texel in &mut scanline[x..x + run] {
+                            texel[channel] = value;
+                        }
+                        x += run;
+                    } else {
+                        if count == 0 || x + count > width {
+                            return Err(invalid_data("bad literal span"));
+                        }
+                        for texel in &mut scanline[x..x + count] {
+                            texel[channel] = byte()?;
+                        }
+                        x += count;
+                    }
+                }
+            }
+        } else {
+            scanline[0] = header;
+            for texel in scanline.iter_mut().skip(1) {
+                *texel = [byte()?, byte()?, byte()?, byte()?];
+            }
+        }
+
+        data.extend(scanline.iter().map(|rgbe| {
+            if rgbe[3] == 0 {
+                return Vec3::new(0.0, 0.0, 0.0);
+            }
+            let f = 2f64.powi(rgbe[3] as i32 - 136);
+            Vec3::new(
+                (rgbe[0] as f64 + 0.5) * f,
+                (rgbe[1] as f64 + 0.5) * f,
+                (rgbe[2] as f64 + 0.5) * f,
+            )
+        }));
+    }
+
+    Ok(HdrImage::new(width, height, data))
+}
+
+/// Parser for Portable Float Maps, colour (`PF`) or greyscale (`Pf`).
+fn parse_pfm(bytes: &[u8]) -> io::Result<HdrImage> {
+    // Three whitespace-separated header tokens follow the magic number, and a single
+    // whitespace character separates the last of them from the raster.
+    let mut pos = 0;
+    let mut tokens = Vec::new();
+    while tokens.len() < 4 {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(invalid_data("truncated PFM header"));
+        }
+        tokens.push(String::from_utf8_lossy(&bytes[start..pos]).into_owned());
+    }
+    pos += 1;
+
+    let channels = match tokens[0].as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("missing PFM signature")),
+    };
+    let number = |token: &str| {
+        token
+            .parse::<f64>()
+            .map_err(|_| invalid_data(&format!("invalid PFM header value '{}'", token)))
+    };
+    let width = number(&tokens[1])? as usize;
+    let height = number(&tokens[2])? as usize;
+    let scale = number(&tokens[3])?;
+    let little_endian = scale < 0.0;
+
+    let expected = width * height * channels * 4;
+    if bytes.len() < pos + expected {
+        return Err(invalid_data("truncated pixel data"));
+    }
+
+    let float = |i: usize| {
+        let b = [
+            bytes[pos + 4 * i],
+            bytes[pos + 4 * i + 1],
+            bytes[pos + 4 * i + 2],
+            bytes[pos + 4 * i + 3],
+        ];
+        let value = if little_endian {
+            f32::from_le_bytes(b)
+        } else {
+            f32::from_be_bytes(b)
+        };
+        value as f64
+    };
+
+    // Rows are stored bottom to top.
+    let mut data = Vec::with_capacity(width * height);
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let i = (y * width + x) * channels;
+            data.push(if channels == 3 {
+                Vec3::new(float(i), float(i + 1), float(i + 2))
+            } else {
+                Vec3::new(float(i), float(i), float(i))
+            });
+        }
+    }
+
+    Ok(HdrImage::new(width, height, data))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_radiance_and_pfm() {
+        // 8x1 image with one run-length encoded scanline: 1.0 on the left half, 4.0 on
+        // the right, stored as mantissa 128 and exponents 129 and 131.
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        bytes.extend_from_slice(&[2, 2, 0, 8]);
+        for _ in 0..3 {
+            bytes.extend_from_slice(&[128 + 8, 128]);
+        }
+        bytes.extend_from_slice(&[128 + 4, 129, 128 + 4, 131]);
+
+        let image = parse_radiance(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (8, 1));
+        assert!((image.pixel(0, 0).x() - 128.5 / 128.0).abs() < 1e-12);
+        assert!((image.pixel(7, 0).z() - 4.0 * 128.5 / 128.0).abs() < 1e-12);
+
+        // 1x2 greyscale float map, little endian, bottom row first.
+        let mut bytes = b"Pf\n1 2\n-1.0\n".to_vec();
+        bytes.extend_from_slice(&0.25f32.to_le_bytes());
+        bytes.extend_from_slice(&2.0f32.to_le_bytes());
+
+        let image = parse_pfm(&bytes).unwrap();
+        assert_eq!(image.pixel(0, 0).y(), 2.0);
+        assert_eq!(image.pixel(0, 1).y(), 0.25);
+        assert!(parse_pfm(b"PF\n4 4\n-1.0\n").is_err());
+    }
+}
@@ -1,103 +1,117 @@
-use std::{fs::File, io::Read};
+use std::sync::Arc;
 
-use crate::{model::vec3::Vec3, util::rtweekend::clamp};
+use crate::model::{hit::HitRecord, vec3::Vec3};
 
-use super::texture::Texture;
+use super::{
+    image_file::Texels,
+    mipmap::{MipMap, TextureFilter, WrapMode},
+    texture::Texture,
+    texture_manager::SharedImage,
+};
 
+/// Texture read from an image file, which covers the unit square of texture
+/// coordinates once unless scaled and offset. Unless told otherwise it reads the
+/// nearest texel and stretches the edges of the image beyond it. Textures from a
+/// `TextureManager` share their image with the others of the same file.
 pub struct ImageTexture {
-    data: Vec<u8>,
-    width: i32,
-    height: i32,
-    bytes_per_scanline: i32,
+    image: Arc<SharedImage>,
+    wrap: WrapMode,
+    filter: TextureFilter,
+    scale: (f64, f64),
+    offset: (f64, f64),
 }
 
-const BYTES_PER_PIXEL: i32 = 3;
-
 impl ImageTexture {
-    pub fn new(filename: String) -> Self {
-        let mut components_per_pixel = BYTES_PER_PIXEL;
-
-        let mut f = File::open(filename).expect("file not found");
-        let mut contents = vec![];
-        f.read_to_end(&mut contents);
-
-        let mut x: i32 = 0;
-        let mut y: i32 = 0;
-        let mut comp: i32 = 0;
-        let img: *mut u8;
-
-        unsafe {
-            img = stb_image_rust::stbi_load_from_memory(
-                contents.as_mut_ptr(),
-                contents.len() as i32,
-                &mut x,
-                &mut y,
-                &mut comp,
-                components_per_pixel,
-            );
+    pub fn new(image: Arc<SharedImage>) -> Self {
+        Self {
+            image,
+            ..Default::default()
         }
+    }
 
-        let width = x;
-        let height = y;
-        let bytes_per_scanline = BYTES_PER_PIXEL * width;
-        let data = unsafe {
-            std::slice::from_raw_parts(img, (x * y * comp * components_per_pixel) as usize).to_vec()
-        };
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
 
-        unsafe {
-            stb_image_rust::c_runtime::free(img);
-        }
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
 
-        Self {
-            width,
-            height,
-            bytes_per_scanline,
-            data,
-        }
+    /// Fits `su` by `sv` copies of the image in the unit square.
+    pub fn with_scale(mut self, su: f64, sv: f64) -> Self {
+        self.scale = (su, sv);
+        self
+    }
+
+    /// Slides the image by `(ou, ov)` image widths and heights, applied after scaling.
+    pub fn with_offset(mut self, ou: f64, ov: f64) -> Self {
+        self.offset = (ou, ov);
+        self
     }
 }
 
 impl Default for ImageTexture {
     fn default() -> Self {
         Self {
-            data: Default::default(),
-            width: 0,
-            height: 0,
-            bytes_per_scanline: 0,
+            image: Arc::new(SharedImage::resident(MipMap::new(
+                0,
+                0,
+                Texels::Float(Vec::new()),
+            ))),
+            wrap: WrapMode::Clamp,
+            filter: TextureFilter::Nearest,
+            scale: (1.0, 1.0),
+            offset: (0.0, 0.0),
         }
     }
 }
 
-impl Texture for ImageTexture {
-    fn value(&self, u: f64, v: f64, p: &crate::model::vec3::Vec3) -> crate::model::vec3::Vec3 {
-        // If we have no texture data, then return solid cyan as a debugging aid.
-        if self.data.is_empty() {
-            return Vec3::new(0.0, 1.0, 1.0);
-        }
-
-        // Clamp input texture coordinates to [0, 1] x [1, 0]
-        let u = clamp(u, 0.0, 1.0);
-        let v = 1.0 - clamp(v, 0.0, 1.0);
+impl ImageTexture {
+    /// Image coordinates, from the top left corner, of the texture coordinates `(u, v)`.
+    fn st(&self, u: f64, v: f64) -> (f64, f64) {
+        (
+            u * self.scale.0 + self.offset.0,
+            1.0 - (v * self.scale.1 + self.offset.1),
+        )
+    }
 
-        let mut i = (u * self.width as f64) as i32;
-        let mut j = (v * self.height as f64) as i32;
+    /// Filtered RGBA texel at `(u, v)`, for a pixel across which the texture
+    /// coordinates change by `duv_dx` and `duv_dy`, if there is texture data.
+    fn lookup(&self, u: f64, v: f64, duv_dx: (f64, f64), duv_dy: (f64, f64)) -> Option<[f64; 4]> {
+        let mipmap = self.image.mipmap().filter(|mipmap| !mipmap.is_empty())?;
+        let image_derivative = |(du, dv): (f64, f64)| (du * self.scale.0, -dv * self.scale.1);
+        Some(mipmap.lookup(
+            self.filter,
+            self.wrap,
+            self.st(u, v),
+            image_derivative(duv_dx),
+            image_derivative(duv_dy),
+        ))
+    }
 
-        // Clamp integer mapping, since actual coordinates should be less than 1.0
-        if i >= self.width {
-            i = self.width - 1;
+    fn color(&self, u: f64, v: f64, duv_dx: (f64, f64), duv_dy: (f64, f64)) -> Vec3 {
+        match self.lookup(u, v, duv_dx, duv_dy) {
+            Some(texel) => Vec3::new(texel[0], texel[1], texel[2]),
+            // If we have no texture data, then return solid cyan as a debugging aid.
+            None => Vec3::new(0.0, 1.0, 1.0),
         }
+    }
 
-        if j >= self.height {
-            j = self.height - 1;
-        }
+    /// Opacity stored in the alpha channel, 1.0 for images without one.
+    pub fn alpha(&self, u: f64, v: f64) -> f64 {
+        self.lookup(u, v, (0.0, 0.0), (0.0, 0.0))
+            .map_or(1.0, |texel| texel[3])
+    }
+}
 
-        let color_scale = 1.0 / 255.0;
-        let pos = (j * self.bytes_per_scanline + i * BYTES_PER_PIXEL) as usize;
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Vec3) -> Vec3 {
+        self.color(u, v, (0.0, 0.0), (0.0, 0.0))
+    }
 
-        return Vec3::new(
-            color_scale * self.data[pos] as f64,
-            color_scale * self.data[pos + 1] as f64,
-            color_scale * self.data[pos + 2] as f64,
-        );
+    fn filtered_value(&self, rec: &HitRecord) -> Vec3 {
+        self.color(rec.u, rec.v, (rec.dudx, rec.dvdx), (rec.dudy, rec.dvdy))
     }
 }
@@ -0,0 +1,365 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    sync::OnceLock,
+};
+
+use super::hdr_image::HdrImage;
+
+/// How the values stored in an image relate to the values a texture returns.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ColorSpace {
+    /// Colours encoded with the sRGB transfer curve, as 8 and 16 bit colour images
+    /// almost always are. 16 bit images are linearised on load, 8 bit ones keep their
+    /// bytes and are linearised by table when looked up.
+    Srgb,
+    /// Values used as stored, for masks, normal maps and other data.
+    Linear,
+}
+
+/// Image decoded to RGBA texels, with rows from the top. Images without an alpha
+/// channel are opaque.
+pub struct ImageFile {
+    pub width: usize,
+    pub height: usize,
+    pub texels: Texels,
+}
+
+/// RGBA texels, kept at the precision of the file they came from: a byte per channel
+/// for 8 bit images, which are most of them, and a float for the others.
+pub enum Texels {
+    /// Bytes whose colour channels are sRGB encoded.
+    Srgb8(Vec<[u8; 4]>),
+    /// Bytes used as stored.
+    Linear8(Vec<[u8; 4]>),
+    /// Linear values of 16 bit and high dynamic range images.
+    Float(Vec<[f32; 4]>),
+}
+
+impl Texels {
+    pub fn len(&self) -> usize {
+        match self {
+            Texels::Srgb8(texels) | Texels::Linear8(texels) => texels.len(),
+            Texels::Float(texels) => texels.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes taken by each texel.
+    pub fn texel_size(&self) -> usize {
+        match self {
+            Texels::Srgb8(_) | Texels::Linear8(_) => std::mem::size_of::<[u8; 4]>(),
+            Texels::Float(_) => std::mem::size_of::<[f32; 4]>(),
+        }
+    }
+
+    /// Linear value of texel `i`.
+    pub fn get(&self, i: usize) -> [f32; 4] {
+        match self {
+            Texels::Srgb8(texels) => {
+                let [r, g, b, a] = texels[i];
+                let table = srgb_table();
+                [
+                    table[r as usize],
+                    table[g as usize],
+                    table[b as usize],
+                    a as f32 / 255.0,
+                ]
+            }
+            Texels::Linear8(texels) => texels[i].map(|c| c as f32 / 255.0),
+            Texels::Float(texels) => texels[i],
+        }
+    }
+
+    pub fn format(&self) -> TexelFormat {
+        match self {
+            Texels::Srgb8(_) => TexelFormat::Srgb8,
+            Texels::Linear8(_) => TexelFormat::Linear8,
+            Texels::Float(_) => TexelFormat::Float,
+        }
+    }
+
+    /// The `width` by `height` block at `(x, y)` of these texels, which hold rows of
+    /// `stride` texels.
+    pub fn crop(
+        &self,
+        stride: usize,
+        (x, y): (usize, usize),
+        (width, height): (usize, usize),
+    ) -> Texels {
+        fn rows<T: Copy>(
+            texels: &[T],
+            stride: usize,
+            x: usize,
+            y: usize,
+            width: usize,
+            height: usize,
+        ) -> Vec<T> {
+            (y..y + height)
+                .flat_map(|row| {
+                    texels[row * stride + x..row * stride + x + width]
+                        .iter()
+                        .copied()
+                })
+                .collect()
+        }
+        match self {
+            Texels::Srgb8(texels) => Texels::Srgb8(rows(texels, stride, x, y, width, height)),
+            Texels::Linear8(texels) => Texels::Linear8(rows(texels, stride, x, y, width, height)),
+            Texels::Float(texels) => Texels::Float(rows(texels, stride, x, y, width, height)),
+        }
+    }
+
+    /// The texels as they are stored, to be read back with `TexelFormat::read`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Texels::Srgb8(texels) | Texels::Linear8(texels) => texels.concat(),
+            Texels::Float(texels) => texels
+                .iter()
+                .flatten()
+                .flat_map(|c| c.to_le_bytes())
+                .collect(),
+        }
+    }
+
+    /// Texels stored the same way as these, holding the linear values `values`.
+    pub fn like(&self, values: Vec<[f32; 4]>) -> Texels {
+        let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        match self {
+            Texels::Srgb8(_) => Texels::Srgb8(
+                values
+                    .iter()
+                    .map(|&[r, g, b, a]| {
+                        [
+                            byte(linear_to_srgb(r)),
+                            byte(linear_to_srgb(g)),
+                            byte(linear_to_srgb(b)),
+                            byte(a),
+                        ]
+                    })
+                    .collect(),
+            ),
+            Texels::Linear8(_) => Texels::Linear8(values.iter().map(|v| v.map(byte)).collect()),
+            Texels::Float(_) => Texels::Float(values),
+        }
+    }
+}
+
+/// How `Texels` store their values.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TexelFormat {
+    Srgb8,
+    Linear8,
+    Float,
+}
+
+impl TexelFormat {
+    /// Texels from the bytes `Texels::to_bytes` wrote.
+    pub fn read(&self, bytes: &[u8]) -> Texels {
+        let quads = || bytes.chunks_exact(4).map(|b| [b[0], b[1], b[2], b[3]]);
+        match self {
+            TexelFormat::Srgb8 => Texels::Srgb8(quads().collect()),
+            TexelFormat::Linear8 => Texels::Linear8(quads().collect()),
+            TexelFormat::Float => {
+                let values: Vec<f32> = quads().map(f32::from_le_bytes).collect();
+                Texels::Float(
+                    values
+                        .chunks_exact(4)
+                        .map(|v| [v[0], v[1], v[2], v[3]])
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+impl ImageFile {
+    /// Loads a PNG, JPEG, TGA or BMP image, which is treated as `color_space`, or a
+    /// Radiance HDR or PFM image, which is linear already. 16 bit images keep their
+    /// precision.
+    pub fn load(filename: &str, color_space: ColorSpace) -> io::Result<ImageFile> {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let image = match extension.as_deref() {
+            Some("hdr") | Some("pic") | Some("pfm") => HdrImage::load(filename).map(|hdr| {
+                let texels = Texels::Float(
+                    hdr.data
+                        .iter()
+                        .map(|c| [c.x() as f32, c.y() as f32, c.z() as f32, 1.0])
+                        .collect(),
+                );
+                ImageFile {
+                    width: hdr.width,
+                    height: hdr.height,
+                    texels,
+                }
+            }),
+            _ => fs::read(filename).and_then(|bytes| ImageFile::decode(&bytes, color_space)),
+        };
+
+        image.map_err(|e| io::Error::new(e.kind(), format!("cannot load '{}': {}", filename, e)))
+    }
+
+    /// Decodes a PNG, JPEG, TGA or BMP image held in memory.
+    pub fn decode(bytes: &[u8], color_space: ColorSpace) -> io::Result<ImageFile> {
+        let len = i32::try_from(bytes.len()).map_err(|_| invalid_data("image file too large"))?;
+        let (mut width, mut height, mut channels) = (0, 0, 0);
+
+        // SAFETY: stb_image only reads `len` bytes from the buffer.
+        let known = unsafe {
+            stb_image_rust::stbi_info_from_memory(
+                bytes.as_ptr(),
+                len,
+                &mut width,
+                &mut height,
+                &mut channels,
+            )
+        };
+        if known == 0 {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "not a PNG, JPEG, TGA or BMP image",
+            ));
+        }
+        let texel_count = width as usize * height as usize;
+
+        // Every format is expanded to RGBA, with opaque alpha where there was none.
+        // SAFETY: a successful load returns `4 * width * height` values, which are
+        // copied out before being freed.
+        let texels = unsafe {
+            if stb_image_rust::stbi_is_16_bit_from_memory(bytes.as_ptr(), len) != 0 {
+                let data = stb_image_rust::stbi_load_16_from_memory(
+                    bytes.as_ptr(),
+                    len,
+                    &mut width,
+                    &mut height,
+                    &mut channels,
+                    4,
+                );
+                if data.is_null() {
+                    return Err(invalid_data("corrupt image data"));
+                }
+                let texels = std::slice::from_raw_parts(data, 4 * texel_count)
+                    .chunks_exact(4)
+                    .map(|v| {
+                        let v = [v[0], v[1], v[2], v[3]].map(|c| c as f32 / 65535.0);
+                        match color_space {
+                            ColorSpace::Srgb => [
+                                srgb_to_linear(v[0]),
+                                srgb_to_linear(v[1]),
+                                srgb_to_linear(v[2]),
+                                v[3],
+                            ],
+                            ColorSpace::Linear => v,
+                        }
+                    })
+                    .collect();
+                stb_image_rust::c_runtime::free(data as *mut u8);
+                Texels::Float(texels)
+            } else {
+                let data = stb_image_rust::stbi_load_from_memory(
+                    bytes.as_ptr() as *mut u8,
+                    len,
+                    &mut width,
+                    &mut height,
+                    &mut channels,
+                    4,
+                );
+                if data.is_null() {
+                    return Err(invalid_data("corrupt image data"));
+                }
+                let texels = std::slice::from_raw_parts(data, 4 * texel_count)
+                    .chunks_exact(4)
+                    .map(|v| [v[0], v[1], v[2], v[3]])
+                    .collect();
+                stb_image_rust::c_runtime::free(data);
+                match color_space {
+                    ColorSpace::Srgb => Texels::Srgb8(texels),
+                    ColorSpace::Linear => Texels::Linear8(texels),
+                }
+            }
+        };
+
+        Ok(ImageFile {
+            width: width as usize,
+            height: height as usize,
+            texels,
+        })
+    }
+}
+
+/// Linear values of the 256 sRGB encoded bytes.
+fn srgb_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)))
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::png::encode_png;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_linearises_colour_only() {
+        let png = encode_png(2, 1, &[188, 188, 188, 0, 255, 10]);
+
+        let color = ImageFile::decode(&png, ColorSpace::Srgb).unwrap();
+        assert_eq!((color.width, color.height), (2, 1));
+        assert_eq!(color.texels.texel_size(), 4);
+        assert!((color.texels.get(0)[0] - 0.5).abs() < 0.005);
+        assert_eq!(color.texels.get(0)[3], 1.0);
+        assert_eq!(color.texels.get(1)[1], 1.0);
+
+        let data = ImageFile::decode(&png, ColorSpace::Linear).unwrap();
+        assert_eq!(data.texels.get(0)[0], 188.0 / 255.0);
+        assert_eq!(data.texels.get(1)[2], 10.0 / 255.0);
+    }
+
+    #[test]
+    fn test_errors_name_the_problem() {
+        let error = ImageFile::decode(b"plain text", ColorSpace::Srgb)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+
+        // A PNG cut off after its header.
+        let png = encode_png(4, 4, &[0; 48]);
+        let error = ImageFile::decode(&png[..40], ColorSpace::Srgb)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let error = ImageFile::load("missing.png", ColorSpace::Srgb)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert!(error.to_string().contains("missing.png"));
+    }
+}
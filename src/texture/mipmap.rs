@@ -0,0 +1,562 @@
+use std::sync::Arc;
+
+use super::image_file::Texels;
+
+/// How texel coordinates outside the image fold back into it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WrapMode {
+    /// Tiles the image.
+    Repeat,
+    /// Tiles the image, flipping every other copy so that the edges match.
+    Mirror,
+    /// Extends the edge texels outwards.
+    Clamp,
+}
+
+impl WrapMode {
+    fn apply(&self, i: i64, size: usize) -> usize {
+        let n = size as i64;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                if i < n {
+                    i
+                } else {
+                    2 * n - 1 - i
+                }
+            }
+            WrapMode::Clamp => i.clamp(0, n - 1),
+        };
+        i as usize
+    }
+}
+
+/// How texels are combined into a texture value.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextureFilter {
+    /// The texel under the lookup point.
+    Nearest,
+    /// Linear interpolation between the four closest texels.
+    Bilinear,
+    /// Catmull-Rom spline through the sixteen closest texels, sharper than bilinear
+    /// when the texture is magnified.
+    Bicubic,
+    /// Bilinear lookups in the two mipmap levels whose texels are about as wide as the
+    /// pixel footprint, blended together.
+    Trilinear,
+    /// Gaussian weighted average over the elliptical footprint of the pixel (Heckbert,
+    /// 1989), which stays sharp along the ellipse where trilinear blurs at grazing
+    /// angles.
+    Ewa,
+}
+
+/// Footprints more elongated than this are widened, bounding the texels an EWA
+/// lookup reads at grazing angles.
+const MAX_ANISOTROPY: f64 = 8.0;
+
+/// Falloff of the EWA Gaussian, which reaches e^-2 of its peak at the ellipse.
+const EWA_ALPHA: f64 = 2.0;
+
+/// Levels are kept in square tiles of this many texels on a side, which a texture
+/// cache can drop and read back one at a time.
+pub const TILE_SIZE: usize = 64;
+
+/// Block of up to `TILE_SIZE` by `TILE_SIZE` texels of one level, rows from the top.
+pub struct Tile {
+    pub width: usize,
+    pub texels: Texels,
+}
+
+impl Tile {
+    /// Texel `(x, y)`, counted from the top left of the tile.
+    pub fn texel(&self, x: usize, y: usize) -> [f32; 4] {
+        self.texels.get(y * self.width + x)
+    }
+
+    pub fn memory(&self) -> usize {
+        self.texels.len() * self.texels.texel_size()
+    }
+}
+
+/// Keeps the tiles of a mipmap, numbered level by level and row by row within each.
+pub trait TileStore {
+    /// Texel `(x, y)` of tile `tile`, counted from the top left of the tile.
+    fn texel(&self, tile: usize, x: usize, y: usize) -> [f32; 4];
+}
+
+/// Tiles that all stay in memory.
+pub struct ResidentTiles(pub Vec<Tile>);
+
+impl TileStore for ResidentTiles {
+    fn texel(&self, tile: usize, x: usize, y: usize) -> [f32; 4] {
+        self.0[tile].texel(x, y)
+    }
+}
+
+/// Whole level of the pyramid, while it is being built.
+struct Image {
+    width: usize,
+    height: usize,
+    texels: Texels,
+}
+
+impl Image {
+    /// Half the size in each direction, every texel averaging a 2 by 2 block. The
+    /// last row or column of odd sizes is averaged with itself. The texels are
+    /// averaged linearly and stored the same way as this image's.
+    fn downsample(&self) -> Image {
+        let width = self.width.div_ceil(2);
+        let height = self.height.div_ceil(2);
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let sx = (2 * x + dx).min(self.width - 1);
+                    let sy = (2 * y + dy).min(self.height - 1);
+                    let texel = self.texels.get(sy * self.width + sx);
+                    for c in 0..4 {
+                        sum[c] += 0.25 * texel[c];
+                    }
+                }
+                texels.push(sum);
+            }
+        }
+
+        Image {
+            width,
+            height,
+            texels: self.texels.like(texels),
+        }
+    }
+
+    /// Cuts the image into tiles, row by row.
+    fn tiles(&self) -> impl Iterator<Item = Tile> + '_ {
+        let across = self.width.div_ceil(TILE_SIZE);
+        let down = self.height.div_ceil(TILE_SIZE);
+        (0..across * down).map(move |i| {
+            let (x, y) = ((i % across) * TILE_SIZE, (i / across) * TILE_SIZE);
+            let size = (
+                TILE_SIZE.min(self.width - x),
+                TILE_SIZE.min(self.height - y),
+            );
+            Tile {
+                width: size.0,
+                texels: self.texels.crop(self.width, (x, y), size),
+            }
+        })
+    }
+}
+
+/// Size of a level and where its tiles start.
+struct Level {
+    width: usize,
+    height: usize,
+    tiles_across: usize,
+    first_tile: usize,
+}
+
+/// RGBA image with its chain of ever smaller prefiltered copies, down to a single
+/// texel, for looking up the texture averaged over areas of any size. Texture
+/// coordinates `(s, t)` run over [0, 1] from the top left corner of the image, and
+/// beyond that fold back in as `wrap` says, so textures sharing an image may differ.
+/// Every level is stored in tiles, kept by a `TileStore`.
+pub struct MipMap {
+    levels: Vec<Level>,
+    memory: usize,
+    tiles: Arc<dyn TileStore + Sync + Send>,
+}
+
+impl MipMap {
+    /// `texels` holds the rows of the image from the top. The tiles stay in memory.
+    pub fn new(width: usize, height: usize, texels: Texels) -> Self {
+        Self::new_with_store(width, height, texels, |tiles| {
+            Arc::new(ResidentTiles(tiles))
+        })
+    }
+
+    /// Mipmap whose tiles are handed to `store`, which keeps them wherever it likes.
+    pub fn new_with_store(
+        width: usize,
+        height: usize,
+        texels: Texels,
+        store: impl FnOnce(Vec<Tile>) -> Arc<dyn TileStore + Sync + Send>,
+    ) -> Self {
+        assert_eq!(texels.len(), width * height, "texels do not fit the image");
+
+        let mut image = Image {
+            width,
+            height,
+            texels,
+        };
+        let mut levels = Vec::new();
+        let mut tiles = Vec::new();
+        loop {
+            levels.push(Level {
+                width: image.width,
+                height: image.height,
+                tiles_across: image.width.div_ceil(TILE_SIZE),
+                first_tile: tiles.len(),
+            });
+            tiles.extend(image.tiles());
+            if image.texels.is_empty() || (image.width == 1 && image.height == 1) {
+                break;
+            }
+            image = image.downsample();
+        }
+
+        Self {
+            levels,
+            memory: tiles.iter().map(Tile::memory).sum(),
+            tiles: store(tiles),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.memory == 0
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    /// Bytes taken by the texels of every level, were they all in memory.
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Texel `(x, y)` of `level`, counted from the top left and wrapped into it.
+    pub fn texel(&self, wrap: WrapMode, level: usize, x: i64, y: i64) -> [f64; 4] {
+        let level = &self.levels[level.min(self.levels.len() - 1)];
+        let x = wrap.apply(x, level.width);
+        let y = wrap.apply(y, level.height);
+        let tile = level.first_tile + (y / TILE_SIZE) * level.tiles_across + x / TILE_SIZE;
+        self.tiles
+            .texel(tile, x % TILE_SIZE, y % TILE_SIZE)
+            .map(|c| c as f64)
+    }
+
+    /// The texture at `(s, t)` as seen through a pixel across which the coordinates
+    /// change by `dst0` horizontally and `dst1` vertically. Filters that ignore the
+    /// footprint read the full resolution image.
+    pub fn lookup(
+        &self,
+        filter: TextureFilter,
+        wrap: WrapMode,
+        st: (f64, f64),
+        dst0: (f64, f64),
+        dst1: (f64, f64),
+    ) -> [f64; 4] {
+        match filter {
+            TextureFilter::Nearest => self.nearest(wrap, 0, st),
+            TextureFilter::Bilinear => self.bilinear(wrap, 0, st),
+            TextureFilter::Bicubic => self.bicubic(wrap, 0, st),
+            TextureFilter::Trilinear => {
+                let width = 2.0
+                    * dst0
+                        .0
+                        .abs()
+                        .max(dst0.1.abs())
+                        .max(dst1.0.abs())
+                        .max(dst1.1.abs());
+                self.between_levels(self.level_of_detail(width), |level| {
+                    self.bilinear(wrap, level, st)
+                })
+            }
+            TextureFilter::Ewa => {
+                let (mut major, mut minor) = (dst0, dst1);
+                if length(major) < length(minor) {
+                    (major, minor) = (minor, major);
+                }
+                let major_length = length(major);
+                let mut minor_length = length(minor);
+
+                if minor_length * MAX_ANISOTROPY < major_length && minor_length > 0.0 {
+                    let scale = major_length / (minor_length * MAX_ANISOTROPY);
+                    minor = (minor.0 * scale, minor.1 * scale);
+                    minor_length *= scale;
+                }
+                if minor_length == 0.0 {
+                    return self.bilinear(wrap, 0, st);
+                }
+
+                // The level whose texels match the short axis, so that the long one
+                // spans at most a few dozen of them.
+                self.between_levels(self.level_of_detail(minor_length), |level| {
+                    self.ewa(wrap, level, st, major, minor)
+                })
+            }
+        }
+    }
+
+    /// Fractional level whose texels are `width` wide in texture coordinates.
+    fn level_of_detail(&self, width: f64) -> f64 {
+        let resolution = self.width().max(self.height()) as f64;
+        (width * resolution).max(1e-12).log2()
+    }
+
+    /// Blends `lookup` at the levels on either side of `level`.
+    fn between_levels(&self, level: f64, lookup: impl Fn(usize) -> [f64; 4]) -> [f64; 4] {
+        let last = self.levels.len() - 1;
+        if level <= 0.0 {
+            return lookup(0);
+        }
+        if level >= last as f64 {
+            return lookup(last);
+        }
+
+        let below = level.floor() as usize;
+        let fraction = level - below as f64;
+        let (fine, coarse) = (lookup(below), lookup(below + 1));
+        std::array::from_fn(|c| (1.0 - fraction) * fine[c] + fraction * coarse[c])
+    }
+
+    fn nearest(&self, wrap: WrapMode, level: usize, (s, t): (f64, f64)) -> [f64; 4] {
+        let Level { width, height, .. } = self.levels[level];
+        self.texel(
+            wrap,
+            level,
+            (s * width as f64).floor() as i64,
+            (t * height as f64).floor() as i64,
+        )
+    }
+
+    fn bilinear(&self, wrap: WrapMode, level: usize, (s, t): (f64, f64)) -> [f64; 4] {
+        let Level { width, height, .. } = self.levels[level];
+        let x = s * width as f64 - 0.5;
+        let y = t * height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let weights = [
+            ((0, 0), (1.0 - fx) * (1.0 - fy)),
+            ((1, 0), fx * (1.0 - fy)),
+            ((0, 1), (1.0 - fx) * fy),
+            ((1, 1), fx * fy),
+        ];
+        let mut sum = [0.0; 4];
+        for ((dx, dy), weight) in weights {
+            let texel = self.texel(wrap, level, x0 + dx, y0 + dy);
+            for c in 0..4 {
+                sum[c] += weight * texel[c];
+            }
+        }
+        sum
+    }
+
+    fn bicubic(&self, wrap: WrapMode, level: usize, (s, t): (f64, f64)) -> [f64; 4] {
+        let Level { width, height, .. } = self.levels[level];
+        let x = s * width as f64 - 0.5;
+        let y = t * height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (wx, wy) = (catmull_rom_weights(x - x0), catmull_rom_weights(y - y0));
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let mut sum = [0.0; 4];
+        for (j, weight_y) in wy.iter().enumerate() {
+            for (i, weight_x) in wx.iter().enumerate() {
+                let texel = self.texel(wrap, level, x0 + i as i64 - 1, y0 + j as i64 - 1);
+                for c in 0..4 {
+                    sum[c] += weight_x * weight_y * texel[c];
+                }
+            }
+        }
+        // The spline overshoots next to sharp edges.
+        sum.map(|c| c.max(0.0))
+    }
+
+    /// Gaussian weighted average of the texels of `level` inside the ellipse with
+    /// axes `major` and `minor` centred on `(s, t)`.
+    fn ewa(
+        &self,
+        wrap: WrapMode,
+        level: usize,
+        (s, t): (f64, f64),
+        major: (f64, f64),
+        minor: (f64, f64),
+    ) -> [f64; 4] {
+        let Level { width, height, .. } = self.levels[level];
+        let (w, h) = (width as f64, height as f64);
+        let (s, t) = (s * w - 0.5, t * h - 0.5);
+        let (major, minor) = ((major.0 * w, major.1 * h), (minor.0 * w, minor.1 * h));
+
+        // Implicit ellipse a s^2 + b s t + c t^2 = 1, widened by a texel so that it
+        // never falls between texel centres.
+        let mut a = major.1 * major.1 + minor.1 * minor.1 + 1.0;
+        let mut b = -2.0 * (major.0 * major.1 + minor.0 * minor.1);
+        let mut c = major.0 * major.0 + minor.0 * minor.0 + 1.0;
+        let inverse_f = 1.0 / (a * c - b * b * 0.25);
+        a *= inverse_f;
+        b *= inverse_f;
+        c *= inverse_f;
+
+        // Bounding box of the ellipse.
+        let determinant = 4.0 * a * c - b * b;
+        let half_width = 2.0 * (determinant * c).sqrt() / determinant;
+        let half_height = 2.0 * (determinant * a).sqrt() / determinant;
+        let (s0, s1) = (
+            (s - half_width).ceil() as i64,
+            (s + half_width).floor() as i64,
+        );
+        let (t0, t1) = (
+            (t - half_height).ceil() as i64,
+            (t + half_height).floor() as i64,
+        );
+
+        let mut sum = [0.0; 4];
+        let mut weight_sum = 0.0;
+        for it in t0..=t1 {
+            let dt = it as f64 - t;
+            for is in s0..=s1 {
+                let ds = is as f64 - s;
+                let r2 = a * ds * ds + b * ds * dt + c * dt * dt;
+                if r2 < 1.0 {
+                    let weight = (-EWA_ALPHA * r2).exp() - (-EWA_ALPHA).exp();
+                    let texel = self.texel(wrap, level, is, it);
+                    for ch in 0..4 {
+                        sum[ch] += weight * texel[ch];
+                    }
+                    weight_sum += weight;
+                }
+            }
+        }
+
+        if weight_sum > 0.0 {
+            sum.map(|c| c / weight_sum)
+        } else {
+            self.bilinear(wrap, level, (s / w, t / h))
+        }
+    }
+}
+
+fn length((x, y): (f64, f64)) -> f64 {
+    (x * x + y * y).sqrt()
+}
+
+/// Weights of the four texels around a point `f` past the second one.
+fn catmull_rom_weights(f: f64) -> [f64; 4] {
+    let (f2, f3) = (f * f, f * f * f);
+    [
+        -0.5 * f3 + f2 - 0.5 * f,
+        1.5 * f3 - 2.5 * f2 + 1.0,
+        -1.5 * f3 + 2.0 * f2 + 0.5 * f,
+        0.5 * f3 - 0.5 * f2,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: usize, height: usize) -> MipMap {
+        let texels = (0..width * height)
+            .map(|i| [(i % width) as f32, (i / width) as f32, 0.0, 1.0])
+            .collect();
+        MipMap::new(width, height, Texels::Float(texels))
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        assert_eq!(WrapMode::Repeat.apply(-1, 4), 3);
+        assert_eq!(WrapMode::Repeat.apply(9, 4), 1);
+        assert_eq!(WrapMode::Mirror.apply(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.apply(5, 4), 2);
+        assert_eq!(WrapMode::Clamp.apply(-3, 4), 0);
+        assert_eq!(WrapMode::Clamp.apply(7, 4), 3);
+    }
+
+    #[test]
+    fn test_pyramid_keeps_the_mean() {
+        let mipmap = gradient(5, 3);
+        assert_eq!(mipmap.levels.len(), 4);
+        assert_eq!(mipmap.memory(), (15 + 6 + 2 + 1) * 16);
+        assert_eq!((mipmap.levels[1].width, mipmap.levels[1].height), (3, 2));
+
+        let top = mipmap.texel(WrapMode::Clamp, mipmap.levels.len() - 1, 0, 0);
+        assert!((top[3] - 1.0).abs() < 1e-6);
+        assert!(top[0] > 1.0 && top[0] < 3.0);
+    }
+
+    #[test]
+    fn test_filters_reproduce_a_linear_ramp() {
+        let mipmap = gradient(8, 8);
+        // Halfway between the centres of texels 2 and 3, away from the edges.
+        let st = (3.0 / 8.0, 4.5 / 8.0);
+        let none = (0.0, 0.0);
+
+        for filter in [TextureFilter::Bilinear, TextureFilter::Bicubic] {
+            let value = mipmap.lookup(filter, WrapMode::Clamp, st, none, none);
+            assert!((value[0] - 2.5).abs() < 1e-9);
+            assert!((value[1] - 4.0).abs() < 1e-9);
+        }
+        assert_eq!(
+            mipmap.lookup(TextureFilter::Nearest, WrapMode::Clamp, st, none, none)[0],
+            3.0
+        );
+    }
+
+    #[test]
+    fn test_wide_footprints_average_the_texture() {
+        let texels = (0..64)
+            .map(|i| {
+                let on = (i % 8 + i / 8) % 2 == 0;
+                [f32::from(u8::from(on)); 4]
+            })
+            .collect();
+        let mipmap = MipMap::new(8, 8, Texels::Float(texels));
+
+        // A footprint far larger than a texel blurs the checkerboard to grey, whether
+        // it is round or stretched along the diagonal.
+        for (dst0, dst1) in [((0.5, 0.0), (0.0, 0.5)), ((0.6, 0.3), (-0.05, 0.1))] {
+            for filter in [TextureFilter::Trilinear, TextureFilter::Ewa] {
+                let value = mipmap.lookup(filter, WrapMode::Repeat, (0.3, 0.6), dst0, dst1);
+                assert!(
+                    (value[0] - 0.5).abs() < 0.05,
+                    "{:?} gave {}",
+                    filter,
+                    value[0]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_levels_are_split_into_tiles() {
+        let mipmap = gradient(150, 70);
+        let level = &mipmap.levels[0];
+        assert_eq!((level.tiles_across, mipmap.levels[1].first_tile), (3, 6));
+        assert_eq!(
+            mipmap.texel(WrapMode::Clamp, 0, 149, 69),
+            [149.0, 69.0, 0.0, 1.0]
+        );
+        assert_eq!(
+            mipmap.texel(WrapMode::Clamp, 0, 64, 3),
+            [64.0, 3.0, 0.0, 1.0]
+        );
+        assert_eq!(
+            mipmap.memory(),
+            (150 * 70 + 75 * 35 + 38 * 18 + 19 * 9 + 10 * 5 + 5 * 3 + 3 * 2 + 2 + 1) * 16
+        );
+    }
+
+    #[test]
+    fn test_byte_levels_average_linear_values() {
+        // Black and white columns average to half the light, which sRGB encodes as
+        // 188 rather than 128.
+        let texels = (0..16)
+            .map(|i| if i % 2 == 0 { [0, 0, 0, 255] } else { [255; 4] })
+            .collect();
+        let mipmap = MipMap::new(4, 4, Texels::Srgb8(texels));
+        assert_eq!(mipmap.memory(), (16 + 4 + 1) * 4);
+
+        let grey = mipmap.texel(WrapMode::Clamp, 1, 0, 0);
+        assert!((grey[0] - 0.5).abs() < 0.005);
+        assert_eq!(grey[3], 1.0);
+    }
+}
@@ -1,6 +1,11 @@
+pub mod alpha_channel;
 pub mod checker;
+pub mod hdr_image;
 pub mod image;
+pub mod image_file;
+pub mod mipmap;
 pub mod noise;
 pub mod perlin;
 pub mod solid_color;
 pub mod texture;
+pub mod texture_manager;
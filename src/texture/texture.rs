@@ -1,7 +1,13 @@
-use crate::model::vec3::Vec3;
+use crate::model::{hit::HitRecord, vec3::Vec3};
 
 use Vec3 as Point3;
 
 pub trait Texture {
     fn value(&self, u: f64, v: f64, p: &Point3) -> Vec3;
+
+    /// Value seen over the footprint of the hit's pixel, for textures that can
+    /// filter away detail finer than that. Others look up the hit point itself.
+    fn filtered_value(&self, rec: &HitRecord) -> Vec3 {
+        self.value(rec.u, rec.v, &rec.p)
+    }
 }
@@ -0,0 +1,427 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock, RwLock, Weak,
+    },
+};
+
+use super::{
+    image::ImageTexture,
+    image_file::{ColorSpace, ImageFile, TexelFormat},
+    mipmap::{MipMap, ResidentTiles, Tile, TileStore},
+};
+
+/// Scratch files made by this process so far, which numbers the next one.
+static SCRATCH_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Image that any number of textures read, decoded with its mipmap when first
+/// looked up. The mipmap of a manager with a memory budget keeps its tiles in the
+/// manager's cache, which may drop them again while unused.
+pub struct SharedImage {
+    filename: String,
+    color_space: ColorSpace,
+    mipmap: OnceLock<Option<MipMap>>,
+    cache: Weak<ImageCache>,
+}
+
+impl SharedImage {
+    /// Image already in memory, which stays there.
+    pub fn resident(mipmap: MipMap) -> Self {
+        Self {
+            filename: String::new(),
+            color_space: ColorSpace::Linear,
+            mipmap: OnceLock::from(Some(mipmap)),
+            cache: Weak::new(),
+        }
+    }
+
+    /// The decoded image, or `None` if it cannot be loaded, which is reported once.
+    /// Other threads wanting the image wait for it rather than decode it too.
+    pub fn mipmap(&self) -> Option<&MipMap> {
+        self.mipmap
+            .get_or_init(|| match ImageFile::load(&self.filename, self.color_space) {
+                Ok(image) => Some(match self.cache.upgrade() {
+                    Some(cache) => cache.decoded(&self.filename, image),
+                    None => MipMap::new(image.width, image.height, image.texels),
+                }),
+                Err(error) => {
+                    eprintln!("\n{}", error);
+                    None
+                }
+            })
+            .as_ref()
+    }
+}
+
+/// Tile of a `PagedTiles`, if in memory, and when it was last read.
+struct TileSlot {
+    tile: RwLock<Option<Arc<Tile>>>,
+    last_use: AtomicU64,
+}
+
+/// Where a tile is in a scratch file, and how wide it is.
+struct Extent {
+    offset: u64,
+    length: usize,
+    width: usize,
+}
+
+/// Tiles of one image in a cache with a memory budget. All of them are written to a
+/// scratch file when the image is decoded, so that those dropped to make room for
+/// others can be read back when needed again.
+struct PagedTiles {
+    slots: Vec<TileSlot>,
+    extents: Vec<Extent>,
+    format: TexelFormat,
+    path: PathBuf,
+    file: Mutex<File>,
+    clock: Arc<AtomicU64>,
+    cache: Weak<ImageCache>,
+}
+
+impl PagedTiles {
+    /// Writes `tiles` to a new scratch file at `path`, returning it with the extent
+    /// of every tile.
+    fn write(tiles: &[Tile], path: &PathBuf) -> io::Result<(File, Vec<Extent>)> {
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut extents = Vec::with_capacity(tiles.len());
+        let mut offset = 0;
+        for tile in tiles {
+            let bytes = tile.texels.to_bytes();
+            file.write_all(&bytes)?;
+            extents.push(Extent {
+                offset,
+                length: bytes.len(),
+                width: tile.width,
+            });
+            offset += bytes.len() as u64;
+        }
+        Ok((file, extents))
+    }
+
+    /// Reads tile `index` back from the file, unless another thread already has.
+    fn page_in(&self, index: usize) -> Arc<Tile> {
+        let mut slot = self.slots[index].tile.write().unwrap();
+        if let Some(tile) = slot.as_ref() {
+            return tile.clone();
+        }
+        let Extent {
+            offset,
+            length,
+            width,
+        } = self.extents[index];
+        let mut bytes = vec![0; length];
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut bytes))
+            .unwrap_or_else(|error| panic!("cannot read back {}: {}", self.path.display(), error));
+        drop(file);
+
+        let tile = Arc::new(Tile {
+            width,
+            texels: self.format.read(&bytes),
+        });
+        *slot = Some(tile.clone());
+        drop(slot);
+        if let Some(cache) = self.cache.upgrade() {
+            cache.reloads.fetch_add(1, Ordering::Relaxed);
+            cache.loaded(tile.memory());
+        }
+        tile
+    }
+}
+
+impl TileStore for PagedTiles {
+    fn texel(&self, tile: usize, x: usize, y: usize) -> [f32; 4] {
+        let slot = &self.slots[tile];
+        // The clock only ticks when tiles are loaded, so reads rarely write here.
+        let now = self.clock.load(Ordering::Relaxed);
+        if slot.last_use.load(Ordering::Relaxed) != now {
+            slot.last_use.store(now, Ordering::Relaxed);
+        }
+        if let Some(tile) = slot.tile.read().unwrap().as_ref() {
+            return tile.texel(x, y);
+        }
+        self.page_in(tile).texel(x, y)
+    }
+}
+
+impl Drop for PagedTiles {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Decoded images of one manager, the tiles of theirs it keeps in memory, and what
+/// they cost.
+struct ImageCache {
+    images: Mutex<HashMap<(String, ColorSpace), Arc<SharedImage>>>,
+    paged: Mutex<Vec<Arc<PagedTiles>>>,
+    budget: Option<usize>,
+    resident_bytes: AtomicUsize,
+    reloads: AtomicUsize,
+    clock: Arc<AtomicU64>,
+}
+
+impl ImageCache {
+    /// Mipmap of the image decoded from `filename`, whose tiles are paged through a
+    /// scratch file if there is a budget. Without a budget, or if the file cannot be
+    /// written, they all stay in memory.
+    fn decoded(self: Arc<Self>, filename: &str, image: ImageFile) -> MipMap {
+        let Some(budget) = self.budget else {
+            let mipmap = MipMap::new(image.width, image.height, image.texels);
+            self.resident_bytes
+                .fetch_add(mipmap.memory(), Ordering::Relaxed);
+            return mipmap;
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "ray_trace_{}_{}.tiles",
+            std::process::id(),
+            SCRATCH_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let format = image.texels.format();
+        let mut paged = None;
+        let mipmap = MipMap::new_with_store(image.width, image.height, image.texels, |tiles| {
+            let (file, extents) = match PagedTiles::write(&tiles, &path) {
+                Ok(written) => written,
+                Err(error) => {
+                    eprintln!(
+                        "\ncannot page {} within {} bytes, keeping it in memory: {}",
+                        filename, budget, error
+                    );
+                    return Arc::new(ResidentTiles(tiles));
+                }
+            };
+            let now = self.clock.load(Ordering::Relaxed);
+            let tiles = Arc::new(PagedTiles {
+                slots: tiles
+                    .into_iter()
+                    .map(|tile| TileSlot {
+                        tile: RwLock::new(Some(Arc::new(tile))),
+                        last_use: AtomicU64::new(now),
+                    })
+                    .collect(),
+                extents,
+                format,
+                path,
+                file: Mutex::new(file),
+                clock: self.clock.clone(),
+                cache: Arc::downgrade(&self),
+            });
+            paged = Some(tiles.clone());
+            tiles
+        });
+        if let Some(paged) = paged {
+            self.paged.lock().unwrap().push(paged);
+        }
+        self.loaded(mipmap.memory());
+        mipmap
+    }
+
+    /// Accounts for tiles of `bytes` coming into memory. Past the budget, the tiles
+    /// read longest ago are dropped until an eighth of it is free again, so that a
+    /// full cache does not look for tiles to drop on every read.
+    fn loaded(&self, bytes: usize) {
+        self.clock.fetch_add(1, Ordering::Relaxed);
+        let mut resident = self.resident_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let Some(budget) = self.budget else {
+            return;
+        };
+        if resident <= budget {
+            return;
+        }
+
+        let paged = self.paged.lock().unwrap();
+        let mut candidates: Vec<(u64, &TileSlot)> = paged
+            .iter()
+            .flat_map(|tiles| tiles.slots.iter())
+            .map(|slot| (slot.last_use.load(Ordering::Relaxed), slot))
+            .collect();
+        candidates.sort_by_key(|&(last_use, _)| last_use);
+
+        for (_, slot) in candidates {
+            if resident <= budget - budget / 8 {
+                break;
+            }
+            // Tiles being read back right now are left alone.
+            let Ok(mut tile) = slot.tile.try_write() else {
+                continue;
+            };
+            if let Some(tile) = tile.take() {
+                resident = self
+                    .resident_bytes
+                    .fetch_sub(tile.memory(), Ordering::Relaxed)
+                    - tile.memory();
+            }
+        }
+    }
+}
+/// Hands out textures for image files, so that every file is decoded once however
+/// many textures use it, and only when a ray first reads it. With a memory budget,
+/// the tiles of the decoded mipmaps are kept in a cache that drops the least
+/// recently read ones to make room for others, so scenes whose textures do not fit
+/// in memory, or a single image larger than the budget, still render.
+pub struct TextureManager {
+    cache: Arc<ImageCache>,
+}
+
+/// Snapshot of the images a manager knows about.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TextureMemory {
+    /// Distinct image files handed out.
+    pub images: usize,
+    /// Those decoded so far.
+    pub decoded: usize,
+    /// Bytes their tiles in memory take right now.
+    pub bytes: usize,
+    /// Tiles read back after being dropped.
+    pub reloads: usize,
+}
+
+impl TextureManager {
+    pub fn new() -> Self {
+        Self {
+            cache: Arc::new(ImageCache {
+                images: Mutex::new(HashMap::new()),
+                paged: Mutex::new(Vec::new()),
+                budget: None,
+                resident_bytes: AtomicUsize::new(0),
+                reloads: AtomicUsize::new(0),
+                clock: Arc::new(AtomicU64::new(0)),
+            }),
+        }
+    }
+
+    /// Keeps the tiles of the decoded images within about `bytes` of memory.
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self {
+        Arc::get_mut(&mut self.cache)
+            .expect("the budget is set before handing out textures")
+            .budget = Some(bytes);
+        self
+    }
+
+    /// Texture showing `filename`, read as `color_space`, sharing its image with every
+    /// other texture of the same file and colour space. Errors reading the file are
+    /// reported when it is first looked up, and the texture then shows solid cyan.
+    pub fn texture(&self, filename: &str, color_space: ColorSpace) -> ImageTexture {
+        let mut images = self.cache.images.lock().unwrap();
+        let image = images
+            .entry((filename.to_string(), color_space))
+            .or_insert_with(|| {
+                Arc::new(SharedImage {
+                    filename: filename.to_string(),
+                    color_space,
+                    mipmap: OnceLock::new(),
+                    cache: Arc::downgrade(&self.cache),
+                })
+            });
+        ImageTexture::new(image.clone())
+    }
+
+    pub fn memory(&self) -> TextureMemory {
+        let images = self.cache.images.lock().unwrap();
+        TextureMemory {
+            images: images.len(),
+            decoded: images
+                .values()
+                .filter(|image| image.mipmap.get().is_some())
+                .count(),
+            bytes: self.cache.resident_bytes.load(Ordering::Relaxed),
+            reloads: self.cache.reloads.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for TextureManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::vec3::Vec3, texture::texture::Texture, util::png::write_png};
+
+    use super::*;
+
+    /// Writes a `size` square PNG of one grey level to the temporary directory.
+    fn grey_png(name: &str, size: usize, level: u8) -> String {
+        let path = std::env::temp_dir().join(format!("texture_manager_{}", name));
+        let filename = path.to_str().unwrap().to_string();
+        write_png(&filename, size, size, &vec![level; 3 * size * size]).unwrap();
+        filename
+    }
+
+    #[test]
+    fn test_images_are_shared_and_loaded_lazily() {
+        let filename = grey_png("shared.png", 4, 255);
+        let manager = TextureManager::new();
+        let a = manager.texture(&filename, ColorSpace::Srgb);
+        let b = manager.texture(&filename, ColorSpace::Srgb);
+        assert_eq!(manager.memory().images, 1);
+        assert_eq!(manager.memory().decoded, 0);
+
+        assert_eq!(a.value(0.5, 0.5, &Default::default()).x(), 1.0);
+        assert_eq!(b.value(0.2, 0.7, &Default::default()).y(), 1.0);
+        let memory = manager.memory();
+        assert_eq!(memory.decoded, 1);
+        assert_eq!(memory.bytes, (16 + 4 + 1) * 4);
+        fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_budget_drops_least_recently_read_tiles() {
+        let dark = grey_png("dark.png", 64, 0);
+        let light = grey_png("light.png", 64, 255);
+        // Room for about one 64 by 64 tile of 4 byte texels.
+        let budget = 5000 * 4;
+        let manager = TextureManager::new().with_memory_budget(budget);
+        let first = manager.texture(&dark, ColorSpace::Linear);
+        let second = manager.texture(&light, ColorSpace::Linear);
+
+        assert_eq!(first.value(0.5, 0.5, &Default::default()).x(), 0.0);
+        assert_eq!(second.value(0.5, 0.5, &Default::default()).x(), 1.0);
+        assert!(manager.memory().bytes <= budget);
+
+        // The dark image's tile is read back when needed again.
+        assert_eq!(first.value(0.5, 0.5, &Default::default()).x(), 0.0);
+        assert!(manager.memory().reloads >= 1);
+
+        let missing = manager.texture("missing.png", ColorSpace::Srgb);
+        assert_eq!(
+            missing.value(0.5, 0.5, &Default::default()),
+            Vec3::new(0.0, 1.0, 1.0)
+        );
+        fs::remove_file(dark).unwrap();
+        fs::remove_file(light).unwrap();
+    }
+
+    #[test]
+    fn test_image_over_budget_is_not_kept_whole() {
+        let filename = grey_png("large.png", 256, 255);
+        // A sixth of the 256 by 256 image, without its mipmap.
+        let budget = 256 * 256 * 4 / 6;
+        let manager = TextureManager::new().with_memory_budget(budget);
+        let texture = manager.texture(&filename, ColorSpace::Linear);
+
+        for pass in 0..2 {
+            for i in 0..16 {
+                let (u, v) = ((i % 4) as f64 / 4.0 + 0.1, (i / 4) as f64 / 4.0 + 0.1);
+                assert_eq!(texture.value(u, v, &Default::default()).x(), 1.0);
+                assert!(manager.memory().bytes <= budget, "pass {} read {}", pass, i);
+            }
+        }
+        assert!(manager.memory().reloads >= 16);
+        fs::remove_file(filename).unwrap();
+    }
+}
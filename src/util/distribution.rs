@@ -0,0 +1,128 @@
+/// Piecewise-constant distribution over [0, 1), with one bin per function value.
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    func_int: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: &[f64]) -> Self {
+        let n = func.len();
+        let func: Vec<f64> = func.iter().map(|f| f.max(0.0)).collect();
+
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f64;
+        }
+        let func_int = cdf[n];
+
+        // A function that is zero everywhere is sampled uniformly.
+        for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+            *c = if func_int > 0.0 {
+                *c / func_int
+            } else {
+                i as f64 / n as f64
+            };
+        }
+
+        Self {
+            func,
+            cdf,
+            func_int,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Maps the uniform `u` to a point in [0, 1), returning it with its density and bin.
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        // Last bin whose cdf does not exceed u.
+        let offset = self
+            .cdf
+            .partition_point(|c| *c <= u)
+            .saturating_sub(1)
+            .min(self.count() - 1);
+
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.0
+        };
+
+        let x = (offset as f64 + du) / self.count() as f64;
+        (x, self.pdf_of_bin(offset), offset)
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        let offset = ((x * self.count() as f64) as usize).min(self.count() - 1);
+        self.pdf_of_bin(offset)
+    }
+
+    fn pdf_of_bin(&self, offset: usize) -> f64 {
+        if self.func_int > 0.0 {
+            self.func[offset] / self.func_int
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Piecewise-constant distribution over [0, 1)², sampled by picking a row from the
+/// marginal distribution and then a column within that row.
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `func` holds `height` rows of `width` values.
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        let conditional: Vec<Distribution1D> = (0..height)
+            .map(|v| Distribution1D::new(&func[v * width..(v + 1) * width]))
+            .collect();
+        let row_integrals: Vec<f64> = conditional.iter().map(|d| d.func_int).collect();
+
+        Self {
+            conditional,
+            marginal: Distribution1D::new(&row_integrals),
+        }
+    }
+
+    /// Returns the sampled point (u along a row, v across rows) and its density.
+    pub fn sample_continuous(&self, u1: f64, u2: f64) -> (f64, f64, f64) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u2);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u1);
+
+        (u, v, pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = ((v * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        self.marginal.pdf(v) * self.conditional[row].pdf(u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distribution_follows_function() {
+        let distribution = Distribution1D::new(&[1.0, 0.0, 3.0]);
+
+        // The middle bin can never be chosen and the last one is three times as likely.
+        let (x, pdf, offset) = distribution.sample_continuous(0.5);
+        assert_eq!(offset, 2);
+        assert!((pdf - 2.25).abs() < 1e-12);
+        assert!((2.0 / 3.0..1.0).contains(&x));
+        assert_eq!(distribution.pdf(0.5), 0.0);
+        assert!((distribution.pdf(0.1) - 0.75).abs() < 1e-12);
+
+        let (_, pdf, offset) = distribution.sample_continuous(0.1);
+        assert_eq!(offset, 0);
+        assert!((pdf - 0.75).abs() < 1e-12);
+    }
+}
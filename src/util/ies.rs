@@ -0,0 +1,203 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+};
+
+/// Photometric profile read from an IES LM-63 file, describing how the intensity of a
+/// luminaire varies with direction. Angles follow type C photometry: the vertical angle
+/// is measured from the luminaire axis and the horizontal angle around it.
+pub struct IesProfile {
+    vertical_angles: Vec<f64>,
+    horizontal_angles: Vec<f64>,
+    /// Candela values, one row of vertical samples per horizontal angle.
+    candela: Vec<Vec<f64>>,
+    max_candela: f64,
+}
+
+impl IesProfile {
+    pub fn load(filename: &str) -> io::Result<IesProfile> {
+        IesProfile::parse(&fs::read_to_string(filename)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<IesProfile> {
+        // Keywords and free-form header lines run up to the TILT line.
+        let mut lines = text.lines();
+        let tilt = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => {
+                    break line.trim_start()["TILT=".len()..].trim().to_string()
+                }
+                Some(_) => continue,
+                None => return Err(invalid_data("missing TILT line")),
+            }
+        };
+
+        let rest: Vec<&str> = lines.collect();
+        let mut numbers = rest
+            .iter()
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                token
+                    .parse::<f64>()
+                    .map_err(|_| invalid_data(&format!("invalid number '{}'", token)))
+            });
+        let mut next = move || {
+            numbers
+                .next()
+                .unwrap_or(Err(invalid_data("unexpected end of data")))
+        };
+
+        match tilt.as_str() {
+            "NONE" => {}
+            "INCLUDE" => {
+                // Lamp-to-luminaire geometry, then pairs of tilt angles and factors.
+                next()?;
+                let count = next()? as usize;
+                for _ in 0..2 * count {
+                    next()?;
+                }
+            }
+            _ => {
+                return Err(io::Error::new(
+                    ErrorKind::Unsupported,
+                    format!("external tilt file '{}'", tilt),
+                ))
+            }
+        }
+
+        let _lamp_count = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometric_type = next()?;
+        // Units and luminous opening dimensions.
+        for _ in 0..4 {
+            next()?;
+        }
+        let ballast_factor = next()?;
+        let _ballast_lamp_factor = next()?;
+        let _input_watts = next()?;
+
+        if photometric_type != 1.0 {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "only type C photometry is supported",
+            ));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid_data("empty candela table"));
+        }
+
+        let vertical_angles = (0..vertical_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<f64>>>()?;
+        let horizontal_angles = (0..horizontal_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<f64>>>()?;
+        let mut candela = Vec::with_capacity(horizontal_count);
+        for _ in 0..horizontal_count {
+            candela.push(
+                (0..vertical_count)
+                    .map(|_| next().map(|c| c * multiplier * ballast_factor))
+                    .collect::<io::Result<Vec<f64>>>()?,
+            );
+        }
+
+        let max_candela = candela.iter().flatten().cloned().fold(0.0, f64::max);
+        if max_candela <= 0.0 {
+            return Err(invalid_data("profile emits no light"));
+        }
+
+        Ok(IesProfile {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+            max_candela,
+        })
+    }
+
+    /// Intensity relative to the brightest direction of the profile, for a direction at
+    /// `theta` degrees from the axis and `phi` degrees around it.
+    pub fn relative_intensity(&self, theta: f64, phi: f64) -> f64 {
+        // Fold the azimuth into the range covered by the table, following its symmetry.
+        let phi = phi.rem_euclid(360.0);
+        let last = *self.horizontal_angles.last().unwrap();
+        let phi = if last <= 0.0 {
+            0.0
+        } else if last <= 90.0 {
+            let phi = phi % 180.0;
+            if phi > 90.0 {
+                180.0 - phi
+            } else {
+                phi
+            }
+        } else if last <= 180.0 && phi > 180.0 {
+            360.0 - phi
+        } else {
+            phi
+        };
+
+        let (h0, h1, th) = bracket(&self.horizontal_angles, phi);
+        let vertical = |h: usize| {
+            if theta > *self.vertical_angles.last().unwrap() {
+                return 0.0;
+            }
+            let (v0, v1, tv) = bracket(&self.vertical_angles, theta);
+            (1.0 - tv) * self.candela[h][v0] + tv * self.candela[h][v1]
+        };
+
+        ((1.0 - th) * vertical(h0) + th * vertical(h1)) / self.max_candela
+    }
+}
+
+/// Indices of the samples around `x` in the ascending `angles`, with the interpolation weight.
+fn bracket(angles: &[f64], x: f64) -> (usize, usize, f64) {
+    if x <= angles[0] {
+        return (0, 0, 0.0);
+    }
+
+    for i in 1..angles.len() {
+        if x <= angles[i] {
+            let t = (x - angles[i - 1]) / (angles[i] - angles[i - 1]);
+            return (i - 1, i, t);
+        }
+    }
+
+    let last = angles.len() - 1;
+    (last, last, 0.0)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("IES profile: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = "IESNA:LM-63-2002
+[TEST] test
+TILT=NONE
+1 1000 2 3 2 1 2 0.1 0.1 0
+1.0 1.0 10
+0 45 90
+0 90
+100 50 0
+100, 100, 100
+";
+
+    #[test]
+    fn test_interpolates_vertical_and_horizontal_angles() {
+        let profile = IesProfile::parse(PROFILE).unwrap();
+
+        assert!((profile.relative_intensity(0.0, 0.0) - 1.0).abs() < 1e-9);
+        assert!((profile.relative_intensity(22.5, 0.0) - 0.75).abs() < 1e-9);
+        assert!((profile.relative_intensity(90.0, 45.0) - 0.5).abs() < 1e-9);
+        // Quadrant symmetry mirrors 135 degrees back onto 45 degrees.
+        assert!((profile.relative_intensity(90.0, 135.0) - 0.5).abs() < 1e-9);
+        assert_eq!(profile.relative_intensity(120.0, 0.0), 0.0);
+        assert!(IesProfile::parse("TILT=NONE\n1 1000").is_err());
+    }
+}
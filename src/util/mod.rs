@@ -1 +1,4 @@
+pub mod distribution;
+pub mod ies;
+pub mod png;
 pub mod rtweekend;
@@ -0,0 +1,131 @@
+use std::{fs::File, io, io::Write};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Largest payload of an uncompressed deflate block.
+const MAX_STORED_BLOCK: usize = 65535;
+
+/// Writes 8-bit RGB pixels, given top row first, as a PNG file. The image data is
+/// stored without compression, which keeps the encoder trivial at the cost of size.
+pub fn write_png(filename: &str, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    let mut file = File::create(filename)?;
+    file.write_all(&encode_png(width, height, rgb))
+}
+
+pub fn encode_png(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    assert_eq!(
+        rgb.len(),
+        3 * width * height,
+        "pixel data does not fit the image"
+    );
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, truecolour, deflate, adaptive filtering, no interlace.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // Every scanline starts with its filter type, here always none.
+    let mut scanlines = Vec::with_capacity((3 * width + 1) * height);
+    for row in rgb.chunks(3 * width.max(1)) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps `data` in a zlib stream made of stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = u8::from(blocks.peek().is_none());
+        let len = block.len() as u16;
+        out.push(last);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn test_encoded_png_reads_back() {
+        let (width, height) = (3, 2);
+        let rgb: Vec<u8> = (0..(3 * width * height) as u8).map(|i| 14 * i).collect();
+        let mut png = encode_png(width, height, &rgb);
+
+        let mut x = 0;
+        let mut y = 0;
+        let mut comp = 0;
+        let decoded = unsafe {
+            let img = stb_image_rust::stbi_load_from_memory(
+                png.as_mut_ptr(),
+                png.len() as i32,
+                &mut x,
+                &mut y,
+                &mut comp,
+                3,
+            );
+            assert!(!img.is_null());
+            let decoded = std::slice::from_raw_parts(img, rgb.len()).to_vec();
+            stb_image_rust::c_runtime::free(img);
+            decoded
+        };
+
+        assert_eq!((x, y), (width as i32, height as i32));
+        assert_eq!(decoded, rgb);
+    }
+}